@@ -0,0 +1,287 @@
+#![warn(missing_docs)]
+
+//! Derive macros for the payloads of the `kaantor` protocols.
+//!
+//! `Dispatch` generates, for a payload enum, a handler trait with one method
+//! per variant and the dispatch of the received messages to those methods.
+//! `PayloadDebug` formats the payloads the way the node logs print them.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DataEnum, DeriveInput, Error, Fields, Ident, LitStr,
+    Result, Variant,
+};
+
+/// Derives `kaantor::Dispatch` for a payload enum, together with the handler
+/// trait, named `<Enum>Handler` unless `#[payload(handler = "...")]` says
+/// otherwise. The trait has a `handle_<variant>` method for every variant,
+/// which receives the message, the neighbours and the fields of the variant.
+#[proc_macro_derive(Dispatch, attributes(payload))]
+pub fn derive_dispatch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_dispatch(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Debug` for a payload enum: the variant name in upper case words,
+/// followed by the fields separated by ` | `, e.g. `BACK CHILD | [A2, A3]`.
+/// The name can be changed with `#[payload(name = "...")]` on the variant,
+/// and a field marked with `#[payload(iter)]` prints its items separated by
+/// commas, like `kaantor::debug_iter`, e.g. `BACK CHILD | A2, A3`.
+#[proc_macro_derive(PayloadDebug, attributes(payload))]
+pub fn derive_payload_debug(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_debug(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn payload_enum(input: &DeriveInput) -> Result<&DataEnum> {
+    match &input.data {
+        Data::Enum(data) => Ok(data),
+        _ => Err(Error::new_spanned(
+            &input.ident,
+            "only enums can be used as payloads",
+        )),
+    }
+}
+
+/// Reads the value of the `#[payload(key = "...")]` attribute, if any.
+fn payload_attr(attrs: &[syn::Attribute], key: &str) -> Result<Option<LitStr>> {
+    let mut value = None;
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("payload")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                value = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown payload attribute"))
+            }
+        })?;
+    }
+
+    Ok(value)
+}
+
+/// Returns `true` if the field is marked with `#[payload(iter)]`.
+fn is_iter(field: &syn::Field) -> Result<bool> {
+    let mut iter = false;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("payload")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("iter") {
+                iter = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown payload attribute"))
+            }
+        })?;
+    }
+
+    Ok(iter)
+}
+
+/// Splits a camel case identifier in its words, e.g. `BackNotAChild`
+/// gives `Back`, `Not`, `A`, `Child`. A run of capitals is one word,
+/// e.g. `HTTPGet` gives `HTTP`, `Get`.
+fn words(ident: &Ident) -> Vec<String> {
+    let chars: Vec<_> = ident.to_string().chars().collect();
+    let mut words: Vec<String> = vec![];
+    for (i, &c) in chars.iter().enumerate() {
+        let prev_upper = i > 0 && chars[i - 1].is_uppercase();
+        let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+        let starts = c.is_uppercase() && (!prev_upper || next_lower);
+        match words.last_mut() {
+            Some(w) if !starts => w.push(c),
+            _ => words.push(c.to_string()),
+        }
+    }
+    words
+}
+
+fn method_name(variant: &Variant) -> Ident {
+    let words: Vec<_> = words(&variant.ident)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect();
+    format_ident!("handle_{}", words.join("_"))
+}
+
+/// Returns the bindings for the fields of the variant, and the pattern which
+/// binds them. The bindings are `__f0`, `__f1`, ... whatever the names of the
+/// fields, so they never shadow the identifiers of the generated code.
+fn bindings(variant: &Variant) -> (Vec<Ident>, TokenStream2) {
+    let ident = &variant.ident;
+    let names: Vec<_> = (0..variant.fields.len())
+        .map(|i| format_ident!("__f{}", i))
+        .collect();
+    match &variant.fields {
+        Fields::Unit => (names, quote!(Self::#ident)),
+        Fields::Unnamed(_) => (names.clone(), quote!(Self::#ident(#(#names),*))),
+        Fields::Named(fields) => {
+            let fields = fields.named.iter().map(|f| &f.ident);
+            let pat = quote!(Self::#ident { #(#fields: #names),* });
+            (names, pat)
+        }
+    }
+}
+
+/// Returns the names of the parameters of the handler method for the fields
+/// of the variant: the names of the fields, with a `_` appended to the ones
+/// which clash with the other parameters, or `v0`, `v1`, ... for a tuple.
+fn params(variant: &Variant) -> Vec<Ident> {
+    variant
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| match &f.ident {
+            Some(ident) if ident == "msg" || ident == "ns" => format_ident!("{}_", ident),
+            Some(ident) => ident.clone(),
+            None => Ident::new(&format!("v{i}"), Span::call_site()),
+        })
+        .collect()
+}
+
+fn expand_dispatch(input: DeriveInput) -> Result<TokenStream2> {
+    let data = payload_enum(&input)?;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "generic payloads cannot be dispatched",
+        ));
+    }
+
+    let vis = &input.vis;
+    let payload = &input.ident;
+    let handler = match payload_attr(&input.attrs, "handler")? {
+        Some(name) => name.parse::<Ident>()?,
+        None => format_ident!("{}Handler", payload),
+    };
+
+    let mut methods = vec![];
+    let mut arms = vec![];
+    for variant in &data.variants {
+        let method = method_name(variant);
+        let (names, pat) = bindings(variant);
+        let params = params(variant);
+        let types = variant.fields.iter().map(|f| &f.ty);
+        let doc = format!("Handles a `{}` message.", variant.ident);
+
+        methods.push(quote! {
+            #[doc = #doc]
+            #[allow(clippy::ptr_arg)]
+            fn #method(
+                &mut self,
+                msg: &::kaantor::protocol::Message<#payload>,
+                ns: impl Iterator<Item = ::kaantor::ActorId>,
+                #(#params: &#types),*
+            ) -> ::kaantor::ContinuationHandler<#payload>;
+        });
+        arms.push(quote! {
+            #pat => handler.#method(&msg, ns, #(#names),*),
+        });
+    }
+
+    let doc = format!("The handler of the `{payload}` messages, one method per variant.");
+    Ok(quote! {
+        #[doc = #doc]
+        #vis trait #handler {
+            #(#methods)*
+        }
+
+        impl<H> ::kaantor::Dispatch<H> for #payload
+        where
+            H: #handler,
+        {
+            fn dispatch(
+                handler: &mut H,
+                ns: impl Iterator<Item = ::kaantor::ActorId>,
+                msg: ::kaantor::protocol::Message<Self>,
+            ) -> ::kaantor::ContinuationHandler<Self> {
+                match msg.payload() {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+fn expand_debug(mut input: DeriveInput) -> Result<TokenStream2> {
+    let data = payload_enum(&input)?.clone();
+    let payload = &input.ident;
+
+    // The type parameters are formatted with `Debug` as well.
+    let params: Vec<_> = input
+        .generics
+        .type_params()
+        .map(|p| p.ident.clone())
+        .collect();
+    let clause = input.generics.make_where_clause();
+    for param in params {
+        clause
+            .predicates
+            .push(parse_quote!(#param: ::std::fmt::Debug));
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut arms = vec![];
+    for variant in &data.variants {
+        let name = match payload_attr(&variant.attrs, "name")? {
+            Some(name) => name.value(),
+            None => words(&variant.ident).join(" ").to_uppercase(),
+        };
+        let (names, pat) = bindings(variant);
+        let mut fields = vec![];
+        for (binding, field) in names.iter().zip(&variant.fields) {
+            fields.push(if is_iter(field)? {
+                quote! {
+                    f.write_str(" | ")?;
+                    for (i, item) in #binding.into_iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{:?}", item)?;
+                    }
+                }
+            } else {
+                quote!(write!(f, " | {:?}", #binding)?;)
+            });
+        }
+
+        arms.push(quote! {
+            #pat => {
+                f.write_str(#name)?;
+                #(#fields)*
+                Ok(())
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::std::fmt::Debug for #payload #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn words_() {
+        let words = |s: &str| words(&Ident::new(s, Span::call_site())).join(" ");
+        assert_eq!("Back Not A Child", words("BackNotAChild"));
+        assert_eq!("HTTP Get", words("HTTPGet"));
+        assert_eq!("Get HTTP", words("GetHTTP"));
+        assert_eq!("Ack2 PC", words("Ack2PC"));
+    }
+}
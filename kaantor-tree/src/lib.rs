@@ -31,6 +31,14 @@ where
         self.value.key()
     }
 
+    pub fn value(&self) -> &I {
+        &self.value
+    }
+
+    pub fn children(&self) -> &[I::Key] {
+        &self.children
+    }
+
     #[inline]
     pub fn is_leaf(&self) -> bool {
         self.children.is_empty()
@@ -60,6 +68,14 @@ where
     pub fn new(root: I::Key, nodes: Vec<KNode<I>>) -> Self {
         Self { root, nodes }
     }
+
+    pub fn root(&self) -> &I::Key {
+        &self.root
+    }
+
+    pub fn nodes(&self) -> &[KNode<I>] {
+        &self.nodes
+    }
 }
 
 impl<I> KTree<I>
@@ -4,11 +4,11 @@ use kaantor::{
     NodeActor, *,
 };
 use log::{debug, info};
-use std::fmt::Debug;
 
 // use ptree::*;
 
-#[derive(Clone)]
+#[derive(Clone, Dispatch, PayloadDebug)]
+#[payload(handler = "DFHandler")]
 enum Payload {
     Start,
     Go,
@@ -16,50 +16,14 @@ enum Payload {
     BackNotAChild,
 }
 
-impl Debug for Payload {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Start => write!(f, "START"),
-            Self::Go => write!(f, "GO"),
-            Self::BackChild => write!(f, "BACK CHILD"),
-            Self::BackNotAChild => write!(f, "BACK NOT A CHILD"),
-        }
-    }
-}
-
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum Parent {
     NoParent,
     Root,
     Parent(ActorId),
 }
 
-trait DFHandler {
-    fn handle_start(
-        &mut self,
-        msg: &protocol::Message<Payload>,
-        ns: impl Iterator<Item = ActorId>,
-    ) -> ContinuationHandler<Payload>;
-
-    fn handle_go(
-        &mut self,
-        msg: &protocol::Message<Payload>,
-        ns: impl Iterator<Item = ActorId>,
-    ) -> ContinuationHandler<Payload>;
-
-    fn handle_back_child(
-        &mut self,
-        msg: &protocol::Message<Payload>,
-        ns: impl Iterator<Item = ActorId>,
-    ) -> ContinuationHandler<Payload>;
-
-    fn handle_back_not_a_child(
-        &mut self,
-        msg: &protocol::Message<Payload>,
-        ns: impl Iterator<Item = ActorId>,
-    ) -> ContinuationHandler<Payload>;
-}
-
 trait DFSender {
     fn send_go_to_node(&self, sid: Session, to: ActorId) -> ContinuationHandler<Payload>;
 
@@ -232,12 +196,7 @@ impl ProtocolHandler for Handler {
         ns: impl Iterator<Item = ActorId>,
         msg: protocol::Message<Self::Payload>,
     ) -> ContinuationHandler<Self::Payload> {
-        match msg.payload() {
-            &Payload::Start => self.handle_start(&msg, ns),
-            &Payload::Go => self.handle_go(&msg, ns),
-            &Payload::BackChild => self.handle_back_child(&msg, ns),
-            &Payload::BackNotAChild => self.handle_back_not_a_child(&msg, ns),
-        }
+        Payload::dispatch(self, ns, msg)
     }
 }
 
@@ -267,7 +226,7 @@ fn main() {
 
         let msg = Builder::with_from_api()
             .with_to_actor(p1.aid())
-            .with_session(50.into())
+            .with_session(protocol::SessionAllocator::for_api().next_session())
             .with_payload(Payload::Start)
             .with_sender(p1.aid())
             .build();
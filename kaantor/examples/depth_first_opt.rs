@@ -32,6 +32,7 @@ impl Debug for Payload {
 }
 
 #[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum Parent {
     NoParent,
     Root,
@@ -221,10 +222,10 @@ impl ProtocolHandler for Handler {
         ns: impl Iterator<Item = ActorId>,
         msg: protocol::Message<Self::Payload>,
     ) -> ContinuationHandler<Self::Payload> {
-        match &msg.payload() {
-            &Payload::Start => self.handle_start(&msg, &mut ns.into_iter()),
-            &Payload::Go(visited) => self.handle_go(&msg, ns, visited.clone()),
-            &Payload::Back(visited) => self.handle_back(&msg, ns, visited.clone()),
+        match msg.payload() {
+            Payload::Start => self.handle_start(&msg, &mut ns.into_iter()),
+            Payload::Go(visited) => self.handle_go(&msg, ns, visited.clone()),
+            Payload::Back(visited) => self.handle_back(&msg, ns, visited.clone()),
         }
     }
 }
@@ -255,7 +256,7 @@ fn main() {
 
         let msg = Builder::with_from_api()
             .with_to_actor(p1.aid())
-            .with_session(50.into())
+            .with_session(protocol::SessionAllocator::for_api().next_session())
             .with_payload(Payload::Start)
             .with_sender(p1.aid())
             .build();
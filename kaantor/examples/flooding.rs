@@ -63,7 +63,7 @@ impl ProtocolHandler for MyHandler {
 
                 if !self.sessions.contains(session) {
                     //info!("Node {} received the payload", self.aid);
-                    self.sessions.push(session.clone());
+                    self.sessions.push(*session);
 
                     // forward the message to all neighbours excepts the source.
                     let sender: ActorId = msg.sender().as_aid();
@@ -104,7 +104,7 @@ fn main() {
 
         let msg = Builder::with_from_api()
             .with_to_actor(p1.aid())
-            .with_session(50.into())
+            .with_session(protocol::SessionAllocator::for_api().next_session())
             .with_payload(MyPayload::Start(999))
             .with_sender(p1.aid())
             .build();
@@ -42,6 +42,7 @@ impl Debug for Payload {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum Parent {
     NoParent,
     Root,
@@ -81,7 +82,7 @@ impl Handler {
             .iter()
             .find(|n| n.root == nid)
             .map(|n| {
-                if n.children.len() == 0 {
+                if n.children.is_empty() {
                     tb.add_empty_child(format!("{:?}", nid))
                 } else {
                     let tb = tb.begin_child(format!("{:?}", nid));
@@ -99,7 +100,7 @@ impl Handler {
     fn debug_spanning_tree(&self) {
         let mut tb = TreeBuilder::new("SPANNING-TREE".to_string());
         let tb = self.build_child(&mut tb, self.aid).build();
-        let _ = print_tree(&tb).unwrap();
+        print_tree(&tb).unwrap();
     }
 
     /// The node received a START. It marks itself as ROOT, sends a GO message to all its
@@ -111,7 +112,7 @@ impl Handler {
     ) -> ContinuationHandler<Payload> {
         self.parent = Parent::Root;
         self.exp_messages = ns.count();
-        let session = msg.session().clone();
+        let session = *msg.session();
 
         self.send_go_to_all_except(session, vec![])
     }
@@ -133,7 +134,7 @@ impl Handler {
                     // Continue the discovery of the spanning tree by sending
                     // a GO message to all the neighbours except the one that
                     // sent us the GO.
-                    self.send_go_to_all_except(session.clone(), vec![sender])
+                    self.send_go_to_all_except(*session, vec![sender])
                 } else {
                     // Finalize the spanning tree search for this node.
                     // Send back_child to the node that sent us the GO meesage.
@@ -277,11 +278,11 @@ impl ProtocolHandler for Handler {
         ns: impl Iterator<Item = ActorId>,
         msg: protocol::Message<Self::Payload>,
     ) -> ContinuationHandler<Self::Payload> {
-        match &msg.payload() {
-            &Payload::Start => self.handle_start(&msg, ns),
-            &Payload::Go => self.handle_go(&msg, ns),
-            &Payload::BackNoChild => self.handle_back_no_child(&msg, ns),
-            &Payload::BackChild(nodes) => self.handle_back_child(&msg, nodes.clone(), ns),
+        match msg.payload() {
+            Payload::Start => self.handle_start(&msg, ns),
+            Payload::Go => self.handle_go(&msg, ns),
+            Payload::BackNoChild => self.handle_back_no_child(&msg, ns),
+            Payload::BackChild(nodes) => self.handle_back_child(&msg, nodes.clone(), ns),
         }
     }
 }
@@ -312,7 +313,7 @@ fn main() {
 
         let msg = Builder::with_from_api()
             .with_to_actor(p1.aid())
-            .with_session(50.into())
+            .with_session(protocol::SessionAllocator::for_api().next_session())
             .with_payload(Payload::Start)
             .with_sender(p1.aid())
             .build();
@@ -0,0 +1,128 @@
+//! The flooding algorithm where every node runs in its own process.
+//!
+//! Start each node in a different terminal, the node 1 starts the flooding
+//! once it is connected to its neighbours:
+//!
+//! RUST_LOG=info cargo run --features tcp --example tcp_flooding -- 5
+//! RUST_LOG=info cargo run --features tcp --example tcp_flooding -- 4
+//! ...
+//! RUST_LOG=info cargo run --features tcp --example tcp_flooding -- 1
+
+use actix::prelude::*;
+use kaantor::{
+    protocol::{Builder, Session, SessionAllocator},
+    transport::tcp,
+    NodeActor, *,
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum MyPayload {
+    Start(usize),
+    Forward(usize),
+}
+
+struct MyHandler {
+    aid: ActorId,
+    sessions: Vec<Session>,
+}
+
+impl MyHandler {
+    pub fn build(aid: ActorId) -> Node<NodeActor<Self>, MyPayload> {
+        let h = Self {
+            aid,
+            sessions: vec![],
+        };
+        NodeActor::build(h)
+    }
+}
+
+impl ProtocolHandler for MyHandler {
+    type Payload = MyPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: protocol::Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+
+        match msg.payload() {
+            MyPayload::Start(value) => {
+                self.sessions.push(session);
+
+                let msg = Builder::with_from_actor(self.aid)
+                    .with_to_all_actors()
+                    .with_session(session)
+                    .with_payload(MyPayload::Forward(*value))
+                    .with_sender(self.aid)
+                    .build();
+
+                ContinuationHandler::SendToAllNodes(msg)
+            }
+            MyPayload::Forward(value) if !self.sessions.contains(&session) => {
+                println!("Node {:?} received the payload {}", self.aid, value);
+                self.sessions.push(session);
+
+                let sender: ActorId = msg.sender().as_aid();
+                let msg = Builder::with_message(msg).with_sender(self.aid).build();
+                ContinuationHandler::SendToAllNodesExcept(msg, vec![sender])
+            }
+            MyPayload::Forward(_) => ContinuationHandler::Done,
+        }
+    }
+}
+
+fn config() -> tcp::Config {
+    (1..=5)
+        .fold(tcp::Config::new(), |cfg, i| {
+            let addr = format!("127.0.0.1:{}", 7000 + i).parse().unwrap();
+            cfg.with_node(i.into(), addr)
+        })
+        .with_edge(1.into(), 2.into())
+        .with_edge(1.into(), 3.into())
+        .with_edge(2.into(), 4.into())
+        .with_edge(4.into(), 5.into())
+        .with_edge(3.into(), 5.into())
+}
+
+fn main() {
+    env_logger::init();
+
+    let aid: usize = std::env::args()
+        .nth(1)
+        .and_then(|a| a.parse().ok())
+        .expect("usage: tcp_flooding <node id between 1 and 5>");
+    debug!("Starting the example TCP FLOODING for node {}", aid);
+
+    let sys = System::new();
+    sys.block_on(async {
+        let mut node = MyHandler::build(aid.into());
+        let addr = tcp::join(&mut node, &config()).await.unwrap();
+        println!("Node {:?} listens at {}", node.aid(), addr);
+
+        if aid == 1 {
+            // The session carries the identifier of the node, so it is unique
+            // even if several processes start a flooding at the same time.
+            let session = SessionAllocator::new(node.aid()).next_session();
+            let msg = Builder::with_from_api()
+                .with_to_actor(node.aid())
+                .with_session(session)
+                .with_payload(MyPayload::Start(999))
+                .with_sender(node.aid())
+                .build();
+
+            let _ = node.send(msg).await;
+        }
+
+        actix_rt::time::sleep(Duration::from_secs(5)).await;
+    });
+
+    println!("Finished the test");
+}
@@ -0,0 +1,150 @@
+//! Measures the time taken by a number of ping-pong round trips between two
+//! nodes, for each of the transports: actix recipients, tokio channels,
+//! Unix domain sockets and TCP.
+//!
+//! cargo run --release --features channel,tcp,uds --example transport_overhead
+
+use actix::prelude::*;
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    StreamExt,
+};
+use kaantor::{
+    protocol::Builder,
+    transport::{channel, tcp, uds},
+    NodeActor, *,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const ROUND_TRIPS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Payload {
+    Start,
+    Ping(usize),
+    Pong(usize),
+}
+
+struct PingHandler {
+    aid: ActorId,
+    done: UnboundedSender<()>,
+}
+
+impl PingHandler {
+    fn send(
+        &self,
+        msg: &protocol::Message<Payload>,
+        to: ActorId,
+        payload: Payload,
+    ) -> ContinuationHandler<Payload> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(*msg.session())
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToNode(to, msg)
+    }
+}
+
+impl ProtocolHandler for PingHandler {
+    type Payload = Payload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        mut ns: impl Iterator<Item = ActorId>,
+        msg: protocol::Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let sender = msg.sender().as_aid();
+
+        match msg.payload() {
+            Payload::Start => self.send(&msg, ns.next().unwrap(), Payload::Ping(1)),
+            Payload::Ping(n) => self.send(&msg, sender, Payload::Pong(*n)),
+            Payload::Pong(n) if *n < ROUND_TRIPS => self.send(&msg, sender, Payload::Ping(n + 1)),
+            Payload::Pong(_) => {
+                let _ = self.done.unbounded_send(());
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+type PingNode = NodeHandler<PingHandler>;
+
+fn build_pair(done: &UnboundedSender<()>) -> (PingNode, PingNode) {
+    let build = |aid: usize| {
+        NodeActor::build(PingHandler {
+            aid: aid.into(),
+            done: done.clone(),
+        })
+    };
+    (build(1), build(2))
+}
+
+async fn measure(name: &str, n1: &mut PingNode, done: &mut (impl StreamExt<Item = ()> + Unpin)) {
+    let msg = Builder::with_from_api()
+        .with_to_actor(n1.aid())
+        .with_session(protocol::SessionAllocator::for_api().next_session())
+        .with_payload(Payload::Start)
+        .with_sender(n1.aid())
+        .build();
+
+    let start = Instant::now();
+    n1.send(msg).await.unwrap();
+    done.next().await;
+    let elapsed = start.elapsed();
+
+    println!(
+        "{:<8} {:>10.3?} total {:>10.3?} per round trip",
+        name,
+        elapsed,
+        elapsed / ROUND_TRIPS as u32
+    );
+}
+
+fn main() {
+    env_logger::init();
+
+    let sys = System::new();
+    sys.block_on(async {
+        let (tx, mut done) = unbounded();
+
+        let (mut n1, mut n2) = build_pair(&tx);
+        add_edge(&mut n1, &mut n2).await;
+        measure("actix", &mut n1, &mut done).await;
+
+        let (mut n1, mut n2) = build_pair(&tx);
+        channel::add_edge(&mut n1, &mut n2).await;
+        measure("channel", &mut n1, &mut done).await;
+
+        let (mut n1, mut n2) = build_pair(&tx);
+        let dir = std::env::temp_dir();
+        let cfg = uds::Config::new()
+            .with_node(n1.aid(), dir.join("kaantor-overhead-1.sock"))
+            .with_node(n2.aid(), dir.join("kaantor-overhead-2.sock"))
+            .with_edge(n1.aid(), n2.aid());
+        uds::listen(&n1, cfg.addr(n1.aid()).unwrap()).await.unwrap();
+        uds::join(&mut n2, &cfg).await.unwrap();
+        uds::connect_neighbours(&mut n1, &cfg).await.unwrap();
+        measure("uds", &mut n1, &mut done).await;
+
+        let (mut n1, mut n2) = build_pair(&tx);
+        let localhost = "127.0.0.1:0".parse().unwrap();
+        let a1 = tcp::listen(&n1, localhost).await.unwrap();
+        let a2 = tcp::listen(&n2, localhost).await.unwrap();
+        let cfg = tcp::Config::new()
+            .with_node(n1.aid(), a1)
+            .with_node(n2.aid(), a2)
+            .with_edge(n1.aid(), n2.aid());
+        tcp::connect_neighbours(&mut n1, &cfg).await.unwrap();
+        tcp::connect_neighbours(&mut n2, &cfg).await.unwrap();
+        measure("tcp", &mut n1, &mut done).await;
+
+        actix_rt::time::sleep(Duration::from_millis(10)).await;
+    });
+}
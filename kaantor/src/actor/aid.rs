@@ -1,7 +1,8 @@
 use std::fmt::Debug;
 
 /// The identifier for an actor
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActorId(usize);
 
 impl Debug for ActorId {
@@ -0,0 +1,315 @@
+//! Node actors for the handlers which process the messages asynchronously.
+
+use super::dispatcher::Dispatcher;
+use crate::{
+    graph::GraphMsg, node::Builder as NBuilder, protocol::Message as PMsg, ActorId,
+    ContinuationHandler, Node,
+};
+use actix::prelude::*;
+use futures::{future::LocalBoxFuture, FutureExt};
+use log::error;
+use std::{collections::VecDeque, fmt::Debug, panic::AssertUnwindSafe};
+
+type GMsg<P> = GraphMsg<PMsg<P>>;
+
+/// The future returned by an `AsyncProtocolHandler`. It gives back the handler
+/// together with the continuation for the processed message.
+pub type AsyncReceive<H> =
+    LocalBoxFuture<'static, (H, ContinuationHandler<<H as AsyncProtocolHandler>::Payload>)>;
+
+/// The asynchronous counterpart of `ProtocolHandler`, for the handlers which
+/// need to await storage, timers or other services while processing a message.
+///
+/// The handler is moved into the future returned by `receive` and it is handed
+/// back once the future completes. A node processes its messages one at a time:
+/// the next message is not delivered before the future of the previous one
+/// completes, while the other nodes of the arbiter keep running. The deferred
+/// messages are offered again as with `NodeActor`. If the future panics, the
+/// handler is lost with it and the node stops.
+pub trait AsyncProtocolHandler: Sized + 'static {
+    /// The type of payload for the messages.
+    type Payload: Send;
+
+    /// Returns the `ActorId` for the current handler.
+    fn aid(&self) -> ActorId;
+
+    /// Processes the received message.
+    fn receive(self, neighbours: Vec<ActorId>, msg: PMsg<Self::Payload>) -> AsyncReceive<Self>;
+}
+
+/// Represents an actor for a node of the graph driven by an asynchronous handler.
+pub struct AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler,
+{
+    dispatcher: Dispatcher<H::Payload>,
+    // The handler is `None` while a message is processed,
+    // and once it was lost in a panic.
+    ph: Option<H>,
+}
+
+impl<H> AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler,
+    <H as AsyncProtocolHandler>::Payload: Debug + Clone,
+{
+    fn new(ph: H) -> Self {
+        Self {
+            dispatcher: Dispatcher::new(ph.aid()),
+            ph: Some(ph),
+        }
+    }
+}
+
+impl<H> Actor for AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler + Unpin,
+{
+    type Context = ::actix::Context<Self>;
+}
+
+impl<H> Handler<GMsg<H::Payload>> for AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler + Unpin,
+    <H as AsyncProtocolHandler>::Payload: Debug + Clone,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: GMsg<H::Payload>, _ctx: &mut Self::Context) -> Self::Result {
+        self.dispatcher.handle_graph_msg(msg);
+    }
+}
+
+impl<H> Handler<PMsg<H::Payload>> for AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler + Unpin,
+    <H as AsyncProtocolHandler>::Payload: Debug + Clone,
+{
+    type Result = ();
+
+    fn handle(&mut self, mut msg: PMsg<H::Payload>, ctx: &mut Context<Self>) {
+        self.dispatcher.recv(&mut msg);
+        self.offer(msg, VecDeque::new(), false, ctx);
+    }
+}
+
+impl<H> AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler + Unpin,
+    <H as AsyncProtocolHandler>::Payload: Debug + Clone,
+{
+    /// Hands the message to the handler, then the `rest` of the messages one at
+    /// a time. Once they are all processed, if one of them changed the state of
+    /// the node, the deferred messages are offered again the same way.
+    fn offer(
+        &mut self,
+        msg: PMsg<H::Payload>,
+        mut rest: VecDeque<PMsg<H::Payload>>,
+        changed: bool,
+        ctx: &mut Context<Self>,
+    ) {
+        // `wait` holds back the mailbox until the future completes,
+        // so the handler is back before the next message, unless it panicked.
+        let aid = self.dispatcher.aid();
+        let Some(ph) = self.ph.take() else {
+            error!("ASYN | on {:?} | no handler, stop", aid);
+            ctx.stop();
+            return;
+        };
+        let ns = self.dispatcher.neighbours().collect();
+        let fut = AssertUnwindSafe(ph.receive(ns, msg))
+            .catch_unwind()
+            .into_actor(self)
+            .map(move |res, act, ctx| match res {
+                Ok((ph, res)) => {
+                    act.ph = Some(ph);
+                    let changed = changed || !res.is_deferred();
+                    act.dispatcher.dispatch(res, ctx);
+                    // The asynchronous handlers have no callback for the errors,
+                    // which are only logged and traced.
+                    act.dispatcher.take_errors();
+
+                    if rest.is_empty() && changed {
+                        rest = act.dispatcher.take_deferred().into();
+                        if let Some(next) = rest.pop_front() {
+                            act.offer(next, rest, false, ctx);
+                        }
+                    } else if let Some(next) = rest.pop_front() {
+                        act.offer(next, rest, changed, ctx);
+                    }
+                }
+                Err(_) => {
+                    error!("ASYN | on {:?} | the handler panicked, stop", aid);
+                    ctx.stop();
+                }
+            });
+
+        ctx.wait(fut);
+    }
+}
+
+impl<H> AsyncNodeActor<H>
+where
+    H: AsyncProtocolHandler + Unpin,
+    <H as AsyncProtocolHandler>::Payload: Debug + Clone,
+{
+    /// Builds a new node actor.
+    pub fn build(ph: H) -> AsyncNodeHandler<H> {
+        let aid = ph.aid();
+        let actor = AsyncNodeActor::new(ph);
+        let addr = AsyncNodeActor::start(actor);
+
+        NBuilder::from_aid(aid).with_addr(addr).build()
+    }
+}
+
+/// Convenience type
+pub type AsyncNodeHandler<T> = Node<AsyncNodeActor<T>, <T as AsyncProtocolHandler>::Payload>;
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, SessionAllocator},
+    };
+    use futures::{channel::mpsc::UnboundedSender, FutureExt, StreamExt};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    enum Payload {
+        Start(u64),
+        Record(u64),
+        Late(u64),
+        Panic,
+    }
+
+    /// Waits before forwarding the values, the longest waits first.
+    struct Delay {
+        aid: ActorId,
+        records: Vec<u64>,
+        results: UnboundedSender<Vec<u64>>,
+    }
+
+    impl AsyncProtocolHandler for Delay {
+        type Payload = Payload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(mut self, ns: Vec<ActorId>, msg: PMsg<Self::Payload>) -> AsyncReceive<Self> {
+            async move {
+                let res = match msg.payload() {
+                    Payload::Start(v) => {
+                        actix_rt::time::sleep(Duration::from_millis(v * 10)).await;
+                        let fwd = Builder::with_from_actor(self.aid)
+                            .with_to_actor(ns[0])
+                            .with_session(*msg.session())
+                            .with_payload(Payload::Record(*v))
+                            .with_sender(self.aid)
+                            .build();
+                        ContinuationHandler::SendToNode(ns[0], fwd)
+                    }
+                    Payload::Late(_) if self.records.is_empty() => ContinuationHandler::Defer(msg),
+                    Payload::Record(v) | Payload::Late(v) => {
+                        self.records.push(*v);
+                        if self.records.len() == 3 {
+                            let _ = self.results.unbounded_send(self.records.clone());
+                        }
+                        ContinuationHandler::Done
+                    }
+                    Payload::Panic => panic!("the handler panics"),
+                };
+                (self, res)
+            }
+            .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn sequenced_per_node() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=2)
+            .map(|i| {
+                AsyncNodeActor::build(Delay {
+                    aid: i.into(),
+                    records: vec![],
+                    results: tx.clone(),
+                })
+            })
+            .collect();
+
+        let (left, right) = nodes.split_at_mut(1);
+        add_edge(&mut left[0], &mut right[0]).await;
+
+        let session = SessionAllocator::for_api().next_session();
+        for v in [3, 2, 1] {
+            let msg = Builder::with_from_api()
+                .with_to_actor(1.into())
+                .with_session(session)
+                .with_payload(Payload::Start(v))
+                .with_sender(1.into())
+                .build();
+            nodes[0].do_send(msg);
+        }
+
+        let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![3, 2, 1], res);
+    }
+
+    #[actix_rt::test]
+    async fn reoffers_deferred() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut node = AsyncNodeActor::build(Delay {
+            aid: 1.into(),
+            records: vec![],
+            results: tx,
+        });
+
+        // The late value is deferred until a value was recorded.
+        let session = SessionAllocator::for_api().next_session();
+        for payload in [Payload::Late(1), Payload::Record(2), Payload::Record(3)] {
+            let msg = Builder::with_from_api()
+                .with_to_actor(1.into())
+                .with_session(session)
+                .with_payload(payload)
+                .with_sender(1.into())
+                .build();
+            node.do_send(msg);
+        }
+
+        let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![2, 1, 3], res);
+    }
+
+    #[actix_rt::test]
+    async fn stops_on_panic() {
+        let (tx, _rx) = futures::channel::mpsc::unbounded();
+        let mut node = AsyncNodeActor::build(Delay {
+            aid: 1.into(),
+            records: vec![],
+            results: tx,
+        });
+        let session = SessionAllocator::for_api().next_session();
+        let msg = |payload| {
+            Builder::with_from_api()
+                .with_to_actor(1.into())
+                .with_session(session)
+                .with_payload(payload)
+                .with_sender(1.into())
+                .build()
+        };
+
+        // The node stops instead of handling the next messages without its handler.
+        node.send(msg(Payload::Panic)).await.unwrap();
+        assert!(node.send(msg(Payload::Record(1))).await.is_err());
+    }
+}
@@ -0,0 +1,54 @@
+//! The configuration of a node actor before it is started.
+
+use super::NodeActor;
+use crate::{node::Builder as NBuilder, trace::Trace, NodeHandler, ProtocolHandler};
+use actix::prelude::*;
+use std::fmt::Debug;
+
+/// Configures a `NodeActor`, then starts it.
+pub struct NodeActorBuilder<H> {
+    ph: H,
+    trace: Option<Trace>,
+    strict: bool,
+}
+
+impl<H> NodeActorBuilder<H>
+where
+    H: ProtocolHandler + Unpin + 'static,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
+{
+    pub(crate) fn new(ph: H) -> Self {
+        Self {
+            ph,
+            trace: None,
+            strict: false,
+        }
+    }
+
+    /// Records the messages the node sends and receives in the trace.
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Panics on a `NeighbourError` instead of reporting it to the handler,
+    /// which is meant for the tests. The panic stops the node, so the test
+    /// fails on its next exchange with the node.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Starts the node actor.
+    pub fn build(self) -> NodeHandler<H> {
+        let aid = self.ph.aid();
+        let mut actor = NodeActor::new(self.ph);
+        if let Some(trace) = self.trace {
+            actor.dispatcher.set_trace(trace);
+        }
+        actor.dispatcher.set_strict(self.strict);
+        let addr = NodeActor::start(actor);
+
+        NBuilder::from_aid(aid).with_addr(addr).build()
+    }
+}
@@ -0,0 +1,119 @@
+//! The messages deferred by the handler of a node.
+
+use actix::prelude::*;
+
+/// The metrics of the queue of the messages deferred by a node.
+#[derive(Debug, Default, Clone, PartialEq, MessageResponse)]
+pub struct QueueMetrics {
+    /// The number of messages in the queue.
+    pub len: usize,
+    /// The largest number of messages the queue ever had.
+    pub max_len: usize,
+    /// The number of times a message was deferred.
+    pub deferred: usize,
+    /// The number of times a message was offered again to the handler.
+    pub reoffered: usize,
+}
+
+impl QueueMetrics {
+    pub(crate) fn record_defer(&mut self, len: usize) {
+        self.len = len;
+        self.max_len = self.max_len.max(len);
+        self.deferred += 1;
+    }
+
+    pub(crate) fn record_reoffer(&mut self, count: usize) {
+        self.len -= count;
+        self.reoffered += count;
+    }
+}
+
+/// Asks a node for the metrics of its queue of deferred messages.
+#[derive(Debug, Message)]
+#[rtype(result = "QueueMetrics")]
+pub struct GetQueueMetrics;
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        protocol::{Builder, Message, SessionAllocator},
+        ActorId, ContinuationHandler, NodeActor, ProtocolHandler,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    /// Handles the numbers in increasing order, whatever the order they come in.
+    struct InOrder {
+        aid: ActorId,
+        next: usize,
+        results: UnboundedSender<usize>,
+    }
+
+    impl ProtocolHandler for InOrder {
+        type Payload = usize;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: Message<usize>,
+        ) -> ContinuationHandler<usize> {
+            if *msg.payload() != self.next {
+                // A message deferred within a sequence changes nothing either.
+                return ContinuationHandler::Sequence(vec![ContinuationHandler::Defer(msg)]);
+            }
+
+            self.next += 1;
+            let _ = self.results.unbounded_send(*msg.payload());
+            ContinuationHandler::Done
+        }
+    }
+
+    #[actix_rt::test]
+    async fn deferred_() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut node = NodeActor::build(InOrder {
+            aid: 1.into(),
+            next: 0,
+            results: tx,
+        });
+
+        let session = SessionAllocator::for_api().next_session();
+        for i in [3, 2, 1, 0, 4] {
+            let msg = Builder::with_from_api()
+                .with_to_actor(1.into())
+                .with_session(session)
+                .with_payload(i)
+                .with_sender(1.into())
+                .build();
+            node.do_send(msg);
+        }
+
+        let mut received = vec![];
+        for _ in 0..5 {
+            let i = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push(i);
+        }
+        assert_eq!(vec![0, 1, 2, 3, 4], received);
+
+        // 3, 2 and 1 are deferred once, then 3 and 2 once more when 0 is
+        // handled, and 3 one last time when 1 is handled.
+        let metrics = node.queue_metrics().await.unwrap();
+        assert_eq!(
+            QueueMetrics {
+                len: 0,
+                max_len: 3,
+                deferred: 6,
+                reoffered: 6,
+            },
+            metrics
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! The part of a node actor which talks to the neighbours.
+
+use super::{NeighbourError, QueueMetrics};
+use crate::{
+    graph::GraphMsg,
+    protocol::{Message as PMsg, MessageId, MessageIds},
+    trace::{Event, Trace},
+    ActorId, ContinuationHandler, Proxies,
+};
+use actix::prelude::*;
+use log::{error, info};
+use std::{collections::VecDeque, fmt::Debug};
+
+type GMsg<P> = GraphMsg<PMsg<P>>;
+
+/// Keeps the proxies of the neighbours of a node, assigns the message
+/// identifiers, executes the continuations returned by the handler and
+/// keeps the messages it deferred.
+pub(crate) struct Dispatcher<P>
+where
+    P: Send,
+{
+    aid: ActorId,
+    proxies: Proxies<P>,
+    mids: MessageIds,
+    trace: Option<Trace>,
+    // Boxed, so the actors stay `Unpin` whatever the payload.
+    deferred: VecDeque<Box<PMsg<P>>>,
+    metrics: QueueMetrics,
+    strict: bool,
+    errors: Vec<NeighbourError>,
+}
+
+impl<P> Dispatcher<P>
+where
+    P: Send + Debug + Clone,
+{
+    pub(crate) fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            proxies: Default::default(),
+            mids: MessageIds::new(aid),
+            trace: None,
+            deferred: Default::default(),
+            metrics: Default::default(),
+            strict: false,
+            errors: vec![],
+        }
+    }
+
+    pub(crate) fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    /// Records the messages sent and received by the node in the trace.
+    pub(crate) fn set_trace(&mut self, trace: Trace) {
+        self.trace = Some(trace);
+    }
+
+    /// Panics on the errors instead of keeping them for the handler.
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Takes the errors caused by the continuations executed so far.
+    pub(crate) fn take_errors(&mut self) -> Vec<NeighbourError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn error(&mut self, err: NeighbourError) {
+        self.record(Event::Error {
+            aid: self.aid,
+            error: err.clone(),
+        });
+        error!("ERROR | on {:?} | {}", self.aid, err);
+
+        if self.strict {
+            panic!("{err}");
+        }
+        self.errors.push(err);
+    }
+
+    #[inline]
+    fn record(&self, event: Event) {
+        if let Some(trace) = &self.trace {
+            trace.record(event);
+        }
+    }
+
+    /// Takes the deferred messages, to offer them again to the handler.
+    pub(crate) fn take_deferred(&mut self) -> Vec<PMsg<P>> {
+        self.metrics.record_reoffer(self.deferred.len());
+        self.deferred.drain(..).map(|msg| *msg).collect()
+    }
+
+    /// Returns the metrics of the queue of deferred messages.
+    pub(crate) fn queue_metrics(&self) -> QueueMetrics {
+        self.metrics.clone()
+    }
+
+    /// Returns the list of neighbours.
+    pub(crate) fn neighbours(&self) -> impl Iterator<Item = ActorId> + '_ {
+        self.proxies.aids()
+    }
+
+    /// Handles a configuration message.
+    pub(crate) fn handle_graph_msg(&mut self, msg: GMsg<P>) {
+        self.proxies.handle_msg(msg);
+    }
+
+    /// Assigns a new message identifier to a message which is about to be sent.
+    #[inline]
+    fn stamp_msg(&mut self, msg: &mut PMsg<P>) -> MessageId {
+        let mid = self.mids.next_mid();
+        msg.mid = Some(mid);
+        mid
+    }
+
+    /// Sends a copy of the message to every neighbour but the excluded ones,
+    /// each with its own identifier, and returns the identifiers.
+    fn send_all_except(&mut self, msg: PMsg<P>, except: &[ActorId]) -> Vec<MessageId> {
+        let me = self.aid;
+        let tids: Vec<_> = self
+            .proxies
+            .aids()
+            .filter(|aid| !except.contains(aid))
+            .collect();
+
+        let mut mids = Vec::with_capacity(tids.len());
+        for tid in tids {
+            let mut msg = msg.clone();
+            let mid = self.stamp_msg(&mut msg);
+            let session = *msg.session();
+            self.record(Event::Send {
+                aid: me,
+                mid,
+                session,
+            });
+            let from = msg.from();
+            let to = msg.to();
+            let pld = msg.payload();
+            info!(
+                "SEND | from {:?} to node {:?} of all-{:?} | {:?} | {:?}->{:?} | {:?} | {:?}",
+                me, tid, except, mid, from, to, session, pld
+            );
+
+            self.proxies.do_send_to_proxy(&me, &tid, msg);
+            mids.push(mid);
+        }
+        mids
+    }
+
+    /// Records the reception of a message.
+    pub(crate) fn recv(&mut self, msg: &mut PMsg<P>) {
+        // Messages injected through the api are not sent by any node,
+        // so they get their identifier when they enter the network.
+        let mid = match msg.mid {
+            Some(mid) => mid,
+            None => self.stamp_msg(msg),
+        };
+
+        let me = self.aid;
+        let sender = msg.sender();
+        let from = msg.from().clone();
+        let to = msg.to().clone();
+        let session = *msg.session();
+        let pld = msg.payload();
+        self.record(Event::Recv {
+            aid: me,
+            mid,
+            session,
+        });
+
+        info!(
+            "RECV | on {:?} from {:?} | {:?} | {:?}->{:?} | {:?} | {:?}",
+            me, sender, mid, from, to, session, pld
+        );
+    }
+
+    /// Executes the continuation returned by the handler of the actor.
+    pub(crate) fn dispatch<A>(&mut self, res: ContinuationHandler<P>, ctx: &mut Context<A>)
+    where
+        A: Actor<Context = Context<A>> + Handler<PMsg<P>>,
+        P: 'static,
+    {
+        let me = self.aid;
+
+        match res {
+            ContinuationHandler::SendToNode(tid, mut msg) => {
+                let mid = self.stamp_msg(&mut msg);
+                let sid = *msg.session();
+                if !self.proxies.contains(&tid) {
+                    self.error(NeighbourError::UnknownDestination {
+                        aid: me,
+                        dest: tid,
+                        mid,
+                        session: sid,
+                    });
+                    return;
+                }
+
+                self.record(Event::Send {
+                    aid: me,
+                    mid,
+                    session: sid,
+                });
+                let from = msg.from();
+                let to = msg.to();
+                let pld = msg.payload();
+                info!(
+                    "SEND | from {:?} to node {:?} | {:?} | {:?}->{:?} | {:?} | {:?}",
+                    me, to, mid, from, to, sid, pld
+                );
+
+                self.proxies.do_send_to_proxy(&me, &tid, msg)
+            }
+            ContinuationHandler::SendToAllNodes(msg) => {
+                self.send_all_except(msg, &[]);
+            }
+            ContinuationHandler::SendToAllNodesExcept(mut msg, except) => {
+                let unknown: Vec<_> = except
+                    .iter()
+                    .filter(|aid| !self.proxies.contains(aid))
+                    .copied()
+                    .collect();
+                if unknown.is_empty() {
+                    self.send_all_except(msg, &except);
+                    return;
+                }
+
+                // The errors refer to the first copy, or to the message
+                // itself if no neighbour is left to send it to.
+                let session = *msg.session();
+                let mid = match self.send_all_except(msg.clone(), &except).first() {
+                    Some(mid) => *mid,
+                    None => self.stamp_msg(&mut msg),
+                };
+                for excluded in unknown {
+                    self.error(NeighbourError::UnknownExcluded {
+                        aid: me,
+                        excluded,
+                        mid,
+                        session,
+                    });
+                }
+            }
+            ContinuationHandler::Schedule(delay, msg) => {
+                let from = msg.from();
+                let to = msg.to();
+                let session = *msg.session();
+                let pld = msg.payload();
+                info!(
+                    "SCHD | on {:?} in {:?} | {:?}->{:?} | {:?} | {:?}",
+                    me, delay, from, to, session, pld
+                );
+
+                ctx.notify_later(msg, delay);
+            }
+            ContinuationHandler::Defer(msg) => {
+                self.deferred.push_back(Box::new(msg));
+                self.metrics.record_defer(self.deferred.len());
+
+                let msg = self.deferred.back().expect("the message was deferred");
+                info!(
+                    "DEFR | on {:?} | {:?} | {:?} | {:?} | queued {}",
+                    me,
+                    msg.mid,
+                    msg.session(),
+                    msg.payload(),
+                    self.deferred.len()
+                );
+            }
+            ContinuationHandler::Sequence(conts) => {
+                conts.into_iter().for_each(|res| self.dispatch(res, ctx))
+            }
+            ContinuationHandler::Done => (),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! The errors of the continuations returned by the handlers.
+
+use crate::{
+    protocol::{MessageId, Session},
+    ActorId,
+};
+use std::fmt::Display;
+
+/// A continuation which names a node which is not a neighbour. The messages
+/// only go to the neighbours, so it is almost always a bug in the protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NeighbourError {
+    /// The destination of `SendToNode` is not a neighbour, so the message
+    /// was not sent.
+    UnknownDestination {
+        /// The node which sent the message.
+        aid: ActorId,
+        /// The destination.
+        dest: ActorId,
+        /// The identifier of the message.
+        mid: MessageId,
+        /// The session of the message.
+        session: Session,
+    },
+    /// A node excluded by `SendToAllNodesExcept` is not a neighbour.
+    /// The message was sent to the other neighbours.
+    UnknownExcluded {
+        /// The node which sent the message.
+        aid: ActorId,
+        /// The node excluded.
+        excluded: ActorId,
+        /// The identifier of the message.
+        mid: MessageId,
+        /// The session of the message.
+        session: Session,
+    },
+}
+
+impl NeighbourError {
+    /// Returns the node which sent the message.
+    pub fn aid(&self) -> ActorId {
+        match self {
+            Self::UnknownDestination { aid, .. } | Self::UnknownExcluded { aid, .. } => *aid,
+        }
+    }
+
+    /// Returns the session of the message.
+    pub fn session(&self) -> Session {
+        match self {
+            Self::UnknownDestination { session, .. } | Self::UnknownExcluded { session, .. } => {
+                *session
+            }
+        }
+    }
+}
+
+impl Display for NeighbourError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownDestination { aid, dest, mid, .. } => {
+                write!(
+                    f,
+                    "{aid:?} sent {mid:?} to {dest:?}, which is not a neighbour"
+                )
+            }
+            Self::UnknownExcluded {
+                aid, excluded, mid, ..
+            } => write!(
+                f,
+                "{aid:?} sent {mid:?} to all but {excluded:?}, which is not a neighbour"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NeighbourError {}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, FromId, Message, SessionAllocator},
+        trace::{Event, Trace},
+        ContinuationHandler, NodeActor, ProtocolHandler,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    /// Sends a message to a node which is not a neighbour, then to all the
+    /// neighbours but another one which is not a neighbour either.
+    struct Stray {
+        aid: ActorId,
+        results: UnboundedSender<NeighbourError>,
+    }
+
+    impl ProtocolHandler for Stray {
+        type Payload = ();
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: Message<()>,
+        ) -> ContinuationHandler<()> {
+            if matches!(msg.from(), FromId::Api) {
+                let msg = Builder::with_from_actor(self.aid)
+                    .with_to_actor(3.into())
+                    .with_session(*msg.session())
+                    .with_payload(())
+                    .with_sender(self.aid)
+                    .build();
+                ContinuationHandler::SendToNode(3.into(), msg)
+            } else {
+                ContinuationHandler::Done
+            }
+        }
+
+        fn on_neighbour_error(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            err: NeighbourError,
+        ) -> ContinuationHandler<()> {
+            let _ = self.results.unbounded_send(err.clone());
+            let msg = Builder::with_from_actor(self.aid)
+                .with_to_all_actors()
+                .with_session(err.session())
+                .with_payload(())
+                .with_sender(self.aid)
+                .build();
+            ContinuationHandler::SendToAllNodesExcept(msg, vec![4.into()])
+        }
+    }
+
+    fn start() -> Message<()> {
+        Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(())
+            .with_sender(1.into())
+            .build()
+    }
+
+    #[actix_rt::test]
+    async fn neighbour_errors() {
+        let trace = Trace::new();
+        let mut events = trace.subscribe();
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes = [
+            NodeActor::builder(Stray {
+                aid: 1.into(),
+                results: tx.clone(),
+            })
+            .with_trace(trace.clone())
+            .build(),
+            NodeActor::build(Stray {
+                aid: 2.into(),
+                results: tx.clone(),
+            }),
+            NodeActor::build(Stray {
+                aid: 5.into(),
+                results: tx,
+            }),
+        ];
+        let (a, b) = nodes.split_at_mut(1);
+        add_edge(&mut a[0], &mut b[0]).await;
+        add_edge(&mut a[0], &mut b[1]).await;
+        let msg = start();
+        let session = *msg.session();
+        nodes[0].do_send(msg);
+
+        let mut errors = vec![];
+        let mut mids = vec![];
+        while errors.len() < 2 {
+            let event = actix_rt::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .unwrap()
+                .unwrap();
+            match event {
+                Event::Error { error, .. } => errors.push(error),
+                // The message to the unknown destination is never sent.
+                Event::Send { mid, .. } => {
+                    assert!(!errors.is_empty());
+                    mids.push(mid);
+                }
+                _ => (),
+            }
+        }
+        assert!(matches!(
+            errors[0],
+            NeighbourError::UnknownDestination { dest, .. } if dest == 3.into()
+        ));
+        assert!(matches!(
+            errors[1],
+            NeighbourError::UnknownExcluded { excluded, .. } if excluded == 4.into()
+        ));
+
+        // Every copy of the broadcast has its own identifier.
+        assert_eq!(2, mids.len());
+        assert_ne!(mids[0], mids[1]);
+
+        // Only the first error is handed to the handler, the second one
+        // comes from the continuation of the callback.
+        let err = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(errors[0], err);
+        assert_eq!(ActorId::from(1), err.aid());
+        assert_eq!(session, err.session());
+        assert!(nodes[0].queue_metrics().await.is_ok());
+    }
+
+    #[actix_rt::test]
+    #[should_panic]
+    async fn strict() {
+        // A strict node panics on the first error, which fails the test.
+        let (tx, _rx) = futures::channel::mpsc::unbounded();
+        let mut strict = NodeActor::builder(Stray {
+            aid: 1.into(),
+            results: tx,
+        })
+        .strict()
+        .build();
+        strict.send(start()).await.unwrap();
+    }
+}
@@ -1,14 +1,25 @@
 //! The actor functionality
 
 mod aid;
+mod asynch;
+mod builder;
+mod deferred;
+mod dispatcher;
+mod errors;
+mod sessions;
 
 pub use aid::*;
+pub use asynch::*;
+pub use builder::*;
+pub use deferred::*;
+pub use errors::*;
+pub use sessions::*;
 
 use std::fmt::Debug;
 
-use crate::{node::Builder as NBuilder, protocol::Message as ProMsg, *};
+use self::dispatcher::Dispatcher;
+use crate::{protocol::Message as ProMsg, trace::Trace, *};
 use actix::prelude::*;
-use log::info;
 
 type PMsg<P> = ProMsg<P>;
 type GMsg<P> = graph::GraphMsg<PMsg<P>>;
@@ -18,35 +29,21 @@ pub struct NodeActor<H>
 where
     H: ProtocolHandler,
 {
-    proxies: Proxies<H::Payload>,
+    dispatcher: Dispatcher<H::Payload>,
     ph: H,
 }
 
 impl<H> NodeActor<H>
 where
     H: ProtocolHandler,
-    <H as ProtocolHandler>::Payload: Debug,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
 {
     fn new(ph: H) -> Self {
         Self {
-            proxies: Default::default(),
+            dispatcher: Dispatcher::new(ph.aid()),
             ph,
         }
     }
-
-    fn info_msg(&self, msg: &PMsg<H::Payload>) {
-        let me = self.ph.aid();
-        let sender = msg.sender();
-        let from = msg.from().clone();
-        let to = msg.to().clone();
-        let session = *msg.session();
-        let pld = msg.payload();
-
-        info!(
-            "RECV | on {:?} from {:?} | {:?}->{:?} | {:?} | {:?}",
-            me, sender, from, to, session, pld
-        );
-    }
 }
 
 impl<H> Actor for NodeActor<H>
@@ -59,11 +56,12 @@ where
 impl<H> Handler<GMsg<H::Payload>> for NodeActor<H>
 where
     H: ProtocolHandler + Unpin + 'static,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
 {
     type Result = ();
 
     fn handle(&mut self, msg: GMsg<H::Payload>, _ctx: &mut Self::Context) -> Self::Result {
-        self.proxies.handle_msg(msg);
+        self.dispatcher.handle_graph_msg(msg);
     }
 }
 
@@ -74,65 +72,106 @@ where
 {
     type Result = ();
 
-    fn handle(&mut self, msg: PMsg<H::Payload>, _: &mut Context<Self>) {
-        self.info_msg(&msg);
-        let me = self.ph.aid();
-        let ns = self.proxies.aids();
+    fn handle(&mut self, mut msg: PMsg<H::Payload>, ctx: &mut Context<Self>) {
+        self.dispatcher.recv(&mut msg);
 
+        let ns = self.dispatcher.neighbours();
         let res = self.ph.receive(ns, msg);
-        match res {
-            ContinuationHandler::SendToNode(tid, msg) => {
-                let from = msg.from();
-                let to = msg.to();
-                let sid = *msg.session();
-                let pld = msg.payload();
-                info!(
-                    "SEND | from {:?} to node {:?} | {:?}->{:?} | {:?} | {:?}",
-                    me, to, from, to, sid, pld
-                );
-
-                self.proxies.do_send_to_proxy(&me, &tid, msg)
+        let changed = !res.is_deferred();
+        self.dispatch(res, ctx);
+
+        if changed {
+            self.reoffer(ctx);
+        }
+    }
+}
+
+impl<H> Handler<GetQueueMetrics> for NodeActor<H>
+where
+    H: ProtocolHandler + Unpin + 'static,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
+{
+    type Result = QueueMetrics;
+
+    fn handle(&mut self, _msg: GetQueueMetrics, _ctx: &mut Context<Self>) -> QueueMetrics {
+        self.dispatcher.queue_metrics()
+    }
+}
+
+impl<H> NodeActor<H>
+where
+    H: ProtocolHandler + Unpin + 'static,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
+{
+    /// Executes the continuation, then hands the errors it caused to the
+    /// handler. The errors caused by the continuations the handler returns
+    /// for them are recorded, but they are not handed back to the handler.
+    fn dispatch(&mut self, res: ContinuationHandler<H::Payload>, ctx: &mut Context<Self>) {
+        self.dispatcher.dispatch(res, ctx);
+
+        for err in self.dispatcher.take_errors() {
+            let ns = self.dispatcher.neighbours();
+            let res = self.ph.on_neighbour_error(ns, err);
+            self.dispatcher.dispatch(res, ctx);
+        }
+        self.dispatcher.take_errors();
+    }
+
+    /// Offers the deferred messages again to the handler, in the order they
+    /// were deferred, as long as one of them changes the state of the node.
+    fn reoffer(&mut self, ctx: &mut Context<Self>) {
+        loop {
+            let deferred = self.dispatcher.take_deferred();
+            if deferred.is_empty() {
+                return;
             }
-            ContinuationHandler::SendToAllNodes(msg) => {
-                let from = msg.from();
-                let to = msg.to();
-                let session = *msg.session();
-                let pld = msg.payload();
-                info!(
-                    "SEND | from {:?} to all | {:?}->{:?} | {:?} | {:?}",
-                    me, from, to, session, pld
-                );
-
-                self.proxies.do_send_all_except(&me, msg, &[])
+
+            let mut changed = false;
+            for msg in deferred {
+                let ns = self.dispatcher.neighbours();
+                let res = self.ph.receive(ns, msg);
+                changed |= !res.is_deferred();
+                self.dispatch(res, ctx);
             }
-            ContinuationHandler::SendToAllNodesExcept(msg, except) => {
-                let from = msg.from();
-                let to = msg.to();
-                let session = *msg.session();
-                let pld = msg.payload();
-                info!(
-                    "SEND | from {:?} to all-{:?} | {:?}->{:?} | {:?} | {:?}",
-                    me, except, from, to, session, pld
-                );
-
-                self.proxies.do_send_all_except(&me, msg, except.as_slice())
+
+            if !changed {
+                return;
             }
-            ContinuationHandler::Done => (),
         }
     }
 }
 
 impl<H> NodeActor<H>
 where
-    H: ProtocolHandler + Unpin,
+    H: ProtocolHandler + Unpin + 'static,
     <H as ProtocolHandler>::Payload: Debug + Clone,
 {
     /// Builds a new node actor.
     pub fn build(ph: H) -> Node<NodeActor<H>, H::Payload> {
-        let aid = ph.aid();
-        let actor = NodeActor::new(ph);
-        let addr = NodeActor::start(actor);
+        NodeActorBuilder::new(ph).build()
+    }
 
-        NBuilder::from_aid(aid).with_addr(addr).build()
+    /// Builds a new node actor which records the messages
+    /// it sends and receives in the trace.
+    pub fn build_traced(ph: H, trace: Trace) -> Node<NodeActor<H>, H::Payload> {
+        NodeActorBuilder::new(ph).with_trace(trace).build()
+    }
+
+    /// Returns a builder, to configure the node actor before it is started.
+    pub fn builder(ph: H) -> NodeActorBuilder<H> {
+        NodeActorBuilder::new(ph)
+    }
+}
+
+impl<H, F> NodeActor<Sessions<H, F>>
+where
+    H: ProtocolHandler + Unpin + 'static,
+    <H as ProtocolHandler>::Payload: Debug + Clone,
+    F: FnMut(ActorId, protocol::Session) -> H + Unpin + 'static,
+{
+    /// Builds a new node actor which creates a fresh handler instance, using
+    /// the factory, for every session it takes part in.
+    pub fn build_per_session(aid: ActorId, factory: F) -> NodeHandler<Sessions<H, F>> {
+        NodeActor::build(Sessions::new(aid, factory))
     }
 }
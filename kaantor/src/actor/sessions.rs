@@ -0,0 +1,323 @@
+//! Per-session handler instances.
+
+use crate::{
+    protocol::{Origin, Session},
+    ActorId, ContinuationHandler, NeighbourError, ProtocolHandler,
+};
+use log::debug;
+use std::collections::{BTreeSet, HashMap};
+
+/// The sessions finished out of order kept for an allocator, before the
+/// oldest ones expire.
+const MAX_FINISHED: usize = 1024;
+
+/// The sessions of an allocator which finished on the node: all the ones
+/// below a low watermark, and the ones above it which finished out of order.
+#[derive(Default)]
+struct Finished {
+    low: usize,
+    above: BTreeSet<usize>,
+}
+
+impl Finished {
+    fn contains(&self, seq: usize) -> bool {
+        seq < self.low || self.above.contains(&seq)
+    }
+
+    /// Records the finished session, and raises the watermark over the
+    /// sessions which finished in a row. Once too many finished out of order,
+    /// the sessions below the oldest one expire: they are considered finished.
+    fn insert(&mut self, seq: usize) {
+        if seq >= self.low {
+            self.above.insert(seq);
+        }
+        while self.above.len() > MAX_FINISHED {
+            let oldest = self.above.pop_first().unwrap();
+            self.low = oldest + 1;
+        }
+        while self.above.remove(&self.low) {
+            self.low += 1;
+        }
+    }
+}
+
+/// A handler which creates a fresh handler instance for every `Session`,
+/// using a factory, and routes each message to the instance of its session.
+/// An instance is dropped as soon as it reports it finished its work, and
+/// any late message for a finished session is ignored. The finished sessions
+/// are remembered with a low watermark for each allocator of sessions, since
+/// the allocators number their sessions in order, so a message for a session
+/// older than the watermark which never ran on the node is ignored too.
+pub struct Sessions<H, F>
+where
+    H: ProtocolHandler,
+    F: FnMut(ActorId, Session) -> H,
+{
+    aid: ActorId,
+    factory: F,
+    handlers: HashMap<Session, H>,
+    finished: HashMap<Origin, Finished>,
+}
+
+impl<H, F> Sessions<H, F>
+where
+    H: ProtocolHandler,
+    F: FnMut(ActorId, Session) -> H,
+{
+    /// Creates a new handler for the actor `aid`, which uses the factory
+    /// to create the handler instance for a new session.
+    pub fn new(aid: ActorId, factory: F) -> Self {
+        Self {
+            aid,
+            factory,
+            handlers: Default::default(),
+            finished: Default::default(),
+        }
+    }
+
+    /// Returns the number of sessions which are still running.
+    pub fn active(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Returns `true` if the session finished on this node.
+    pub fn is_session_finished(&self, session: &Session) -> bool {
+        self.finished
+            .get(&session.allocator())
+            .is_some_and(|f| f.contains(session.seq()))
+    }
+}
+
+impl<H, F> ProtocolHandler for Sessions<H, F>
+where
+    H: ProtocolHandler,
+    F: FnMut(ActorId, Session) -> H,
+{
+    type Payload = H::Payload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        msg: crate::protocol::Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+
+        if !self.handlers.contains_key(&session) && self.is_session_finished(&session) {
+            debug!("drop message on {:?} for finished {:?}", self.aid, session);
+            return ContinuationHandler::Done;
+        }
+
+        let aid = self.aid;
+        let factory = &mut self.factory;
+        let handler = self
+            .handlers
+            .entry(session)
+            .or_insert_with(|| factory(aid, session));
+
+        let res = handler.receive(neighbours, msg);
+
+        if handler.is_finished() {
+            debug!("finished {:?} on {:?}", session, self.aid);
+            self.handlers.remove(&session);
+            self.finished
+                .entry(session.allocator())
+                .or_default()
+                .insert(session.seq());
+        }
+
+        res
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        match self.handlers.get_mut(&err.session()) {
+            Some(handler) => handler.on_neighbour_error(neighbours, err),
+            None => {
+                debug!(
+                    "drop error on {:?} for finished {:?}: {}",
+                    self.aid,
+                    err.session(),
+                    err
+                );
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::Builder, protocol::Message, NodeActor};
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    enum Payload {
+        Start,
+        Go,
+        Back(usize),
+    }
+
+    /// Counts the nodes of the graph with the echo algorithm.
+    struct Echo {
+        aid: ActorId,
+        root: bool,
+        parent: Option<ActorId>,
+        pending: usize,
+        count: usize,
+        finished: bool,
+        results: UnboundedSender<(Session, usize)>,
+    }
+
+    impl Echo {
+        fn send(&self, session: Session, payload: Payload) -> Message<Payload> {
+            Builder::with_from_actor(self.aid)
+                .with_to_all_actors()
+                .with_session(session)
+                .with_payload(payload)
+                .with_sender(self.aid)
+                .build()
+        }
+
+        fn check_done(&mut self, session: Session) -> ContinuationHandler<Payload> {
+            if self.pending != 0 {
+                return ContinuationHandler::Done;
+            }
+
+            self.finished = true;
+            match self.parent {
+                Some(pid) => ContinuationHandler::SendToNode(
+                    pid,
+                    self.send(session, Payload::Back(self.count)),
+                ),
+                None => {
+                    let _ = self.results.unbounded_send((session, self.count));
+                    ContinuationHandler::Done
+                }
+            }
+        }
+    }
+
+    impl ProtocolHandler for Echo {
+        type Payload = Payload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            msg: Message<Self::Payload>,
+        ) -> ContinuationHandler<Self::Payload> {
+            let session = *msg.session();
+            let sender = msg.sender().as_aid();
+
+            match msg.payload() {
+                Payload::Start => {
+                    self.root = true;
+                    self.count = 1;
+                    self.pending = ns.count();
+                    ContinuationHandler::SendToAllNodes(self.send(session, Payload::Go))
+                }
+                Payload::Go if !self.root && self.parent.is_none() => {
+                    self.parent = Some(sender);
+                    self.count = 1;
+                    self.pending = ns.count() - 1;
+                    if self.pending == 0 {
+                        self.check_done(session)
+                    } else {
+                        let go = self.send(session, Payload::Go);
+                        ContinuationHandler::SendToAllNodesExcept(go, vec![sender])
+                    }
+                }
+                Payload::Go => {
+                    self.pending -= 1;
+                    self.check_done(session)
+                }
+                Payload::Back(c) => {
+                    self.count += c;
+                    self.pending -= 1;
+                    self.check_done(session)
+                }
+            }
+        }
+
+        fn is_finished(&self) -> bool {
+            self.finished
+        }
+    }
+
+    #[test]
+    fn finished_sessions() {
+        let mut f = Finished::default();
+        for seq in [1, 0, 3] {
+            f.insert(seq);
+        }
+        assert_eq!(2, f.low);
+        assert!(f.contains(1) && !f.contains(2) && f.contains(3));
+
+        // The oldest sessions expire once too many finished out of order.
+        for seq in 4..MAX_FINISHED + 5 {
+            f.insert(seq);
+        }
+        assert!(f.contains(2));
+        assert_eq!(MAX_FINISHED + 5, f.low);
+        assert!(f.above.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn concurrent_sessions() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| {
+                let tx = tx.clone();
+                NodeActor::build_per_session(i.into(), move |aid, _| Echo {
+                    aid,
+                    root: false,
+                    parent: None,
+                    pending: 0,
+                    count: 0,
+                    finished: false,
+                    results: tx.clone(),
+                })
+            })
+            .collect();
+
+        for (a, b) in [(0, 1), (0, 2), (1, 3), (3, 4), (2, 4)] {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[a], &mut right[0]).await;
+        }
+
+        for (i, session) in [(0, 10), (4, 20)] {
+            let aid = nodes[i].aid();
+            let msg = Builder::with_from_api()
+                .with_to_actor(aid)
+                .with_session(session.into())
+                .with_payload(Payload::Start)
+                .with_sender(aid)
+                .build();
+            nodes[i].do_send(msg);
+        }
+
+        let mut results = vec![];
+        for _ in 0..2 {
+            let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            results.push(res);
+        }
+        assert!(results.contains(&(Session::from(10), 5)));
+        assert!(results.contains(&(Session::from(20), 5)));
+    }
+}
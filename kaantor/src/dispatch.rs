@@ -0,0 +1,176 @@
+//! Dispatch of the received messages to per-variant handler methods.
+
+use crate::{protocol::Message, ActorId, ContinuationHandler};
+
+pub use kaantor_derive::{Dispatch, PayloadDebug};
+
+/// A payload which dispatches the messages to the methods of a handler,
+/// one method per kind of message. It is usually derived, see `Dispatch`,
+/// and called from `ProtocolHandler::receive`.
+pub trait Dispatch<H>: Sized {
+    /// Calls the method of the handler for the payload of the message.
+    fn dispatch(
+        handler: &mut H,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self>,
+    ) -> ContinuationHandler<Self>;
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::protocol::{Builder, SessionAllocator};
+
+    #[derive(Clone, Dispatch, PayloadDebug)]
+    #[payload(handler = "CountHandler")]
+    enum Payload {
+        Start,
+        Add(usize),
+        BackChild {
+            #[payload(iter)]
+            aids: Vec<ActorId>,
+            level: usize,
+        },
+        Visited(Vec<ActorId>),
+        // The fields are named after the identifiers of the generated code.
+        HTTPGet {
+            msg: usize,
+            ns: usize,
+            handler: usize,
+            f: usize,
+        },
+        #[payload(name = "DONE!")]
+        Done,
+    }
+
+    struct Counter {
+        total: usize,
+        children: usize,
+    }
+
+    impl CountHandler for Counter {
+        fn handle_start(
+            &mut self,
+            _msg: &Message<Payload>,
+            ns: impl Iterator<Item = ActorId>,
+        ) -> ContinuationHandler<Payload> {
+            self.total = ns.count();
+            ContinuationHandler::Done
+        }
+
+        fn handle_add(
+            &mut self,
+            _msg: &Message<Payload>,
+            _ns: impl Iterator<Item = ActorId>,
+            v: &usize,
+        ) -> ContinuationHandler<Payload> {
+            self.total += v;
+            ContinuationHandler::Done
+        }
+
+        fn handle_back_child(
+            &mut self,
+            _msg: &Message<Payload>,
+            _ns: impl Iterator<Item = ActorId>,
+            aids: &Vec<ActorId>,
+            level: &usize,
+        ) -> ContinuationHandler<Payload> {
+            self.children += aids.len() * level;
+            ContinuationHandler::Done
+        }
+
+        fn handle_visited(
+            &mut self,
+            _msg: &Message<Payload>,
+            _ns: impl Iterator<Item = ActorId>,
+            v0: &Vec<ActorId>,
+        ) -> ContinuationHandler<Payload> {
+            self.children = v0.len();
+            ContinuationHandler::Done
+        }
+
+        fn handle_http_get(
+            &mut self,
+            _msg: &Message<Payload>,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: &usize,
+            ns: &usize,
+            handler: &usize,
+            f: &usize,
+        ) -> ContinuationHandler<Payload> {
+            self.total = msg + ns + handler + f;
+            ContinuationHandler::Done
+        }
+
+        fn handle_done(
+            &mut self,
+            _msg: &Message<Payload>,
+            _ns: impl Iterator<Item = ActorId>,
+        ) -> ContinuationHandler<Payload> {
+            self.total = 0;
+            ContinuationHandler::Done
+        }
+    }
+
+    fn msg(payload: Payload) -> Message<Payload> {
+        Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(payload)
+            .with_sender(1.into())
+            .build()
+    }
+
+    #[test]
+    fn dispatch_() {
+        let mut h = Counter {
+            total: 0,
+            children: 0,
+        };
+        let ns = || [2, 3].into_iter().map(ActorId::from);
+
+        Payload::dispatch(&mut h, ns(), msg(Payload::Start));
+        Payload::dispatch(&mut h, ns(), msg(Payload::Add(5)));
+        assert_eq!(7, h.total);
+
+        let back = Payload::BackChild {
+            aids: vec![4.into(), 5.into()],
+            level: 3,
+        };
+        Payload::dispatch(&mut h, ns(), msg(back));
+        assert_eq!(6, h.children);
+
+        let get = Payload::HTTPGet {
+            msg: 1,
+            ns: 2,
+            handler: 3,
+            f: 4,
+        };
+        Payload::dispatch(&mut h, ns(), msg(get));
+        assert_eq!(10, h.total);
+
+        Payload::dispatch(&mut h, ns(), msg(Payload::Done));
+        assert_eq!(0, h.total);
+    }
+
+    #[test]
+    fn payload_debug() {
+        assert_eq!("START", format!("{:?}", Payload::Start));
+        assert_eq!("ADD | 5", format!("{:?}", Payload::Add(5)));
+        let back = Payload::BackChild {
+            aids: vec![4.into(), 5.into()],
+            level: 3,
+        };
+        assert_eq!("BACK CHILD | A4, A5 | 3", format!("{back:?}"));
+        let visited = Payload::Visited(vec![4.into(), 5.into()]);
+        assert_eq!("VISITED | [A4, A5]", format!("{visited:?}"));
+        let get = Payload::HTTPGet {
+            msg: 1,
+            ns: 2,
+            handler: 3,
+            f: 4,
+        };
+        assert_eq!("HTTP GET | 1 | 2 | 3 | 4", format!("{get:?}"));
+        assert_eq!("DONE!", format!("{:?}", Payload::Done));
+    }
+}
@@ -0,0 +1,205 @@
+use super::Leader;
+use crate::{
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::time::Duration;
+
+/// The payload of the bully algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum BullyPayload {
+    /// Makes the node start an election.
+    Start,
+    /// Asks a node with a larger identifier to take over the election.
+    Election,
+    /// Tells a node with a smaller identifier that the sender took over.
+    Alive,
+    /// Announces the leader.
+    Coordinator(ActorId),
+    /// The timer of an election expired. Sent by a node to itself.
+    Timeout(usize),
+    /// Crashes the node, which ignores all the messages from now on.
+    Crash,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Waiting {
+    Alive,
+    Coordinator,
+}
+
+/// The bully algorithm. A node which starts an election asks the nodes with a
+/// larger identifier to take over. If none answers before the timeout, it is
+/// the largest node alive and announces itself as the leader. Otherwise, it
+/// waits for the announcement for twice the timeout, and starts a new election
+/// if it does not come. The nodes with a smaller identifier than the leader
+/// are bullied into accepting it, the others start a new election.
+///
+/// The nodes only talk to their neighbours, so the graph is expected to be
+/// complete. The nodes can be crashed with `BullyPayload::Crash`, to elect
+/// another leader.
+pub struct Bully {
+    aid: ActorId,
+    timeout: Duration,
+    epoch: usize,
+    waiting: Option<Waiting>,
+    crashed: bool,
+    leader: Option<ActorId>,
+    results: Option<UnboundedSender<Leader>>,
+}
+
+impl Bully {
+    /// Creates the handler for the node `aid`, which waits up to `timeout`
+    /// for the nodes with a larger identifier to answer.
+    pub fn new(aid: ActorId, timeout: Duration) -> Self {
+        Self {
+            aid,
+            timeout,
+            epoch: 0,
+            waiting: None,
+            crashed: false,
+            leader: None,
+            results: None,
+        }
+    }
+
+    /// Sends the leader to the channel, from every node, each time one is elected.
+    pub fn with_results(mut self, results: UnboundedSender<Leader>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Returns the last leader elected.
+    pub fn leader(&self) -> Option<ActorId> {
+        self.leader
+    }
+
+    fn build_msg(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: BullyPayload,
+    ) -> Message<BullyPayload> {
+        Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build()
+    }
+
+    /// Waits for the given kind of message, until the delay elapsed. The
+    /// timers which were set before are ignored from now on.
+    fn wait(
+        &mut self,
+        session: Session,
+        waiting: Waiting,
+        delay: Duration,
+    ) -> ContinuationHandler<BullyPayload> {
+        self.epoch += 1;
+        self.waiting = Some(waiting);
+
+        let timeout = self.build_msg(self.aid, session, BullyPayload::Timeout(self.epoch));
+        ContinuationHandler::Schedule(delay, timeout)
+    }
+
+    fn start(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+    ) -> ContinuationHandler<BullyPayload> {
+        let larger: Vec<_> = ns.filter(|n| *n > self.aid).collect();
+        if larger.is_empty() {
+            return self.coordinate(session);
+        }
+
+        larger
+            .into_iter()
+            .fold(ContinuationHandler::Done, |res, n| {
+                let election = self.build_msg(n, session, BullyPayload::Election);
+                res.and_then(ContinuationHandler::SendToNode(n, election))
+            })
+            .and_then(self.wait(session, Waiting::Alive, self.timeout))
+    }
+
+    fn coordinate(&mut self, session: Session) -> ContinuationHandler<BullyPayload> {
+        self.elect(session, self.aid);
+        let coordinator = Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(BullyPayload::Coordinator(self.aid))
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToAllNodes(coordinator)
+    }
+
+    fn elect(&mut self, session: Session, leader: ActorId) {
+        log::info!("LEADER | on {:?} | {:?} | {:?}", self.aid, session, leader);
+
+        self.epoch += 1;
+        self.waiting = None;
+        self.leader = Some(leader);
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Leader {
+                aid: self.aid,
+                session,
+                leader,
+            });
+        }
+    }
+}
+
+impl ProtocolHandler for Bully {
+    type Payload = BullyPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        if self.crashed {
+            return ContinuationHandler::Done;
+        }
+
+        let session = *msg.session();
+        let sender = msg.sender().as_aid();
+
+        match *msg.payload() {
+            BullyPayload::Start if self.waiting.is_none() => self.start(ns, session),
+            BullyPayload::Start => ContinuationHandler::Done,
+            BullyPayload::Election => {
+                let alive = self.build_msg(sender, session, BullyPayload::Alive);
+                let res = ContinuationHandler::SendToNode(sender, alive);
+                match self.waiting {
+                    None => res.and_then(self.start(ns, session)),
+                    Some(_) => res,
+                }
+            }
+            BullyPayload::Alive if self.waiting == Some(Waiting::Alive) => {
+                self.wait(session, Waiting::Coordinator, 2 * self.timeout)
+            }
+            BullyPayload::Alive => ContinuationHandler::Done,
+            BullyPayload::Coordinator(leader) if leader < self.aid => self.start(ns, session),
+            BullyPayload::Coordinator(leader) => {
+                self.elect(session, leader);
+                ContinuationHandler::Done
+            }
+            BullyPayload::Timeout(epoch) if epoch == self.epoch => match self.waiting {
+                Some(Waiting::Alive) => self.coordinate(session),
+                Some(Waiting::Coordinator) => self.start(ns, session),
+                None => ContinuationHandler::Done,
+            },
+            BullyPayload::Timeout(_) => ContinuationHandler::Done,
+            BullyPayload::Crash => {
+                log::info!("CRASH | on {:?}", self.aid);
+                self.crashed = true;
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
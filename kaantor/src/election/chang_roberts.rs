@@ -0,0 +1,84 @@
+use super::{Election, RingCore};
+use crate::{
+    graph::Ring, protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The payload of the Chang–Roberts algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum ChangRobertsPayload {
+    /// Makes the node a candidate.
+    Start,
+    /// A candidate, travelling around the ring.
+    Candidate(ActorId),
+    /// Announces the leader.
+    Elected(ActorId, usize),
+}
+
+/// The Chang–Roberts algorithm, on a unidirectional ring. Every candidate sends
+/// its identifier to the next node, which forwards it when it is larger than its
+/// own identifier and drops it otherwise. The node which gets its own identifier
+/// back is elected. A node which is not a candidate yet becomes one as soon as
+/// it receives a message.
+pub struct ChangRoberts {
+    core: RingCore,
+    candidate: bool,
+}
+
+impl ChangRoberts {
+    /// Creates the handler for the node `aid` of the ring.
+    pub fn new(aid: ActorId, ring: &Ring) -> Self {
+        Self {
+            core: RingCore::new(aid, ring),
+            candidate: false,
+        }
+    }
+
+    /// Sends the outcome of the election to the channel, from the leader.
+    pub fn with_results(mut self, results: UnboundedSender<Election>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+}
+
+impl ProtocolHandler for ChangRoberts {
+    type Payload = ChangRobertsPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let (aid, next) = (self.core.aid, self.core.next);
+
+        let mut res = ContinuationHandler::Done;
+        if !self.candidate && self.core.leader.is_none() {
+            self.candidate = true;
+            let candidate = ChangRobertsPayload::Candidate(aid);
+            res = self.core.send(next, session, candidate);
+        }
+
+        let cont = match msg.payload() {
+            ChangRobertsPayload::Start => ContinuationHandler::Done,
+            ChangRobertsPayload::Candidate(id) if *id == aid => {
+                self.core.elect(session, aid, ChangRobertsPayload::Elected)
+            }
+            ChangRobertsPayload::Candidate(id) if *id > aid => {
+                let candidate = ChangRobertsPayload::Candidate(*id);
+                self.core.send(next, session, candidate)
+            }
+            ChangRobertsPayload::Candidate(_) => ContinuationHandler::Done,
+            ChangRobertsPayload::Elected(leader, messages) => {
+                let elected = ChangRobertsPayload::Elected;
+                self.core.elected(session, *leader, *messages, elected)
+            }
+        };
+
+        res.and_then(cont)
+    }
+}
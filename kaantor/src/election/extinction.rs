@@ -0,0 +1,157 @@
+use super::Leader;
+use crate::{
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The payload of the echo algorithm with extinction.
+#[derive(Clone, PayloadDebug)]
+pub enum ExtinctionPayload {
+    /// Makes the node an initiator.
+    Start,
+    /// A message of the wave started by the given initiator.
+    Wave(ActorId),
+    /// Announces the leader.
+    Elected(ActorId),
+}
+
+/// The echo algorithm with extinction, on any graph. Every initiator starts
+/// an echo wave tagged with its identifier. A node joins the wave with the
+/// largest identifier it has seen so far, which extinguishes the others, and
+/// a node which is not an initiator joins the first wave it gets. The only wave
+/// to complete is the one of the largest initiator, which becomes the leader
+/// and floods its identifier to all the nodes.
+pub struct Extinction {
+    aid: ActorId,
+    wave: Option<ActorId>,
+    parent: Option<ActorId>,
+    pending: usize,
+    leader: Option<ActorId>,
+    results: Option<UnboundedSender<Leader>>,
+}
+
+impl Extinction {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            wave: None,
+            parent: None,
+            pending: 0,
+            leader: None,
+            results: None,
+        }
+    }
+
+    /// Sends the leader to the channel, from every node, once it is known.
+    pub fn with_results(mut self, results: UnboundedSender<Leader>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Returns the leader, once it is known.
+    pub fn leader(&self) -> Option<ActorId> {
+        self.leader
+    }
+
+    fn build_msg(
+        &self,
+        session: Session,
+        payload: ExtinctionPayload,
+    ) -> Message<ExtinctionPayload> {
+        Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build()
+    }
+
+    /// Joins the wave, forwarding it to all the neighbours but the parent.
+    fn join(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        wave: ActorId,
+        parent: Option<ActorId>,
+    ) -> ContinuationHandler<ExtinctionPayload> {
+        self.wave = Some(wave);
+        self.parent = parent;
+        self.pending = ns.filter(|n| Some(*n) != parent).count();
+
+        let msg = self.build_msg(session, ExtinctionPayload::Wave(wave));
+        let except = parent.into_iter().collect();
+        ContinuationHandler::SendToAllNodesExcept(msg, except).and_then(self.check_done(session))
+    }
+
+    fn check_done(&mut self, session: Session) -> ContinuationHandler<ExtinctionPayload> {
+        if self.pending != 0 {
+            return ContinuationHandler::Done;
+        }
+
+        let wave = self.wave.expect("the node joined a wave");
+        match self.parent {
+            Some(pid) => {
+                let echo = self.build_msg(session, ExtinctionPayload::Wave(wave));
+                ContinuationHandler::SendToNode(pid, echo)
+            }
+            None => {
+                self.elect(session, wave);
+                let elected = self.build_msg(session, ExtinctionPayload::Elected(wave));
+                ContinuationHandler::SendToAllNodes(elected)
+            }
+        }
+    }
+
+    fn elect(&mut self, session: Session, leader: ActorId) {
+        log::info!("LEADER | on {:?} | {:?} | {:?}", self.aid, session, leader);
+
+        self.leader = Some(leader);
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Leader {
+                aid: self.aid,
+                session,
+                leader,
+            });
+        }
+    }
+}
+
+impl ProtocolHandler for Extinction {
+    type Payload = ExtinctionPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let sender = msg.sender().as_aid();
+
+        match *msg.payload() {
+            ExtinctionPayload::Start if self.wave.is_none() => {
+                self.join(ns, session, self.aid, None)
+            }
+            ExtinctionPayload::Start => ContinuationHandler::Done,
+            ExtinctionPayload::Wave(wave) if self.wave.is_none_or(|w| w < wave) => {
+                self.join(ns, session, wave, Some(sender))
+            }
+            ExtinctionPayload::Wave(wave) if self.wave == Some(wave) => {
+                self.pending -= 1;
+                self.check_done(session)
+            }
+            ExtinctionPayload::Wave(_) => ContinuationHandler::Done,
+            ExtinctionPayload::Elected(leader) if self.leader.is_none() => {
+                self.elect(session, leader);
+                let elected = self.build_msg(session, ExtinctionPayload::Elected(leader));
+                ContinuationHandler::SendToAllNodesExcept(elected, vec![sender])
+            }
+            ExtinctionPayload::Elected(_) => ContinuationHandler::Done,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::{Election, RingCore};
+use crate::{
+    graph::Ring, protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The direction in which a message travels around the ring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    /// Towards the next node.
+    Next,
+    /// Towards the previous node.
+    Prev,
+}
+
+impl Direction {
+    /// Returns the opposite direction.
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Next => Self::Prev,
+            Self::Prev => Self::Next,
+        }
+    }
+}
+
+/// The payload of the Hirschberg–Sinclair algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum HirschbergSinclairPayload {
+    /// Makes the node a candidate.
+    Start,
+    /// Probes the neighbourhood of a candidate, in a given round.
+    Probe {
+        /// The candidate.
+        id: ActorId,
+        /// The round of the candidate.
+        round: u32,
+        /// The distance travelled so far.
+        hops: usize,
+        /// The direction of the probe.
+        dir: Direction,
+    },
+    /// Tells the candidate its probe travelled as far as required.
+    Reply {
+        /// The candidate.
+        id: ActorId,
+        /// The direction of the reply.
+        dir: Direction,
+    },
+    /// Announces the leader.
+    Elected(ActorId, usize),
+}
+
+/// The Hirschberg–Sinclair algorithm, on a bidirectional ring. In round `k`, a
+/// candidate probes the nodes at distance up to `2^k` in both directions. The
+/// probe is dropped by the nodes with a larger identifier, and the candidate
+/// goes on with the next round once both probes were answered. The node whose
+/// probes travel all around the ring is elected.
+pub struct HirschbergSinclair {
+    core: RingCore,
+    candidate: bool,
+    round: u32,
+    replies: usize,
+    returned: usize,
+}
+
+impl HirschbergSinclair {
+    /// Creates the handler for the node `aid` of the ring.
+    pub fn new(aid: ActorId, ring: &Ring) -> Self {
+        Self {
+            core: RingCore::new(aid, ring),
+            candidate: false,
+            round: 0,
+            replies: 0,
+            returned: 0,
+        }
+    }
+
+    /// Sends the outcome of the election to the channel, from the leader.
+    pub fn with_results(mut self, results: UnboundedSender<Election>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+
+    fn send_to(
+        &mut self,
+        dir: Direction,
+        msg: &Message<HirschbergSinclairPayload>,
+        payload: HirschbergSinclairPayload,
+    ) -> ContinuationHandler<HirschbergSinclairPayload> {
+        let to = match dir {
+            Direction::Next => self.core.next,
+            Direction::Prev => self.core.prev,
+        };
+        self.core.send(to, *msg.session(), payload)
+    }
+
+    /// Sends the probes of the current round in both directions.
+    fn probe(
+        &mut self,
+        msg: &Message<HirschbergSinclairPayload>,
+    ) -> ContinuationHandler<HirschbergSinclairPayload> {
+        [Direction::Next, Direction::Prev].into_iter().fold(
+            ContinuationHandler::Done,
+            |res, dir| {
+                let probe = HirschbergSinclairPayload::Probe {
+                    id: self.core.aid,
+                    round: self.round,
+                    hops: 1,
+                    dir,
+                };
+                res.and_then(self.send_to(dir, msg, probe))
+            },
+        )
+    }
+}
+
+impl ProtocolHandler for HirschbergSinclair {
+    type Payload = HirschbergSinclairPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let aid = self.core.aid;
+
+        let mut res = ContinuationHandler::Done;
+        if !self.candidate && self.core.leader.is_none() {
+            self.candidate = true;
+            res = self.probe(&msg);
+        }
+
+        let cont = match *msg.payload() {
+            HirschbergSinclairPayload::Start => ContinuationHandler::Done,
+            HirschbergSinclairPayload::Probe { id, .. } if id == aid => {
+                // The probes went all around the ring, in both directions.
+                self.returned += 1;
+                if self.returned == 2 {
+                    let elected = HirschbergSinclairPayload::Elected;
+                    self.core.elect(*msg.session(), aid, elected)
+                } else {
+                    ContinuationHandler::Done
+                }
+            }
+            HirschbergSinclairPayload::Probe {
+                id,
+                round,
+                hops,
+                dir,
+            } if id > aid => {
+                if hops < 1 << round {
+                    let probe = HirschbergSinclairPayload::Probe {
+                        id,
+                        round,
+                        hops: hops + 1,
+                        dir,
+                    };
+                    self.send_to(dir, &msg, probe)
+                } else {
+                    let dir = dir.opposite();
+                    let reply = HirschbergSinclairPayload::Reply { id, dir };
+                    self.send_to(dir, &msg, reply)
+                }
+            }
+            HirschbergSinclairPayload::Probe { .. } => ContinuationHandler::Done,
+            HirschbergSinclairPayload::Reply { id, dir } if id != aid => {
+                let reply = HirschbergSinclairPayload::Reply { id, dir };
+                self.send_to(dir, &msg, reply)
+            }
+            HirschbergSinclairPayload::Reply { .. } => {
+                self.replies += 1;
+                if self.replies == 2 {
+                    self.replies = 0;
+                    self.round += 1;
+                    self.probe(&msg)
+                } else {
+                    ContinuationHandler::Done
+                }
+            }
+            HirschbergSinclairPayload::Elected(leader, messages) => {
+                let elected = HirschbergSinclairPayload::Elected;
+                self.core.elected(*msg.session(), leader, messages, elected)
+            }
+        };
+
+        res.and_then(cont)
+    }
+}
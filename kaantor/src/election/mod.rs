@@ -0,0 +1,285 @@
+//! Leader election algorithms.
+//!
+//! The ring algorithms elect the node with the largest identifier. Once the
+//! leader is known, it is announced around the ring, so every node learns it,
+//! and the node which announced it reports the outcome of the election with
+//! the number of messages the nodes sent before they learned the leader. The
+//! announcement itself, one message per node, is not counted.
+//!
+//! The algorithms for arbitrary graphs report the leader from every node,
+//! once the node learned it.
+
+mod bully;
+mod chang_roberts;
+mod extinction;
+mod hirschberg_sinclair;
+mod peterson;
+
+pub use bully::*;
+pub use chang_roberts::*;
+pub use extinction::*;
+pub use hirschberg_sinclair::*;
+pub use peterson::*;
+
+use crate::{
+    graph::Ring,
+    protocol::{Builder, Session},
+    ActorId, ContinuationHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The outcome of an election.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Election {
+    /// The session of the election.
+    pub session: Session,
+    /// The elected node.
+    pub leader: ActorId,
+    /// The number of messages sent to elect the leader.
+    pub messages: usize,
+}
+
+/// The leader learned by a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leader {
+    /// The node which learned the leader.
+    pub aid: ActorId,
+    /// The session of the election.
+    pub session: Session,
+    /// The elected node.
+    pub leader: ActorId,
+}
+
+/// The state shared by the ring election algorithms.
+struct RingCore {
+    aid: ActorId,
+    next: ActorId,
+    prev: ActorId,
+    messages: usize,
+    leader: Option<ActorId>,
+    announcer: bool,
+    results: Option<UnboundedSender<Election>>,
+}
+
+impl RingCore {
+    fn new(aid: ActorId, ring: &Ring) -> Self {
+        Self {
+            aid,
+            next: ring.next(aid),
+            prev: ring.prev(aid),
+            messages: 0,
+            leader: None,
+            announcer: false,
+            results: None,
+        }
+    }
+
+    fn build_msg<P>(&self, to: ActorId, session: Session, payload: P) -> ContinuationHandler<P> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Sends a message of the election, counting it.
+    fn send<P>(&mut self, to: ActorId, session: Session, payload: P) -> ContinuationHandler<P> {
+        self.messages += 1;
+        self.build_msg(to, session, payload)
+    }
+
+    /// Starts the announcement of the leader around the ring.
+    fn elect<P>(
+        &mut self,
+        session: Session,
+        leader: ActorId,
+        elected: impl Fn(ActorId, usize) -> P,
+    ) -> ContinuationHandler<P> {
+        log::info!("LEADER | on {:?} | {:?} | {:?}", self.aid, session, leader);
+
+        self.leader = Some(leader);
+        self.announcer = true;
+        self.build_msg(self.next, session, elected(leader, self.messages))
+    }
+
+    /// Handles the announcement of the leader, which is forwarded around
+    /// the ring until it is back to the node which started it.
+    fn elected<P>(
+        &mut self,
+        session: Session,
+        leader: ActorId,
+        messages: usize,
+        elected: impl Fn(ActorId, usize) -> P,
+    ) -> ContinuationHandler<P> {
+        if !self.announcer {
+            self.leader = Some(leader);
+            let messages = messages + self.messages;
+            return self.build_msg(self.next, session, elected(leader, messages));
+        }
+
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Election {
+                session,
+                leader,
+                messages,
+            });
+        }
+        ContinuationHandler::Done
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor, ProtocolHandler};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    async fn elect<H, P>(
+        ring: Ring,
+        build: impl Fn(ActorId, &Ring, UnboundedSender<Election>) -> H,
+        start: P,
+    ) -> Election
+    where
+        H: ProtocolHandler<Payload = P> + Unpin + 'static,
+        P: Clone + Send + std::fmt::Debug + 'static,
+    {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=ring.len())
+            .map(|i| NodeActor::build(build(i.into(), &ring, tx.clone())))
+            .collect();
+
+        for (a, b) in ring.topology().edges() {
+            let (a, b) = (a.inner().min(b.inner()), a.inner().max(b.inner()));
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for node in nodes.iter_mut() {
+            let msg = Builder::with_from_api()
+                .with_to_actor(node.aid())
+                .with_session(session)
+                .with_payload(start.clone())
+                .with_sender(node.aid())
+                .build();
+            node.do_send(msg);
+        }
+
+        actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn ring_elections() {
+        let n = 8;
+        let ascending = Ring::with_size(n);
+        let descending = Ring::new((1..=n).rev().map(ActorId::from).collect());
+        let leader = ActorId::from(n);
+
+        // Every candidate is dropped by its next node, but the largest one.
+        let cr = |aid, ring: &Ring, tx| ChangRoberts::new(aid, ring).with_results(tx);
+        let res = elect(ascending.clone(), cr, ChangRobertsPayload::Start).await;
+        assert_eq!((leader, 2 * n - 1), (res.leader, res.messages));
+
+        // Every candidate goes as far as the largest node.
+        let res = elect(descending.clone(), cr, ChangRobertsPayload::Start).await;
+        assert_eq!((leader, n * (n + 1) / 2), (res.leader, res.messages));
+
+        let hs = |aid, ring: &Ring, tx| HirschbergSinclair::new(aid, ring).with_results(tx);
+        for ring in [ascending.clone(), descending.clone()] {
+            let res = elect(ring, hs, HirschbergSinclairPayload::Start).await;
+            assert_eq!(leader, res.leader);
+            assert!(res.messages <= 8 * n * 4);
+        }
+
+        // Two messages per node and per round, and at least half of the
+        // candidates drop out at every round.
+        let peterson = |aid, ring: &Ring, tx| Peterson::new(aid, ring).with_results(tx);
+        for ring in [ascending, descending] {
+            let res = elect(ring, peterson, PetersonPayload::Start).await;
+            assert_eq!(leader, res.leader);
+            assert!(res.messages <= 2 * n * 4 + n);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn graph_elections() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let session = SessionAllocator::for_api().next_session();
+
+        async fn leaders(
+            rx: &mut futures::channel::mpsc::UnboundedReceiver<Leader>,
+            n: usize,
+        ) -> Vec<Leader> {
+            let mut leaders = vec![];
+            for _ in 0..n {
+                let leader = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                leaders.push(leader);
+            }
+            leaders.sort_by_key(|l| l.aid);
+            leaders
+        }
+
+        // Nodes 1, 3 and 4 start a wave, and the wave of node 4 wins.
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| NodeActor::build(Extinction::new(i.into()).with_results(tx.clone())))
+            .collect();
+        for (a, b) in [(0, 1), (0, 2), (1, 3), (3, 4), (2, 4)] {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[a], &mut right[0]).await;
+        }
+        for i in [0, 2, 3] {
+            let aid = nodes[i].aid();
+            let msg = Builder::with_from_api()
+                .with_to_actor(aid)
+                .with_session(session)
+                .with_payload(ExtinctionPayload::Start)
+                .with_sender(aid)
+                .build();
+            nodes[i].do_send(msg);
+        }
+        let expected: Vec<_> = (1..=5)
+            .map(|i| Leader {
+                aid: i.into(),
+                session,
+                leader: 4.into(),
+            })
+            .collect();
+        assert_eq!(expected, leaders(&mut rx, 5).await);
+
+        // Node 5 crashes, so node 4 is elected by the nodes still alive.
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| {
+                let bully = Bully::new(i.into(), Duration::from_millis(50));
+                NodeActor::build(bully.with_results(tx.clone()))
+            })
+            .collect();
+        for b in 1..5 {
+            for a in 0..b {
+                let (left, right) = nodes.split_at_mut(b);
+                add_edge(&mut left[a], &mut right[0]).await;
+            }
+        }
+        for (i, payload) in [(4, BullyPayload::Crash), (0, BullyPayload::Start)] {
+            let aid = nodes[i].aid();
+            let msg = Builder::with_from_api()
+                .with_to_actor(aid)
+                .with_session(session)
+                .with_payload(payload)
+                .with_sender(aid)
+                .build();
+            nodes[i].do_send(msg);
+        }
+        assert_eq!(expected[..4], leaders(&mut rx, 4).await);
+    }
+}
@@ -0,0 +1,112 @@
+use super::{Election, RingCore};
+use crate::{
+    graph::Ring, protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The payload of Peterson's algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum PetersonPayload {
+    /// Makes the node active.
+    Start,
+    /// The identifier an active node stands for.
+    First(ActorId),
+    /// The identifier the previous active node stands for.
+    Second(ActorId),
+    /// Announces the leader.
+    Elected(ActorId, usize),
+}
+
+/// Peterson's algorithm, on a unidirectional ring. Every active node stands
+/// for an identifier. In each round, it sends its identifier to the next
+/// node, then it forwards the identifier of the previous active node. It stays
+/// active, standing for the identifier of the previous active node, only if
+/// that identifier is larger than both its own and the one before. The other
+/// nodes become relays. An active node which gets its own identifier back is
+/// the last one, and the identifier it stands for is the leader.
+pub struct Peterson {
+    core: RingCore,
+    started: bool,
+    active: bool,
+    tid: ActorId,
+    ntid: Option<ActorId>,
+}
+
+impl Peterson {
+    /// Creates the handler for the node `aid` of the ring.
+    pub fn new(aid: ActorId, ring: &Ring) -> Self {
+        Self {
+            core: RingCore::new(aid, ring),
+            started: false,
+            active: false,
+            tid: aid,
+            ntid: None,
+        }
+    }
+
+    /// Sends the outcome of the election to the channel, from the leader.
+    pub fn with_results(mut self, results: UnboundedSender<Election>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+}
+
+impl ProtocolHandler for Peterson {
+    type Payload = PetersonPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let next = self.core.next;
+
+        let mut res = ContinuationHandler::Done;
+        if !self.started && self.core.leader.is_none() {
+            self.started = true;
+            self.active = true;
+            res = self
+                .core
+                .send(next, session, PetersonPayload::First(self.tid));
+        }
+
+        let cont = match msg.payload() {
+            PetersonPayload::Start => ContinuationHandler::Done,
+            PetersonPayload::Elected(leader, messages) => {
+                let elected = PetersonPayload::Elected;
+                self.core.elected(session, *leader, *messages, elected)
+            }
+            PetersonPayload::First(id) if !self.active => {
+                self.core.send(next, session, PetersonPayload::First(*id))
+            }
+            PetersonPayload::Second(id) if !self.active => {
+                self.core.send(next, session, PetersonPayload::Second(*id))
+            }
+            PetersonPayload::First(id) if *id == self.tid => {
+                self.active = false;
+                self.core.elect(session, *id, PetersonPayload::Elected)
+            }
+            PetersonPayload::First(id) => {
+                self.ntid = Some(*id);
+                self.core.send(next, session, PetersonPayload::Second(*id))
+            }
+            PetersonPayload::Second(id) => {
+                let ntid = self.ntid.take().expect("the first identifier came before");
+                if ntid > self.tid && ntid > *id {
+                    self.tid = ntid;
+                    self.core.send(next, session, PetersonPayload::First(ntid))
+                } else {
+                    self.active = false;
+                    ContinuationHandler::Done
+                }
+            }
+        };
+
+        res.and_then(cont)
+    }
+}
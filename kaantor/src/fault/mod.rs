@@ -0,0 +1,354 @@
+//! Fault injection around any protocol handler.
+//!
+//! A `Faults` handler wraps a handler, which runs unchanged, and the API can
+//! crash and restart its node, or cut the links between its node and others to
+//! partition the graph. A crashed node drops every message, including the ones
+//! it scheduled, and the handler is told when its node restarts, to drop its
+//! volatile state. The state the handler keeps survives, as on a stable storage.
+//!
+//! The node can also lose or duplicate the messages it receives from the other
+//! nodes, at random but reproducibly, with the probabilities given to
+//! `with_loss` and `with_duplication`.
+
+use crate::{
+    protocol::{FromId, Message, Session},
+    ActorId, ContinuationHandler, NeighbourError, PayloadDebug, ProtocolHandler,
+};
+use log::debug;
+use std::collections::BTreeSet;
+
+/// A handler which can recover from a crash of its node.
+pub trait Recover: ProtocolHandler {
+    /// Handles the restart of the node, after a crash.
+    fn recover(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        session: Session,
+    ) -> ContinuationHandler<Self::Payload>;
+}
+
+/// The payload of a `Faults` handler, on top of the payload `P`.
+#[derive(Clone, PayloadDebug)]
+pub enum FaultPayload<P> {
+    /// A payload of the handler.
+    App(P),
+    /// Sent by the API to crash the node.
+    Crash,
+    /// Sent by the API to restart the crashed node.
+    Restart,
+    /// Sent by the API to cut the links between the node and the given nodes.
+    Isolate(Vec<ActorId>),
+    /// Sent by the API to restore all the links of the node.
+    Heal,
+}
+
+type FaultCont<P> = ContinuationHandler<FaultPayload<P>>;
+
+/// A xorshift generator, seeded by a node to draw reproducible numbers.
+pub(crate) struct Xorshift(u64);
+
+impl Xorshift {
+    pub(crate) fn new(aid: ActorId) -> Self {
+        Self((aid.inner() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Draws a number between 0 and 1.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Injects faults in the node of the handler `H`.
+pub struct Faults<H> {
+    inner: H,
+    crashed: bool,
+    cut: BTreeSet<ActorId>,
+    loss: f64,
+    duplication: f64,
+    rng: Xorshift,
+}
+
+impl<H> Faults<H>
+where
+    H: Recover,
+    H::Payload: Clone,
+{
+    /// Creates a new handler injecting faults in the node of `inner`.
+    pub fn new(inner: H) -> Self {
+        let rng = Xorshift::new(inner.aid());
+        Self {
+            inner,
+            crashed: false,
+            cut: Default::default(),
+            loss: 0.0,
+            duplication: 0.0,
+            rng,
+        }
+    }
+
+    /// Loses every message from another node with the probability `loss`.
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    /// Handles twice every message from another node which is not lost,
+    /// with the probability `duplication`.
+    pub fn with_duplication(mut self, duplication: f64) -> Self {
+        self.duplication = duplication;
+        self
+    }
+
+    /// Gets the handler the faults are injected in.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Drops the messages sent over the links which are cut.
+    fn filter(
+        &self,
+        ns: &[ActorId],
+        res: ContinuationHandler<H::Payload>,
+    ) -> FaultCont<H::Payload> {
+        // Only the neighbours are excluded, the others would be errors.
+        let cut = |except: &mut Vec<ActorId>| {
+            for n in ns {
+                if self.cut.contains(n) && !except.contains(n) {
+                    except.push(*n);
+                }
+            }
+        };
+
+        match res {
+            ContinuationHandler::SendToNode(to, _) if self.cut.contains(&to) => {
+                debug!("FALT | on {:?} | drop to {:?}", self.inner.aid(), to);
+                ContinuationHandler::Done
+            }
+            ContinuationHandler::SendToAllNodes(msg) if !self.cut.is_empty() => {
+                self.filter(ns, ContinuationHandler::SendToAllNodesExcept(msg, vec![]))
+            }
+            ContinuationHandler::SendToAllNodesExcept(msg, mut except) => {
+                cut(&mut except);
+                ContinuationHandler::SendToAllNodesExcept(
+                    msg.map_payload(FaultPayload::App),
+                    except,
+                )
+            }
+            ContinuationHandler::Sequence(conts) => ContinuationHandler::Sequence(
+                conts.into_iter().map(|c| self.filter(ns, c)).collect(),
+            ),
+            res => res.map(FaultPayload::App),
+        }
+    }
+}
+
+impl<H> ProtocolHandler for Faults<H>
+where
+    H: Recover,
+    H::Payload: Clone,
+{
+    type Payload = FaultPayload<H::Payload>;
+
+    fn aid(&self) -> ActorId {
+        self.inner.aid()
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let aid = self.inner.aid();
+        let from = msg.sender().as_aid();
+
+        match msg.payload() {
+            FaultPayload::App(_) if self.crashed || self.cut.contains(&from) => {
+                debug!("FALT | on {:?} | drop from {:?}", aid, from);
+                ContinuationHandler::Done
+            }
+            FaultPayload::App(_) => {
+                let msg = msg.map_payload(|p| match p {
+                    FaultPayload::App(p) => p,
+                    _ => unreachable!(),
+                });
+                // The messages of the API and the ones the node scheduled are kept.
+                let remote = matches!(msg.from(), FromId::Actor(_)) && from != aid;
+                if remote && self.rng.unit() < self.loss {
+                    debug!("FALT | on {:?} | lose from {:?}", aid, from);
+                    return ContinuationHandler::Done;
+                }
+                if remote && self.rng.unit() < self.duplication {
+                    debug!("FALT | on {:?} | duplicate from {:?}", aid, from);
+                    let first = self.inner.receive(ns.iter().copied(), msg.clone());
+                    let second = self.inner.receive(ns.iter().copied(), msg);
+                    let res = ContinuationHandler::Sequence(vec![first, second]);
+                    return self.filter(&ns, res);
+                }
+                let res = self.inner.receive(ns.iter().copied(), msg);
+                self.filter(&ns, res)
+            }
+            FaultPayload::Crash => {
+                debug!("FALT | on {:?} | crash", aid);
+                self.crashed = true;
+                ContinuationHandler::Done
+            }
+            FaultPayload::Restart if self.crashed => {
+                debug!("FALT | on {:?} | restart", aid);
+                self.crashed = false;
+                let res = self.inner.recover(ns.iter().copied(), *msg.session());
+                self.filter(&ns, res)
+            }
+            FaultPayload::Restart => ContinuationHandler::Done,
+            FaultPayload::Isolate(nodes) => {
+                debug!("FALT | on {:?} | isolate from {:?}", aid, nodes);
+                self.cut.extend(nodes);
+                ContinuationHandler::Done
+            }
+            FaultPayload::Heal => {
+                debug!("FALT | on {:?} | heal", aid);
+                self.cut.clear();
+                ContinuationHandler::Done
+            }
+        }
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let res = self.inner.on_neighbour_error(ns.iter().copied(), err);
+        self.filter(&ns, res)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, SessionAllocator},
+        NodeActor,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    enum EchoPayload {
+        Ping(usize),
+        Pong(usize),
+    }
+
+    /// Answers the pings of the API, through its neighbour.
+    struct Echo {
+        aid: ActorId,
+        results: UnboundedSender<usize>,
+    }
+
+    impl ProtocolHandler for Echo {
+        type Payload = EchoPayload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: Message<EchoPayload>,
+        ) -> ContinuationHandler<EchoPayload> {
+            let msg = match *msg.payload() {
+                EchoPayload::Ping(i) => Builder::with_from_actor(self.aid)
+                    .with_to_all_actors()
+                    .with_session(*msg.session())
+                    .with_payload(EchoPayload::Pong(i))
+                    .with_sender(self.aid)
+                    .build(),
+                EchoPayload::Pong(i) => {
+                    let _ = self.results.unbounded_send(i);
+                    return ContinuationHandler::Done;
+                }
+            };
+            ContinuationHandler::SendToAllNodes(msg)
+        }
+    }
+
+    impl Recover for Echo {
+        fn recover(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            _session: Session,
+        ) -> ContinuationHandler<EchoPayload> {
+            let _ = self.results.unbounded_send(0);
+            ContinuationHandler::Done
+        }
+    }
+
+    fn api(aid: ActorId, payload: FaultPayload<EchoPayload>) -> Message<FaultPayload<EchoPayload>> {
+        Builder::with_from_api()
+            .with_to_actor(aid)
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(payload)
+            .with_sender(aid)
+            .build()
+    }
+
+    #[actix_rt::test]
+    async fn faults() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes: Vec<_> = (1..=2)
+            .map(|i| {
+                NodeActor::build(Faults::new(Echo {
+                    aid: i.into(),
+                    results: tx.clone(),
+                }))
+            })
+            .collect();
+        let (left, right) = nodes.split_at_mut(1);
+        add_edge(&mut left[0], &mut right[0]).await;
+
+        let (a1, a2) = (ActorId::from(1), ActorId::from(2));
+        let ping = |i| api(a1, FaultPayload::App(EchoPayload::Ping(i)));
+
+        // The pongs sent while the link is cut or the node is crashed are lost.
+        // Every message is handled before the next one is sent, and a pong is
+        // queued by the node 2 before the message handled after its ping.
+        let steps = [
+            (0, ping(1)),
+            (0, api(a1, FaultPayload::Isolate(vec![a2]))),
+            (0, ping(2)),
+            (0, api(a1, FaultPayload::Heal)),
+            (0, ping(3)),
+            (1, api(a2, FaultPayload::Crash)),
+            (0, ping(4)),
+            (1, api(a2, FaultPayload::Restart)),
+            (0, ping(5)),
+        ];
+        for (i, msg) in steps {
+            nodes[i].send(msg).await.unwrap();
+        }
+
+        let mut received = vec![];
+        for _ in 0..4 {
+            let i = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push(i);
+        }
+        assert_eq!(vec![1, 3, 0, 5], received);
+    }
+}
@@ -1,5 +1,11 @@
 //! Graph of nodes
 //!
+mod ring;
+mod topology;
+
+pub use ring::*;
+pub use topology::*;
+
 use std::fmt::Debug;
 
 use actix::prelude::*;
@@ -0,0 +1,90 @@
+//! Rings of nodes.
+
+use super::Topology;
+use crate::ActorId;
+
+/// A ring of nodes, with an orientation. The edges of the graph are
+/// bi-directional, so the ring tells every node which of its two
+/// neighbours is the next one and which one is the previous one.
+#[derive(Debug, Clone)]
+pub struct Ring {
+    order: Vec<ActorId>,
+}
+
+impl Ring {
+    /// Creates a ring which visits the nodes in the given order.
+    pub fn new(order: Vec<ActorId>) -> Self {
+        assert!(order.len() >= 2, "a ring has at least two nodes");
+        Self { order }
+    }
+
+    /// Creates a ring of `n` nodes, with the identifiers from 1 to `n`
+    /// increasing in the direction of the ring.
+    pub fn with_size(n: usize) -> Self {
+        Self::new((1..=n).map(ActorId::from).collect())
+    }
+
+    /// Returns the nodes, in the order of the ring.
+    pub fn nodes(&self) -> &[ActorId] {
+        &self.order
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns `true` if the ring has no nodes, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    fn position(&self, aid: ActorId) -> usize {
+        self.order
+            .iter()
+            .position(|n| *n == aid)
+            .unwrap_or_else(|| panic!("{aid:?} is not part of the ring"))
+    }
+
+    /// Returns the node which follows the given one.
+    pub fn next(&self, aid: ActorId) -> ActorId {
+        let i = self.position(aid);
+        self.order[(i + 1) % self.order.len()]
+    }
+
+    /// Returns the node which precedes the given one.
+    pub fn prev(&self, aid: ActorId) -> ActorId {
+        let i = self.position(aid);
+        self.order[(i + self.order.len() - 1) % self.order.len()]
+    }
+
+    /// Returns the edges of the ring.
+    pub fn topology(&self) -> Topology {
+        let next = self.order.iter().cycle().skip(1);
+        self.order
+            .iter()
+            .zip(next)
+            .take(if self.order.len() == 2 {
+                1
+            } else {
+                self.order.len()
+            })
+            .fold(Topology::new(), |t, (a, b)| t.with_edge(*a, *b))
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn ring_() {
+        let ring = Ring::new(vec![3.into(), 1.into(), 2.into()]);
+
+        assert_eq!(ActorId::from(1), ring.next(3.into()));
+        assert_eq!(ActorId::from(3), ring.next(2.into()));
+        assert_eq!(ActorId::from(2), ring.prev(3.into()));
+        assert_eq!(3, ring.topology().edges().count());
+        assert_eq!(1, Ring::with_size(2).topology().edges().count());
+    }
+}
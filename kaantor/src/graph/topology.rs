@@ -0,0 +1,98 @@
+//! The description of the communication graph.
+
+use crate::ActorId;
+
+/// Describes the nodes and the bi-directional edges of a communication graph.
+/// The description is used to establish the connections between the nodes
+/// when they are not created by calling `add_edge` from the same process.
+/// The edges have a weight, 1 unless said otherwise, for the algorithms
+/// which need one.
+#[derive(Debug, Default, Clone)]
+pub struct Topology {
+    edges: Vec<(ActorId, ActorId, u64)>,
+}
+
+impl Topology {
+    /// Creates a new topology without any edge.
+    pub fn new() -> Self {
+        Self { edges: vec![] }
+    }
+
+    /// Adds a bi-directional edge between two nodes.
+    pub fn with_edge(mut self, a: ActorId, b: ActorId) -> Self {
+        self.add_edge(a, b);
+        self
+    }
+
+    /// Adds a bi-directional edge between two nodes.
+    pub fn add_edge(&mut self, a: ActorId, b: ActorId) {
+        self.add_weighted_edge(a, b, 1)
+    }
+
+    /// Adds a bi-directional edge with a weight between two nodes.
+    pub fn with_weighted_edge(mut self, a: ActorId, b: ActorId, weight: u64) -> Self {
+        self.add_weighted_edge(a, b, weight);
+        self
+    }
+
+    /// Adds a bi-directional edge with a weight between two nodes.
+    pub fn add_weighted_edge(&mut self, a: ActorId, b: ActorId, weight: u64) {
+        self.edges.push((a, b, weight));
+    }
+
+    /// Returns the weight of the edge between two nodes, if any.
+    pub fn weight(&self, a: ActorId, b: ActorId) -> Option<u64> {
+        self.edges
+            .iter()
+            .find(|(x, y, _)| (*x, *y) == (a, b) || (*x, *y) == (b, a))
+            .map(|(_, _, w)| *w)
+    }
+
+    /// Returns the list of edges.
+    pub fn edges(&self) -> impl Iterator<Item = (ActorId, ActorId)> + '_ {
+        self.edges.iter().map(|(a, b, _)| (*a, *b))
+    }
+
+    /// Returns the sorted list of nodes which are part of at least one edge.
+    pub fn nodes(&self) -> Vec<ActorId> {
+        let mut nodes: Vec<_> = self.edges.iter().flat_map(|(a, b, _)| [*a, *b]).collect();
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Returns the neighbours of a given node, in the order the edges were added.
+    pub fn neighbours(&self, aid: ActorId) -> impl Iterator<Item = ActorId> + '_ {
+        self.edges.iter().filter_map(move |(a, b, _)| {
+            if *a == aid {
+                Some(*b)
+            } else if *b == aid {
+                Some(*a)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn neighbours_() {
+        let t = Topology::new()
+            .with_edge(1.into(), 2.into())
+            .with_edge(1.into(), 3.into())
+            .with_edge(3.into(), 2.into());
+
+        let ns: Vec<_> = t.neighbours(2.into()).collect();
+        assert_eq!(vec![ActorId::from(1), ActorId::from(3)], ns);
+        assert_eq!(3, t.nodes().len());
+        assert_eq!(Some(1), t.weight(2.into(), 3.into()));
+
+        let t = t.with_weighted_edge(2.into(), 4.into(), 7);
+        assert_eq!(Some(7), t.weight(4.into(), 2.into()));
+        assert_eq!(None, t.weight(1.into(), 4.into()));
+    }
+}
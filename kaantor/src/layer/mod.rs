@@ -0,0 +1,478 @@
+//! Protocol composition: several protocols running on the same node,
+//! layered one on top of the other.
+//!
+//! A lower layer offers a `Service` to the layer on top of it, its `Client`.
+//! The client asks the service to start on the current node and it is notified
+//! once the service produced its response on the node, e.g. a spanning tree
+//! construction followed by a convergecast over the resulting tree. The two
+//! layers are glued by a `Stack`, which is itself a `ProtocolHandler`, and
+//! which multiplexes the payloads of the two layers over the same links.
+//! Stacks can be nested when the upper layer offers a service of its own.
+
+use crate::{
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, NeighbourError, ProtocolHandler,
+};
+use std::fmt::Debug;
+
+/// The payload of a `Stack`, which belongs either to the lower or to the upper layer.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StackPayload<L, U> {
+    /// A payload of the lower layer.
+    Lower(L),
+    /// A payload of the upper layer.
+    Upper(U),
+}
+
+impl<L, U> Debug for StackPayload<L, U>
+where
+    L: Debug,
+    U: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lower(p) => write!(f, "LOWER | {p:?}"),
+            Self::Upper(p) => write!(f, "UPPER | {p:?}"),
+        }
+    }
+}
+
+/// A protocol which offers a service to the protocol layered on top of it.
+pub trait Service: ProtocolHandler {
+    /// The request which starts the service.
+    type Request;
+    /// The result the service produces on a node.
+    type Response;
+
+    /// Starts the service on the current node, as requested by the upper layer.
+    fn request(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        session: Session,
+        req: Self::Request,
+    ) -> ContinuationHandler<Self::Payload>;
+
+    /// Returns the response of the service on the current node, once it is
+    /// available. It is called after each message handled by the node.
+    fn response(&mut self) -> Option<Self::Response>;
+}
+
+/// A protocol which uses the service offered by the protocol layered beneath it.
+pub trait Client<S>: ProtocolHandler
+where
+    S: Service,
+{
+    /// Returns the requests for the lower layer. It is called after each
+    /// message handled by the node.
+    fn requests(&mut self) -> Vec<(Session, S::Request)>;
+
+    /// Handles the response produced by the lower layer on the current node.
+    fn on_response(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        resp: S::Response,
+    ) -> ContinuationHandler<Self::Payload>;
+}
+
+/// Two protocols running on the same node, the upper one being a client
+/// of the service offered by the lower one.
+pub struct Stack<L, U> {
+    lower: L,
+    upper: U,
+}
+
+impl<L, U> Stack<L, U>
+where
+    L: Service,
+    U: Client<L>,
+{
+    /// Creates a new stack from its two layers.
+    pub fn new(lower: L, upper: U) -> Self {
+        Self { lower, upper }
+    }
+
+    /// Gets the lower layer.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Gets the upper layer.
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Hands the requests of the upper layer to the lower one, and the
+    /// responses of the lower layer to the upper one, until neither
+    /// of the layers has anything left for the other one.
+    fn pump(
+        &mut self,
+        ns: &[ActorId],
+        mut res: ContinuationHandler<StackPayload<L::Payload, U::Payload>>,
+    ) -> ContinuationHandler<StackPayload<L::Payload, U::Payload>> {
+        loop {
+            let reqs = self.upper.requests();
+            let resp = self.lower.response();
+
+            if reqs.is_empty() && resp.is_none() {
+                return res;
+            }
+
+            for (session, req) in reqs {
+                let cont = self.lower.request(ns.iter().copied(), session, req);
+                res = res.and_then(cont.map(StackPayload::Lower));
+            }
+
+            if let Some(resp) = resp {
+                let cont = self.upper.on_response(ns.iter().copied(), resp);
+                res = res.and_then(cont.map(StackPayload::Upper));
+            }
+        }
+    }
+}
+
+impl<L, U> ProtocolHandler for Stack<L, U>
+where
+    L: Service,
+    U: Client<L>,
+{
+    type Payload = StackPayload<L::Payload, U::Payload>;
+
+    fn aid(&self) -> ActorId {
+        self.lower.aid()
+    }
+
+    fn receive(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = neighbours.collect();
+
+        let res = match msg.payload() {
+            StackPayload::Lower(_) => {
+                let msg = msg.map_payload(|p| match p {
+                    StackPayload::Lower(p) => p,
+                    StackPayload::Upper(_) => unreachable!(),
+                });
+                let cont = self.lower.receive(ns.iter().copied(), msg);
+                cont.map(StackPayload::Lower)
+            }
+            StackPayload::Upper(_) => {
+                let msg = msg.map_payload(|p| match p {
+                    StackPayload::Upper(p) => p,
+                    StackPayload::Lower(_) => unreachable!(),
+                });
+                let cont = self.upper.receive(ns.iter().copied(), msg);
+                cont.map(StackPayload::Upper)
+            }
+        };
+
+        self.pump(&ns, res)
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        // The error does not tell which layer sent the message.
+        let ns: Vec<_> = neighbours.collect();
+        let lower = self
+            .lower
+            .on_neighbour_error(ns.iter().copied(), err.clone());
+        let upper = self.upper.on_neighbour_error(ns.iter().copied(), err);
+        let res = lower
+            .map(StackPayload::Lower)
+            .and_then(upper.map(StackPayload::Upper));
+
+        self.pump(&ns, res)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.lower.is_finished() && self.upper.is_finished()
+    }
+}
+
+impl<L, U> Service for Stack<L, U>
+where
+    L: Service,
+    U: Client<L> + Service,
+{
+    type Request = U::Request;
+    type Response = U::Response;
+
+    fn request(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        session: Session,
+        req: Self::Request,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = neighbours.collect();
+        let res = self.upper.request(ns.iter().copied(), session, req);
+        self.pump(&ns, res.map(StackPayload::Upper))
+    }
+
+    fn response(&mut self) -> Option<Self::Response> {
+        self.upper.response()
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, SessionAllocator},
+        NodeActor,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    fn build_msg<P>(aid: ActorId, session: Session, payload: P) -> Message<P> {
+        Builder::with_from_actor(aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(aid)
+            .build()
+    }
+
+    #[derive(Debug, Clone)]
+    enum TreePayload {
+        Go,
+        Back(bool),
+    }
+
+    struct Tree {
+        session: Session,
+        parent: Option<ActorId>,
+        children: Vec<ActorId>,
+    }
+
+    /// Builds a spanning tree with the echo algorithm.
+    struct SpanTree {
+        aid: ActorId,
+        started: bool,
+        session: Option<Session>,
+        parent: Option<ActorId>,
+        children: Vec<ActorId>,
+        pending: usize,
+        done: bool,
+        reported: bool,
+    }
+
+    impl SpanTree {
+        fn new(aid: ActorId) -> Self {
+            Self {
+                aid,
+                started: false,
+                session: None,
+                parent: None,
+                children: vec![],
+                pending: 0,
+                done: false,
+                reported: false,
+            }
+        }
+
+        fn check_done(&mut self) -> ContinuationHandler<TreePayload> {
+            if self.pending != 0 {
+                return ContinuationHandler::Done;
+            }
+
+            self.done = true;
+            match self.parent {
+                Some(pid) => {
+                    let msg = build_msg(self.aid, self.session.unwrap(), TreePayload::Back(true));
+                    ContinuationHandler::SendToNode(pid, msg)
+                }
+                None => ContinuationHandler::Done,
+            }
+        }
+    }
+
+    impl ProtocolHandler for SpanTree {
+        type Payload = TreePayload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            msg: Message<Self::Payload>,
+        ) -> ContinuationHandler<Self::Payload> {
+            let sender = msg.sender().as_aid();
+
+            match msg.payload() {
+                TreePayload::Go if !self.started => {
+                    self.started = true;
+                    self.session = Some(*msg.session());
+                    self.parent = Some(sender);
+                    self.pending = ns.count() - 1;
+
+                    let go = build_msg(self.aid, *msg.session(), TreePayload::Go);
+                    ContinuationHandler::SendToAllNodesExcept(go, vec![sender])
+                        .and_then(self.check_done())
+                }
+                TreePayload::Go | TreePayload::Back(false) => {
+                    self.pending -= 1;
+                    self.check_done()
+                }
+                TreePayload::Back(true) => {
+                    self.children.push(sender);
+                    self.pending -= 1;
+                    self.check_done()
+                }
+            }
+        }
+    }
+
+    impl Service for SpanTree {
+        type Request = ();
+        type Response = Tree;
+
+        fn request(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            session: Session,
+            _req: Self::Request,
+        ) -> ContinuationHandler<Self::Payload> {
+            self.started = true;
+            self.session = Some(session);
+            self.pending = ns.count();
+
+            let go = build_msg(self.aid, session, TreePayload::Go);
+            ContinuationHandler::SendToAllNodes(go).and_then(self.check_done())
+        }
+
+        fn response(&mut self) -> Option<Self::Response> {
+            if self.done && !self.reported {
+                self.reported = true;
+                Some(Tree {
+                    session: self.session.unwrap(),
+                    parent: self.parent,
+                    children: self.children.clone(),
+                })
+            } else {
+                None
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    enum SumPayload {
+        Start,
+        Sum(usize),
+    }
+
+    /// Sums the identifiers of the nodes over the spanning tree.
+    struct Convergecast {
+        aid: ActorId,
+        requests: Vec<(Session, ())>,
+        tree: Option<Tree>,
+        sums: Vec<usize>,
+        results: UnboundedSender<usize>,
+    }
+
+    impl Convergecast {
+        fn check_done(&mut self) -> ContinuationHandler<SumPayload> {
+            let tree = match &self.tree {
+                Some(tree) if tree.children.len() == self.sums.len() => tree,
+                _ => return ContinuationHandler::Done,
+            };
+
+            let total = self.aid.inner() + self.sums.iter().sum::<usize>();
+            match tree.parent {
+                Some(pid) => {
+                    let msg = build_msg(self.aid, tree.session, SumPayload::Sum(total));
+                    ContinuationHandler::SendToNode(pid, msg)
+                }
+                None => {
+                    let _ = self.results.unbounded_send(total);
+                    ContinuationHandler::Done
+                }
+            }
+        }
+    }
+
+    impl ProtocolHandler for Convergecast {
+        type Payload = SumPayload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: Message<Self::Payload>,
+        ) -> ContinuationHandler<Self::Payload> {
+            match msg.payload() {
+                SumPayload::Start => {
+                    self.requests.push((*msg.session(), ()));
+                    ContinuationHandler::Done
+                }
+                SumPayload::Sum(v) => {
+                    self.sums.push(*v);
+                    self.check_done()
+                }
+            }
+        }
+    }
+
+    impl Client<SpanTree> for Convergecast {
+        fn requests(&mut self) -> Vec<(Session, ())> {
+            std::mem::take(&mut self.requests)
+        }
+
+        fn on_response(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            tree: Tree,
+        ) -> ContinuationHandler<Self::Payload> {
+            self.tree = Some(tree);
+            self.check_done()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn tree_then_convergecast() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i: usize| {
+                let lower = SpanTree::new(i.into());
+                let upper = Convergecast {
+                    aid: i.into(),
+                    requests: vec![],
+                    tree: None,
+                    sums: vec![],
+                    results: tx.clone(),
+                };
+                NodeActor::build(Stack::new(lower, upper))
+            })
+            .collect();
+
+        for (a, b) in [(0, 1), (0, 2), (1, 3), (3, 4), (2, 4)] {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[a], &mut right[0]).await;
+        }
+
+        let aid = nodes[0].aid();
+        let msg = Builder::with_from_api()
+            .with_to_actor(aid)
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(StackPayload::Upper(SumPayload::Start))
+            .with_sender(aid)
+            .build();
+        nodes[0].do_send(msg);
+
+        let total = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap();
+        assert_eq!(Some(15), total);
+    }
+}
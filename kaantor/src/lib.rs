@@ -2,19 +2,39 @@
 
 //! A crate for distributed systems
 
+extern crate self as kaantor;
+
 mod actor;
+mod dispatch;
+pub mod election;
+pub mod fault;
 pub mod graph;
+pub mod layer;
+pub mod mst;
+pub mod mutex;
 pub mod node;
+pub mod paxos;
 pub mod protocol;
 mod proxy;
+pub mod raft;
+pub mod routing;
+pub mod rpc;
+pub mod snapshot;
+pub mod termination;
+pub mod trace;
+#[cfg(any(feature = "channel", feature = "tcp", feature = "uds"))]
+pub mod transport;
+pub mod traversal;
+pub mod wave;
 
 pub use actor::*;
+pub use dispatch::*;
 pub use node::{Node, Proxies};
 
 use actix::{dev::ToEnvelope, prelude::*};
 use log::debug;
 use protocol::Message as PMsg;
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
 type GMsg<P> = graph::GraphMsg<PMsg<P>>;
 
@@ -26,10 +46,71 @@ pub enum ContinuationHandler<P> {
     SendToAllNodes(protocol::Message<P>),
     /// Send a message to all neightbours excepts few
     SendToAllNodesExcept(protocol::Message<P>, Vec<ActorId>),
+    /// Deliver a message to the node itself once the delay elapsed,
+    /// e.g. to implement timeouts
+    Schedule(Duration, protocol::Message<P>),
+    /// Put the message back in the queue of the node, to offer it again to the
+    /// handler once the state of the node changed, i.e. once the handler
+    /// returned a continuation which defers nothing for a message.
+    Defer(protocol::Message<P>),
+    /// Execute several continuations, in order
+    Sequence(Vec<ContinuationHandler<P>>),
     /// We are done
     Done,
 }
 
+impl<P> ContinuationHandler<P> {
+    /// Transforms the payload of all the messages of the continuation.
+    pub fn map<Q, F>(self, f: F) -> ContinuationHandler<Q>
+    where
+        F: Fn(P) -> Q,
+    {
+        self.map_ref(&f)
+    }
+
+    fn map_ref<Q, F>(self, f: &F) -> ContinuationHandler<Q>
+    where
+        F: Fn(P) -> Q,
+    {
+        match self {
+            Self::SendToNode(aid, msg) => ContinuationHandler::SendToNode(aid, msg.map_payload(f)),
+            Self::SendToAllNodes(msg) => ContinuationHandler::SendToAllNodes(msg.map_payload(f)),
+            Self::SendToAllNodesExcept(msg, except) => {
+                ContinuationHandler::SendToAllNodesExcept(msg.map_payload(f), except)
+            }
+            Self::Schedule(delay, msg) => ContinuationHandler::Schedule(delay, msg.map_payload(f)),
+            Self::Defer(msg) => ContinuationHandler::Defer(msg.map_payload(f)),
+            Self::Sequence(conts) => {
+                ContinuationHandler::Sequence(conts.into_iter().map(|c| c.map_ref(f)).collect())
+            }
+            Self::Done => ContinuationHandler::Done,
+        }
+    }
+
+    /// Returns `true` if the continuation defers a message, even within a
+    /// sequence: the handler did not take the message into account.
+    pub fn is_deferred(&self) -> bool {
+        match self {
+            Self::Defer(_) => true,
+            Self::Sequence(conts) => conts.iter().any(Self::is_deferred),
+            _ => false,
+        }
+    }
+
+    /// Chains two continuations, dropping the ones which do nothing.
+    pub fn and_then(self, next: ContinuationHandler<P>) -> ContinuationHandler<P> {
+        match (self, next) {
+            (Self::Done, next) => next,
+            (first, Self::Done) => first,
+            (Self::Sequence(mut conts), next) => {
+                conts.push(next);
+                Self::Sequence(conts)
+            }
+            (first, next) => Self::Sequence(vec![first, next]),
+        }
+    }
+}
+
 /// The trait which defines the behaviour of a node.
 pub trait ProtocolHandler {
     /// The type of payload for the messages.
@@ -44,6 +125,22 @@ pub trait ProtocolHandler {
         neighbours: impl Iterator<Item = ActorId>, // &Proxies<Self::Payload>,
         msg: protocol::Message<Self::Payload>,
     ) -> ContinuationHandler<Self::Payload>;
+
+    /// Handles a continuation of the handler which named a node which is not
+    /// a neighbour. The error is logged and traced in any case.
+    fn on_neighbour_error(
+        &mut self,
+        _neighbours: impl Iterator<Item = ActorId>,
+        _err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        ContinuationHandler::Done
+    }
+
+    /// Returns `true` once the handler finished its work. It is used to drop
+    /// the handler instances created for a session, see `Sessions`.
+    fn is_finished(&self) -> bool {
+        false
+    }
 }
 
 /// Convenience type
@@ -0,0 +1,521 @@
+use super::Mst;
+use crate::{
+    graph::Topology,
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use kaantor_tree::{KNode, KTree};
+use std::collections::HashMap;
+
+/// The weight of an edge, as seen by GHS. The ties between the weights of the
+/// topology are broken with the identifiers of the nodes, so all the edges
+/// have a distinct weight, which the algorithm requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeWeight {
+    /// The weight of an existing edge, with the smallest and largest ends.
+    Finite(u64, ActorId, ActorId),
+    /// Larger than any edge, e.g. when a fragment has no outgoing edge.
+    Infinite,
+}
+
+impl EdgeWeight {
+    fn new(weight: u64, a: ActorId, b: ActorId) -> Self {
+        Self::Finite(weight, a.min(b), a.max(b))
+    }
+
+    fn value(&self) -> u64 {
+        match self {
+            Self::Finite(weight, _, _) => *weight,
+            Self::Infinite => panic!("an infinite weight has no value"),
+        }
+    }
+}
+
+/// The payload of the GHS algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum GhsPayload {
+    /// Wakes the node up.
+    Start,
+    /// Asks to join the fragment of the receiver, with the level of the sender.
+    Connect(usize),
+    /// Broadcasts the new level and name of a fragment, and whether the
+    /// fragment looks for its minimum outgoing edge.
+    Initiate {
+        /// The level of the fragment.
+        level: usize,
+        /// The name of the fragment, i.e. the weight of its core edge.
+        fragment: EdgeWeight,
+        /// Whether the fragment looks for its minimum outgoing edge.
+        find: bool,
+    },
+    /// Asks whether the edge leads to another fragment.
+    Test {
+        /// The level of the fragment of the sender.
+        level: usize,
+        /// The name of the fragment of the sender.
+        fragment: EdgeWeight,
+    },
+    /// The edge leads to another fragment.
+    Accept,
+    /// The edge is internal to the fragment.
+    Reject,
+    /// Reports the weight of the minimum outgoing edge of a subtree.
+    Report(EdgeWeight),
+    /// Moves the root of the fragment towards its minimum outgoing edge.
+    ChangeRoot,
+    /// Asks the subtree for its part of the spanning tree, once it is built.
+    Collect,
+    /// The nodes of a subtree with their children, and the weight of its edges.
+    Tree(Vec<(ActorId, Vec<ActorId>)>, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeState {
+    Sleeping,
+    Find,
+    Found,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EdgeState {
+    Basic,
+    Branch,
+    Rejected,
+}
+
+/// The algorithm of Gallager, Humblet and Spira. The nodes start as fragments
+/// of level 0, and every fragment looks for its minimum outgoing edge, along
+/// which it connects to another fragment. Two fragments of the same level
+/// merge into a fragment of the next level, and a fragment of a lower level
+/// is absorbed by the other one.
+///
+/// A message which a node cannot handle yet, e.g. a `Test` from a fragment of a
+/// higher level, is deferred with `ContinuationHandler::Defer`, and offered
+/// again once the state of the node changed. Once the
+/// last fragment finds no outgoing edge, the larger node of its core edge
+/// becomes the root of the tree, collects it and reports it.
+///
+/// Any node can be woken up with `GhsPayload::Start`, and the other ones
+/// wake up when they receive their first message.
+pub struct Ghs {
+    aid: ActorId,
+    weights: HashMap<ActorId, EdgeWeight>,
+    edges: HashMap<ActorId, EdgeState>,
+    state: NodeState,
+    level: usize,
+    fragment: Option<EdgeWeight>,
+    in_branch: Option<ActorId>,
+    best_edge: Option<ActorId>,
+    best_weight: EdgeWeight,
+    test_edge: Option<ActorId>,
+    find_count: usize,
+    parent: Option<ActorId>,
+    pending: usize,
+    tree: Vec<(ActorId, Vec<ActorId>)>,
+    tree_weight: u64,
+    results: Option<UnboundedSender<Mst>>,
+}
+
+impl Ghs {
+    /// Creates the handler for the node `aid`, with the weights of its
+    /// edges taken from the topology.
+    pub fn new(aid: ActorId, topology: &Topology) -> Self {
+        let weights: HashMap<_, _> = topology
+            .neighbours(aid)
+            .map(|n| {
+                let weight = topology.weight(aid, n).expect("the edge exists");
+                (n, EdgeWeight::new(weight, aid, n))
+            })
+            .collect();
+        let edges = weights.keys().map(|n| (*n, EdgeState::Basic)).collect();
+
+        Self {
+            aid,
+            weights,
+            edges,
+            state: NodeState::Sleeping,
+            level: 0,
+            fragment: None,
+            in_branch: None,
+            best_edge: None,
+            best_weight: EdgeWeight::Infinite,
+            test_edge: None,
+            find_count: 0,
+            parent: None,
+            pending: 0,
+            tree: vec![],
+            tree_weight: 0,
+            results: None,
+        }
+    }
+
+    /// Sends the spanning tree to the channel, from its root.
+    pub fn with_results(mut self, results: UnboundedSender<Mst>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    fn send(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: GhsPayload,
+    ) -> ContinuationHandler<GhsPayload> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    fn branches(&self) -> impl Iterator<Item = ActorId> + '_ {
+        self.edges
+            .iter()
+            .filter(|(_, s)| **s == EdgeState::Branch)
+            .map(|(n, _)| *n)
+    }
+
+    fn wakeup(&mut self, session: Session) -> ContinuationHandler<GhsPayload> {
+        self.state = NodeState::Found;
+        let Some((m, _)) = self.weights.iter().min_by_key(|(_, w)| **w) else {
+            // A single node is its own spanning tree.
+            return self.collect(session, None);
+        };
+        let m = *m;
+
+        self.edges.insert(m, EdgeState::Branch);
+        self.send(m, session, GhsPayload::Connect(0))
+    }
+
+    /// Looks for the minimum outgoing edge, by testing the basic edges in
+    /// increasing order of weight.
+    fn test(&mut self, session: Session) -> ContinuationHandler<GhsPayload> {
+        let basic = self
+            .edges
+            .iter()
+            .filter(|(_, s)| **s == EdgeState::Basic)
+            .map(|(n, _)| *n)
+            .min_by_key(|n| self.weights[n]);
+
+        self.test_edge = basic;
+        match basic {
+            Some(n) => {
+                let test = GhsPayload::Test {
+                    level: self.level,
+                    fragment: self.fragment.expect("the node is part of a fragment"),
+                };
+                self.send(n, session, test)
+            }
+            None => self.report(session),
+        }
+    }
+
+    fn report(&mut self, session: Session) -> ContinuationHandler<GhsPayload> {
+        if self.find_count != 0 || self.test_edge.is_some() {
+            return ContinuationHandler::Done;
+        }
+
+        self.state = NodeState::Found;
+        let in_branch = self.in_branch.expect("the fragment has a core");
+        self.send(in_branch, session, GhsPayload::Report(self.best_weight))
+    }
+
+    fn change_root(&mut self, session: Session) -> ContinuationHandler<GhsPayload> {
+        let best = self.best_edge.expect("the fragment has an outgoing edge");
+        let res = if self.edges[&best] == EdgeState::Branch {
+            self.send(best, session, GhsPayload::ChangeRoot)
+        } else {
+            self.send(best, session, GhsPayload::Connect(self.level))
+        };
+        self.edges.insert(best, EdgeState::Branch);
+        res
+    }
+
+    /// Sends the request for the tree down the branches, except to the parent.
+    fn collect(
+        &mut self,
+        session: Session,
+        parent: Option<ActorId>,
+    ) -> ContinuationHandler<GhsPayload> {
+        self.parent = parent;
+        let children: Vec<_> = self.branches().filter(|n| Some(*n) != parent).collect();
+        self.pending = children.len();
+
+        children
+            .into_iter()
+            .fold(ContinuationHandler::Done, |res, n| {
+                res.and_then(self.send(n, session, GhsPayload::Collect))
+            })
+            .and_then(self.check_collected(session))
+    }
+
+    fn check_collected(&mut self, session: Session) -> ContinuationHandler<GhsPayload> {
+        if self.pending != 0 {
+            return ContinuationHandler::Done;
+        }
+
+        let children = self
+            .branches()
+            .filter(|n| Some(*n) != self.parent)
+            .collect();
+        self.tree.push((self.aid, children));
+        let tree = std::mem::take(&mut self.tree);
+
+        match self.parent {
+            Some(pid) => self.send(pid, session, GhsPayload::Tree(tree, self.tree_weight)),
+            None => {
+                log::info!("MST | on {:?} | {:?} | {:?}", self.aid, session, tree);
+                if let Some(results) = &self.results {
+                    let nodes = tree
+                        .into_iter()
+                        .map(|(aid, children)| KNode::new(aid, children))
+                        .collect();
+                    let _ = results.unbounded_send(Mst {
+                        session,
+                        tree: KTree::new(self.aid, nodes),
+                        weight: self.tree_weight,
+                    });
+                }
+                ContinuationHandler::Done
+            }
+        }
+    }
+
+    fn handle(&mut self, msg: Message<GhsPayload>) -> ContinuationHandler<GhsPayload> {
+        let session = *msg.session();
+        let j = msg.sender().as_aid();
+
+        match msg.payload() {
+            GhsPayload::Start => ContinuationHandler::Done,
+            GhsPayload::Connect(level) if *level < self.level => {
+                self.edges.insert(j, EdgeState::Branch);
+                if self.state == NodeState::Find {
+                    self.find_count += 1;
+                }
+                let initiate = GhsPayload::Initiate {
+                    level: self.level,
+                    fragment: self.fragment.expect("the node is part of a fragment"),
+                    find: self.state == NodeState::Find,
+                };
+                self.send(j, session, initiate)
+            }
+            GhsPayload::Connect(_) if self.edges[&j] == EdgeState::Basic => {
+                ContinuationHandler::Defer(msg)
+            }
+            GhsPayload::Connect(_) => {
+                let initiate = GhsPayload::Initiate {
+                    level: self.level + 1,
+                    fragment: self.weights[&j],
+                    find: true,
+                };
+                self.send(j, session, initiate)
+            }
+            GhsPayload::Initiate {
+                level,
+                fragment,
+                find,
+            } => {
+                self.level = *level;
+                self.fragment = Some(*fragment);
+                self.state = if *find {
+                    NodeState::Find
+                } else {
+                    NodeState::Found
+                };
+                self.in_branch = Some(j);
+                self.best_edge = None;
+                self.best_weight = EdgeWeight::Infinite;
+
+                let branches: Vec<_> = self.branches().filter(|n| *n != j).collect();
+                let mut res = ContinuationHandler::Done;
+                for n in branches {
+                    res = res.and_then(self.send(n, session, msg.payload().clone()));
+                    if *find {
+                        self.find_count += 1;
+                    }
+                }
+                if *find {
+                    res = res.and_then(self.test(session));
+                }
+                res
+            }
+            GhsPayload::Test { level, .. } if *level > self.level => {
+                ContinuationHandler::Defer(msg)
+            }
+            GhsPayload::Test { fragment, .. } if Some(*fragment) != self.fragment => {
+                self.send(j, session, GhsPayload::Accept)
+            }
+            GhsPayload::Test { .. } => {
+                if self.edges[&j] == EdgeState::Basic {
+                    self.edges.insert(j, EdgeState::Rejected);
+                }
+                if self.test_edge != Some(j) {
+                    self.send(j, session, GhsPayload::Reject)
+                } else {
+                    self.test(session)
+                }
+            }
+            GhsPayload::Accept => {
+                self.test_edge = None;
+                if self.weights[&j] < self.best_weight {
+                    self.best_edge = Some(j);
+                    self.best_weight = self.weights[&j];
+                }
+                self.report(session)
+            }
+            GhsPayload::Reject => {
+                if self.edges[&j] == EdgeState::Basic {
+                    self.edges.insert(j, EdgeState::Rejected);
+                }
+                self.test(session)
+            }
+            GhsPayload::Report(weight) if Some(j) != self.in_branch => {
+                self.find_count -= 1;
+                if *weight < self.best_weight {
+                    self.best_edge = Some(j);
+                    self.best_weight = *weight;
+                }
+                self.report(session)
+            }
+            GhsPayload::Report(_) if self.state == NodeState::Find => {
+                ContinuationHandler::Defer(msg)
+            }
+            GhsPayload::Report(weight) if *weight > self.best_weight => self.change_root(session),
+            GhsPayload::Report(weight)
+                if *weight == EdgeWeight::Infinite && self.best_weight == EdgeWeight::Infinite =>
+            {
+                // The fragment spans the graph, and the larger node of
+                // the core collects the tree.
+                if self.aid > j {
+                    self.collect(session, None)
+                } else {
+                    ContinuationHandler::Done
+                }
+            }
+            GhsPayload::Report(_) => ContinuationHandler::Done,
+            GhsPayload::ChangeRoot => self.change_root(session),
+            GhsPayload::Collect => self.collect(session, Some(j)),
+            GhsPayload::Tree(nodes, weight) => {
+                self.tree.extend(nodes.iter().cloned());
+                self.tree_weight += weight + self.weights[&j].value();
+                self.pending -= 1;
+                self.check_collected(session)
+            }
+        }
+    }
+}
+
+impl ProtocolHandler for Ghs {
+    type Payload = GhsPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let mut res = ContinuationHandler::Done;
+        if self.state == NodeState::Sleeping {
+            res = self.wakeup(*msg.session());
+        }
+
+        res.and_then(self.handle(msg))
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    /// Returns the edges of the minimum spanning tree, with Kruskal's algorithm.
+    fn kruskal(n: usize, edges: &[(usize, usize, u64)]) -> Vec<(usize, usize)> {
+        let mut sorted = edges.to_vec();
+        sorted.sort_by_key(|(a, b, w)| (*w, *a.min(b), *a.max(b)));
+        let mut component: Vec<_> = (0..=n).collect();
+
+        let mut mst = vec![];
+        for (a, b, _) in sorted {
+            let (ca, cb) = (component[a], component[b]);
+            if ca != cb {
+                component
+                    .iter_mut()
+                    .filter(|c| **c == cb)
+                    .for_each(|c| *c = ca);
+                mst.push((a.min(b), a.max(b)));
+            }
+        }
+        mst.sort();
+        mst
+    }
+
+    #[actix_rt::test]
+    async fn ghs() {
+        const EDGES: [(usize, usize, u64); 11] = [
+            (1, 2, 4),
+            (1, 3, 2),
+            (2, 3, 5),
+            (2, 4, 10),
+            (3, 5, 3),
+            (4, 5, 4),
+            (4, 6, 11),
+            (5, 6, 7),
+            (6, 7, 1),
+            (5, 7, 7),
+            (3, 7, 9),
+        ];
+        let topology = EDGES.iter().fold(Topology::new(), |t, (a, b, w)| {
+            t.with_weighted_edge((*a).into(), (*b).into(), *w)
+        });
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=7)
+            .map(|i| NodeActor::build(Ghs::new(i.into(), &topology).with_results(tx.clone())))
+            .collect();
+        for (a, b, _) in EDGES {
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for i in [0, 5] {
+            let aid = nodes[i].aid();
+            let msg = Builder::with_from_api()
+                .with_to_actor(aid)
+                .with_session(session)
+                .with_payload(GhsPayload::Start)
+                .with_sender(aid)
+                .build();
+            nodes[i].do_send(msg);
+        }
+
+        let mst = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut edges: Vec<_> = mst
+            .edges()
+            .into_iter()
+            .map(|(a, b)| (a.inner().min(b.inner()), a.inner().max(b.inner())))
+            .collect();
+        edges.sort();
+
+        let expected = kruskal(7, &EDGES);
+        assert_eq!(expected, edges);
+        assert_eq!(7, mst.tree.nodes().len());
+        let weight: u64 = expected
+            .iter()
+            .map(|(a, b)| topology.weight((*a).into(), (*b).into()).unwrap())
+            .sum();
+        assert_eq!(weight, mst.weight);
+    }
+}
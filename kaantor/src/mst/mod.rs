@@ -0,0 +1,41 @@
+//! Minimum spanning trees.
+//!
+//! The weights of the edges are taken from the `Topology` of the graph, which
+//! has to match the edges added with `add_edge`. The tree is reported by its
+//! root, as a `KTree` of the identifiers of the nodes.
+
+mod ghs;
+
+pub use ghs::*;
+
+use crate::{protocol::Session, ActorId};
+use kaantor_tree::{KItem, KTree};
+
+impl KItem for ActorId {
+    type Key = ActorId;
+
+    fn key(&self) -> &ActorId {
+        self
+    }
+}
+
+/// A minimum spanning tree, as computed by the nodes.
+pub struct Mst {
+    /// The session of the computation.
+    pub session: Session,
+    /// The tree, from the root which reported it.
+    pub tree: KTree<ActorId>,
+    /// The total weight of the edges of the tree.
+    pub weight: u64,
+}
+
+impl Mst {
+    /// Returns the edges of the tree, each one from the parent to the child.
+    pub fn edges(&self) -> Vec<(ActorId, ActorId)> {
+        self.tree
+            .nodes()
+            .iter()
+            .flat_map(|n| n.children().iter().map(|c| (*n.key(), *c)))
+            .collect()
+    }
+}
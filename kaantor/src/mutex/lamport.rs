@@ -0,0 +1,145 @@
+//! Lamport's mutual exclusion, on a complete graph with FIFO channels.
+//!
+//! Every node keeps a queue of the requests, ordered by their Lamport
+//! timestamps. A node broadcasts its request, and the other nodes queue it
+//! and acknowledge it. A node enters the critical section once its request
+//! heads its queue, and it received a later message from every other node.
+//! Leaving the critical section is broadcast, to dequeue the request.
+
+use super::{Granted, MutexPayload, SafetyChecker, Section};
+use crate::{protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The payload of Lamport's mutual exclusion.
+#[derive(Clone, PayloadDebug)]
+pub enum LamportPayload {
+    /// Sent by the application to request the critical section.
+    Request,
+    /// Sent by the application to release the critical section.
+    Release,
+    /// Queues a request, with its timestamp.
+    Ask(u64),
+    /// Acknowledges a request, with the timestamp of the acknowledgement.
+    Ack(u64),
+    /// Dequeues the request of the sender, with the timestamp of the release.
+    Free(u64),
+}
+
+impl MutexPayload for LamportPayload {
+    fn request() -> Self {
+        Self::Request
+    }
+
+    fn release() -> Self {
+        Self::Release
+    }
+}
+
+/// A node of Lamport's mutual exclusion.
+pub struct Lamport {
+    section: Section,
+    clock: u64,
+    timestamp: u64,
+    queue: BTreeSet<(u64, ActorId)>,
+    latest: BTreeMap<ActorId, u64>,
+}
+
+impl Lamport {
+    /// Creates a new node.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            section: Section::new(aid),
+            clock: 0,
+            timestamp: 0,
+            queue: Default::default(),
+            latest: Default::default(),
+        }
+    }
+
+    /// Tells the application when the node entered the critical section.
+    pub fn with_results(mut self, results: UnboundedSender<Granted>) -> Self {
+        self.section.results = Some(results);
+        self
+    }
+
+    /// Records the critical sections in the checker.
+    pub fn with_checker(mut self, checker: SafetyChecker) -> Self {
+        self.section.checker = Some(checker);
+        self
+    }
+
+    /// Updates the clock and the latest timestamp of the sender.
+    fn witness(&mut self, from: ActorId, timestamp: u64) {
+        self.clock = self.clock.max(timestamp) + 1;
+        self.latest.insert(from, timestamp);
+    }
+
+    fn try_enter(&mut self, ns: &[ActorId]) {
+        let head = (self.timestamp, self.section.aid);
+        let later = |n: &ActorId| self.latest.get(n).is_some_and(|t| *t > self.timestamp);
+
+        if self.section.is_waiting() && self.queue.first() == Some(&head) && ns.iter().all(later) {
+            self.section.enter();
+        }
+    }
+}
+
+impl ProtocolHandler for Lamport {
+    type Payload = LamportPayload;
+
+    fn aid(&self) -> ActorId {
+        self.section.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let aid = self.section.aid;
+        let from = msg.sender().as_aid();
+        let session = *msg.session();
+
+        let res = match msg.payload() {
+            LamportPayload::Request => {
+                if !self.section.request(session) {
+                    return ContinuationHandler::Done;
+                }
+                self.clock += 1;
+                self.timestamp = self.clock;
+                self.queue.insert((self.timestamp, aid));
+                self.section
+                    .broadcast(session, LamportPayload::Ask(self.timestamp))
+            }
+            LamportPayload::Release => {
+                if !self.section.release() {
+                    return ContinuationHandler::Done;
+                }
+                self.queue.remove(&(self.timestamp, aid));
+                self.clock += 1;
+                self.section
+                    .broadcast(session, LamportPayload::Free(self.clock))
+            }
+            LamportPayload::Ask(timestamp) => {
+                self.witness(from, *timestamp);
+                self.queue.insert((*timestamp, from));
+                self.section
+                    .send(from, session, LamportPayload::Ack(self.clock))
+            }
+            LamportPayload::Ack(timestamp) => {
+                self.witness(from, *timestamp);
+                ContinuationHandler::Done
+            }
+            LamportPayload::Free(timestamp) => {
+                self.witness(from, *timestamp);
+                self.queue.retain(|(_, n)| *n != from);
+                ContinuationHandler::Done
+            }
+        };
+
+        self.try_enter(&ns);
+        res
+    }
+}
@@ -0,0 +1,332 @@
+//! Distributed mutual exclusion algorithms.
+//!
+//! The application on a node requests the critical section by sending the
+//! message built by `request` to the node. It is told when the node entered
+//! the critical section by the channel given to `with_results`, and it leaves
+//! the critical section by sending the message built by `release`.
+//!
+//! A `SafetyChecker` shared by the nodes verifies that no two nodes are
+//! ever inside the critical section at the same time.
+
+mod lamport;
+mod raymond;
+mod ricart_agrawala;
+mod suzuki_kasami;
+
+pub use lamport::*;
+pub use raymond::*;
+pub use ricart_agrawala::*;
+pub use suzuki_kasami::*;
+
+use crate::{
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::{info, warn};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+/// The payloads of the mutual exclusion algorithms, which
+/// include the requests and the releases of the application.
+pub trait MutexPayload {
+    /// Requests the critical section.
+    fn request() -> Self;
+    /// Releases the critical section.
+    fn release() -> Self;
+}
+
+/// Builds the message the application sends to its node `aid`
+/// to request the critical section.
+pub fn request<P: MutexPayload>(aid: ActorId, session: Session) -> Message<P> {
+    api(aid, session, P::request())
+}
+
+/// Builds the message the application sends to its node `aid`
+/// to release the critical section.
+pub fn release<P: MutexPayload>(aid: ActorId, session: Session) -> Message<P> {
+    api(aid, session, P::release())
+}
+
+fn api<P>(aid: ActorId, session: Session, payload: P) -> Message<P> {
+    Builder::with_from_api()
+        .with_to_actor(aid)
+        .with_session(session)
+        .with_payload(payload)
+        .with_sender(aid)
+        .build()
+}
+
+/// A node entered the critical section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Granted {
+    /// The node.
+    pub aid: ActorId,
+    /// The session of the request.
+    pub session: Session,
+}
+
+/// Two nodes or more were inside the critical section at the same time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The node which entered the critical section.
+    pub aid: ActorId,
+    /// The nodes which were already inside.
+    pub inside: Vec<ActorId>,
+}
+
+#[derive(Default)]
+struct Occupancy {
+    inside: BTreeSet<ActorId>,
+    entries: usize,
+    violations: Vec<Violation>,
+}
+
+/// Records the nodes entering and leaving the critical section,
+/// and the violations of the mutual exclusion.
+#[derive(Clone, Default)]
+pub struct SafetyChecker {
+    occupancy: Arc<Mutex<Occupancy>>,
+}
+
+impl SafetyChecker {
+    /// Creates a checker with nobody inside the critical section.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records that the node entered the critical section.
+    pub fn enter(&self, aid: ActorId) {
+        let mut occupancy = self.occupancy.lock().unwrap();
+        if !occupancy.inside.is_empty() {
+            let inside = occupancy.inside.iter().copied().collect();
+            occupancy.violations.push(Violation { aid, inside });
+        }
+        occupancy.inside.insert(aid);
+        occupancy.entries += 1;
+    }
+
+    /// Records that the node left the critical section.
+    pub fn exit(&self, aid: ActorId) {
+        self.occupancy.lock().unwrap().inside.remove(&aid);
+    }
+
+    /// Returns the number of times a node entered the critical section.
+    pub fn entries(&self) -> usize {
+        self.occupancy.lock().unwrap().entries
+    }
+
+    /// Returns the violations of the mutual exclusion.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.occupancy.lock().unwrap().violations.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    Waiting,
+    Inside,
+}
+
+/// The state of the application on a node, shared by the algorithms.
+struct Section {
+    aid: ActorId,
+    session: Session,
+    state: State,
+    results: Option<UnboundedSender<Granted>>,
+    checker: Option<SafetyChecker>,
+}
+
+impl Section {
+    fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            session: 0.into(),
+            state: State::Idle,
+            results: None,
+            checker: None,
+        }
+    }
+
+    /// Starts waiting for the critical section, unless the node already
+    /// requested it.
+    fn request(&mut self, session: Session) -> bool {
+        if self.state != State::Idle {
+            warn!("MUTX | on {:?} | requested twice", self.aid);
+            return false;
+        }
+        self.session = session;
+        self.state = State::Waiting;
+        true
+    }
+
+    fn enter(&mut self) {
+        info!("MUTX | on {:?} | enter", self.aid);
+        self.state = State::Inside;
+        if let Some(checker) = &self.checker {
+            checker.enter(self.aid);
+        }
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Granted {
+                aid: self.aid,
+                session: self.session,
+            });
+        }
+    }
+
+    /// Leaves the critical section, unless the node is not inside.
+    fn release(&mut self) -> bool {
+        if self.state != State::Inside {
+            warn!("MUTX | on {:?} | released while outside", self.aid);
+            return false;
+        }
+        info!("MUTX | on {:?} | exit", self.aid);
+        self.state = State::Idle;
+        if let Some(checker) = &self.checker {
+            checker.exit(self.aid);
+        }
+        true
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.state == State::Waiting
+    }
+
+    fn is_inside(&self) -> bool {
+        self.state == State::Inside
+    }
+
+    fn send<P>(&self, to: ActorId, session: Session, payload: P) -> ContinuationHandler<P> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    fn broadcast<P>(&self, session: Session, payload: P) -> ContinuationHandler<P> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToAllNodes(msg)
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor, ProtocolHandler};
+    use futures::StreamExt;
+    use std::{collections::HashMap, time::Duration};
+
+    #[test]
+    fn safety_checker() {
+        let checker = SafetyChecker::new();
+        checker.enter(1.into());
+        checker.exit(1.into());
+        checker.enter(2.into());
+        checker.enter(3.into());
+        checker.exit(2.into());
+        checker.exit(3.into());
+
+        assert_eq!(3, checker.entries());
+        assert_eq!(
+            vec![Violation {
+                aid: 3.into(),
+                inside: vec![2.into()]
+            }],
+            checker.violations()
+        );
+    }
+
+    /// Every node of a complete graph requests the critical section a few
+    /// times, and stays inside a bit.
+    async fn contend<H, P>(build: impl Fn(ActorId, UnboundedSender<Granted>, SafetyChecker) -> H)
+    where
+        H: ProtocolHandler<Payload = P> + Unpin + 'static,
+        P: MutexPayload + Clone + Send + std::fmt::Debug + 'static,
+    {
+        const N: usize = 5;
+        const ROUNDS: usize = 3;
+        let checker = SafetyChecker::new();
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=N)
+            .map(|i| NodeActor::build(build(i.into(), tx.clone(), checker.clone())))
+            .collect();
+        for b in 1..N {
+            for a in 0..b {
+                let (left, right) = nodes.split_at_mut(b);
+                add_edge(&mut left[a], &mut right[0]).await;
+            }
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for node in nodes.iter_mut() {
+            let aid = node.aid();
+            node.do_send(request(aid, session));
+        }
+
+        let mut entries: HashMap<ActorId, usize> = HashMap::new();
+        for _ in 0..N * ROUNDS {
+            let granted = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            actix_rt::time::sleep(Duration::from_millis(1)).await;
+
+            let node = &mut nodes[granted.aid.inner() - 1];
+            node.do_send(release(granted.aid, session));
+            let count = entries.entry(granted.aid).or_default();
+            *count += 1;
+            if *count < ROUNDS {
+                node.do_send(request(granted.aid, session));
+            }
+        }
+
+        assert_eq!(N * ROUNDS, checker.entries());
+        assert_eq!(Vec::<Violation>::new(), checker.violations());
+    }
+
+    #[actix_rt::test]
+    async fn mutual_exclusion() {
+        let ra = |aid, tx, checker| {
+            RicartAgrawala::new(aid)
+                .with_results(tx)
+                .with_checker(checker)
+        };
+        contend(ra).await;
+
+        let lamport = |aid, tx, checker| Lamport::new(aid).with_results(tx).with_checker(checker);
+        contend(lamport).await;
+
+        // The token goes along the binary tree rooted at the node 1.
+        let raymond = |aid: ActorId, tx, checker| {
+            let holder = ActorId::from(aid.inner() / 2).max(1.into());
+            Raymond::new(aid, holder)
+                .with_results(tx)
+                .with_checker(checker)
+        };
+        contend(raymond).await;
+
+        let sk = |aid: ActorId, tx, checker| {
+            let sk = SuzukiKasami::new(aid)
+                .with_results(tx)
+                .with_checker(checker);
+            if aid == 1.into() {
+                sk.with_token()
+            } else {
+                sk
+            }
+        };
+        contend(sk).await;
+    }
+}
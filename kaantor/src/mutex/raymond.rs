@@ -0,0 +1,133 @@
+//! Raymond's mutual exclusion, with a token on a spanning tree.
+//!
+//! Every node points to the neighbour which is on the way to the token, its
+//! holder, and queues the requests of its neighbours and its own. A node asks
+//! its holder for the token on behalf of the head of its queue, and the token
+//! goes down to the head of the queue of each node, until it reaches the
+//! node which requested it. The token is the right to enter the critical
+//! section.
+
+use super::{Granted, MutexPayload, SafetyChecker, Section};
+use crate::{
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::VecDeque;
+
+/// The payload of Raymond's mutual exclusion.
+#[derive(Clone, PayloadDebug)]
+pub enum RaymondPayload {
+    /// Sent by the application to request the critical section.
+    Request,
+    /// Sent by the application to release the critical section.
+    Release,
+    /// Asks the holder for the token.
+    Ask,
+    /// The token.
+    Token,
+}
+
+impl MutexPayload for RaymondPayload {
+    fn request() -> Self {
+        Self::Request
+    }
+
+    fn release() -> Self {
+        Self::Release
+    }
+}
+
+/// A node of Raymond's mutual exclusion.
+pub struct Raymond {
+    section: Section,
+    holder: ActorId,
+    queue: VecDeque<ActorId>,
+    asked: bool,
+}
+
+impl Raymond {
+    /// Creates a new node, whose neighbour on the way to the token is
+    /// `holder`. The node which has the token first is its own holder.
+    pub fn new(aid: ActorId, holder: ActorId) -> Self {
+        Self {
+            section: Section::new(aid),
+            holder,
+            queue: Default::default(),
+            asked: false,
+        }
+    }
+
+    /// Tells the application when the node entered the critical section.
+    pub fn with_results(mut self, results: UnboundedSender<Granted>) -> Self {
+        self.section.results = Some(results);
+        self
+    }
+
+    /// Records the critical sections in the checker.
+    pub fn with_checker(mut self, checker: SafetyChecker) -> Self {
+        self.section.checker = Some(checker);
+        self
+    }
+
+    /// Hands the token to the head of the queue, then asks for the token
+    /// again if the queue is not empty.
+    fn assign(&mut self, session: Session) -> ContinuationHandler<RaymondPayload> {
+        let aid = self.section.aid;
+        let mut res = ContinuationHandler::Done;
+
+        if self.holder == aid && !self.section.is_inside() {
+            if let Some(head) = self.queue.pop_front() {
+                self.holder = head;
+                self.asked = false;
+                if head == aid {
+                    self.section.enter();
+                } else {
+                    res = self.section.send(head, session, RaymondPayload::Token);
+                }
+            }
+        }
+
+        if self.holder != aid && !self.queue.is_empty() && !self.asked {
+            self.asked = true;
+            let ask = self.section.send(self.holder, session, RaymondPayload::Ask);
+            res = res.and_then(ask);
+        }
+        res
+    }
+}
+
+impl ProtocolHandler for Raymond {
+    type Payload = RaymondPayload;
+
+    fn aid(&self) -> ActorId {
+        self.section.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let aid = self.section.aid;
+        let session = *msg.session();
+
+        match msg.payload() {
+            RaymondPayload::Request => {
+                if !self.section.request(session) {
+                    return ContinuationHandler::Done;
+                }
+                self.queue.push_back(aid);
+            }
+            RaymondPayload::Release => {
+                if !self.section.release() {
+                    return ContinuationHandler::Done;
+                }
+            }
+            RaymondPayload::Ask => self.queue.push_back(msg.sender().as_aid()),
+            RaymondPayload::Token => self.holder = aid,
+        }
+
+        self.assign(session)
+    }
+}
@@ -0,0 +1,132 @@
+//! The Ricart–Agrawala mutual exclusion, on a complete graph.
+//!
+//! A node asks every other node for the critical section with a Lamport
+//! timestamp, and enters once all of them replied. A node defers its reply
+//! while it is inside, or while its own request is older, and sends the
+//! deferred replies once it leaves the critical section.
+
+use super::{Granted, MutexPayload, SafetyChecker, Section};
+use crate::{protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::BTreeSet;
+
+/// The payload of the Ricart–Agrawala mutual exclusion.
+#[derive(Clone, PayloadDebug)]
+pub enum RicartAgrawalaPayload {
+    /// Sent by the application to request the critical section.
+    Request,
+    /// Sent by the application to release the critical section.
+    Release,
+    /// Asks for the critical section, with the timestamp of the request.
+    Ask(u64),
+    /// Lets the sender enter the critical section.
+    Reply,
+}
+
+impl MutexPayload for RicartAgrawalaPayload {
+    fn request() -> Self {
+        Self::Request
+    }
+
+    fn release() -> Self {
+        Self::Release
+    }
+}
+
+/// A node of the Ricart–Agrawala mutual exclusion.
+pub struct RicartAgrawala {
+    section: Section,
+    clock: u64,
+    timestamp: u64,
+    replies: BTreeSet<ActorId>,
+    deferred: Vec<ActorId>,
+}
+
+impl RicartAgrawala {
+    /// Creates a new node.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            section: Section::new(aid),
+            clock: 0,
+            timestamp: 0,
+            replies: Default::default(),
+            deferred: vec![],
+        }
+    }
+
+    /// Tells the application when the node entered the critical section.
+    pub fn with_results(mut self, results: UnboundedSender<Granted>) -> Self {
+        self.section.results = Some(results);
+        self
+    }
+
+    /// Records the critical sections in the checker.
+    pub fn with_checker(mut self, checker: SafetyChecker) -> Self {
+        self.section.checker = Some(checker);
+        self
+    }
+}
+
+impl ProtocolHandler for RicartAgrawala {
+    type Payload = RicartAgrawalaPayload;
+
+    fn aid(&self) -> ActorId {
+        self.section.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let aid = self.section.aid;
+        let from = msg.sender().as_aid();
+        let session = *msg.session();
+
+        match msg.payload() {
+            RicartAgrawalaPayload::Request => {
+                if !self.section.request(session) {
+                    return ContinuationHandler::Done;
+                }
+                self.clock += 1;
+                self.timestamp = self.clock;
+                self.replies.clear();
+
+                if ns.is_empty() {
+                    self.section.enter();
+                    return ContinuationHandler::Done;
+                }
+                let ask = RicartAgrawalaPayload::Ask(self.timestamp);
+                self.section.broadcast(session, ask)
+            }
+            RicartAgrawalaPayload::Ask(timestamp) => {
+                self.clock = self.clock.max(*timestamp) + 1;
+                let older = (self.timestamp, aid) < (*timestamp, from);
+                if self.section.is_inside() || (self.section.is_waiting() && older) {
+                    self.deferred.push(from);
+                    return ContinuationHandler::Done;
+                }
+                self.section
+                    .send(from, session, RicartAgrawalaPayload::Reply)
+            }
+            RicartAgrawalaPayload::Reply => {
+                self.replies.insert(from);
+                if self.section.is_waiting() && self.replies.len() == ns.len() {
+                    self.section.enter();
+                }
+                ContinuationHandler::Done
+            }
+            RicartAgrawalaPayload::Release => {
+                if !self.section.release() {
+                    return ContinuationHandler::Done;
+                }
+                let replies = std::mem::take(&mut self.deferred)
+                    .into_iter()
+                    .map(|n| self.section.send(n, session, RicartAgrawalaPayload::Reply))
+                    .collect();
+                ContinuationHandler::Sequence(replies)
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! The Suzuki–Kasami mutual exclusion, with a token on a complete graph.
+//!
+//! A node without the token broadcasts the number of its request. Every node
+//! keeps the latest request number of every node, and the token keeps the
+//! number of the latest request granted to every node, with a queue of the
+//! nodes it goes to next. The token is the right to enter the critical
+//! section, and it is handed to the next node when its holder leaves it.
+
+use super::{Granted, MutexPayload, SafetyChecker, Section};
+use crate::{protocol::Message, ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::{BTreeMap, VecDeque};
+
+/// The token of the Suzuki–Kasami mutual exclusion.
+#[derive(Debug, Clone, Default)]
+pub struct SuzukiKasamiToken {
+    /// The number of the latest request granted to every node.
+    granted: BTreeMap<ActorId, u64>,
+    /// The nodes the token goes to next.
+    queue: VecDeque<ActorId>,
+}
+
+/// The payload of the Suzuki–Kasami mutual exclusion.
+#[derive(Clone, PayloadDebug)]
+pub enum SuzukiKasamiPayload {
+    /// Sent by the application to request the critical section.
+    Request,
+    /// Sent by the application to release the critical section.
+    Release,
+    /// Asks for the token, with the number of the request.
+    Ask(u64),
+    /// The token.
+    Token(SuzukiKasamiToken),
+}
+
+impl MutexPayload for SuzukiKasamiPayload {
+    fn request() -> Self {
+        Self::Request
+    }
+
+    fn release() -> Self {
+        Self::Release
+    }
+}
+
+/// A node of the Suzuki–Kasami mutual exclusion.
+pub struct SuzukiKasami {
+    section: Section,
+    requests: BTreeMap<ActorId, u64>,
+    token: Option<SuzukiKasamiToken>,
+}
+
+impl SuzukiKasami {
+    /// Creates a new node, without the token.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            section: Section::new(aid),
+            requests: Default::default(),
+            token: None,
+        }
+    }
+
+    /// Gives the token to the node, which must be done on a single node.
+    pub fn with_token(mut self) -> Self {
+        self.token = Some(Default::default());
+        self
+    }
+
+    /// Tells the application when the node entered the critical section.
+    pub fn with_results(mut self, results: UnboundedSender<Granted>) -> Self {
+        self.section.results = Some(results);
+        self
+    }
+
+    /// Records the critical sections in the checker.
+    pub fn with_checker(mut self, checker: SafetyChecker) -> Self {
+        self.section.checker = Some(checker);
+        self
+    }
+
+    /// Returns `true` if the node requested the token and did not get it yet.
+    fn is_pending(&self, token: &SuzukiKasamiToken, aid: ActorId) -> bool {
+        let requested = self.requests.get(&aid).copied().unwrap_or_default();
+        let granted = token.granted.get(&aid).copied().unwrap_or_default();
+        requested == granted + 1
+    }
+}
+
+impl ProtocolHandler for SuzukiKasami {
+    type Payload = SuzukiKasamiPayload;
+
+    fn aid(&self) -> ActorId {
+        self.section.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let aid = self.section.aid;
+        let from = msg.sender().as_aid();
+        let session = *msg.session();
+
+        match msg.payload() {
+            SuzukiKasamiPayload::Request => {
+                if !self.section.request(session) {
+                    return ContinuationHandler::Done;
+                }
+                if self.token.is_some() {
+                    self.section.enter();
+                    return ContinuationHandler::Done;
+                }
+                let number = self.requests.entry(aid).or_default();
+                *number += 1;
+                let ask = SuzukiKasamiPayload::Ask(*number);
+                self.section.broadcast(session, ask)
+            }
+            SuzukiKasamiPayload::Ask(number) => {
+                let latest = self.requests.entry(from).or_default();
+                *latest = (*latest).max(*number);
+
+                match self.token.take() {
+                    Some(token) if !self.section.is_inside() && self.is_pending(&token, from) => {
+                        let token = SuzukiKasamiPayload::Token(token);
+                        self.section.send(from, session, token)
+                    }
+                    token => {
+                        self.token = token;
+                        ContinuationHandler::Done
+                    }
+                }
+            }
+            SuzukiKasamiPayload::Token(_) => {
+                let SuzukiKasamiPayload::Token(token) = msg.payload else {
+                    unreachable!()
+                };
+                self.token = Some(token);
+                self.section.enter();
+                ContinuationHandler::Done
+            }
+            SuzukiKasamiPayload::Release => {
+                if !self.section.release() {
+                    return ContinuationHandler::Done;
+                }
+                let mut token = self.token.take().expect("the node inside has the token");
+                let requested = self.requests.get(&aid).copied().unwrap_or_default();
+                token.granted.insert(aid, requested);
+
+                for n in ns {
+                    if !token.queue.contains(&n) && self.is_pending(&token, n) {
+                        token.queue.push_back(n);
+                    }
+                }
+                match token.queue.pop_front() {
+                    Some(next) => {
+                        let token = SuzukiKasamiPayload::Token(token);
+                        self.section.send(next, session, token)
+                    }
+                    None => {
+                        self.token = Some(token);
+                        ContinuationHandler::Done
+                    }
+                }
+            }
+        }
+    }
+}
@@ -11,7 +11,7 @@ use crate::{
     graph::GraphMsg,
     protocol::Message as PMsg,
     proxy::{Builder as PxyBuilder, Proxy},
-    ActorId,
+    ActorId, GetQueueMetrics, QueueMetrics,
 };
 use actix::{dev::ToEnvelope, prelude::*};
 use log::debug;
@@ -68,6 +68,13 @@ where
             .build()
     }
 
+    /// Gets a recipient for the protocol messages sent to the node. It is used
+    /// by the transports to deliver the messages received from remote nodes.
+    #[cfg(any(feature = "channel", feature = "tcp", feature = "uds"))]
+    pub(crate) fn recipient(&self) -> Recipient<PMsg<P>> {
+        self.addr.clone().recipient::<PMsg<P>>()
+    }
+
     /// Sends to the current node a configuration message to add a new neighbour proxy
     /// to the current node.
     pub async fn add_proxy(&mut self, proxy: Proxy<PMsg<P>>) -> Result<(), MailboxError> {
@@ -96,4 +103,13 @@ where
         debug!("do_send proto {:?}", self.aid);
         self.addr.do_send(msg)
     }
+
+    /// Gets the metrics of the queue of the messages deferred by the node.
+    pub async fn queue_metrics(&self) -> Result<QueueMetrics, MailboxError>
+    where
+        A: Handler<GetQueueMetrics>,
+        A::Context: ToEnvelope<A, GetQueueMetrics>,
+    {
+        self.addr.send(GetQueueMetrics).await
+    }
 }
@@ -44,6 +44,11 @@ where
         self.proxies.iter().map(|p| p.aid)
     }
 
+    /// Returns `true` if one of the proxies has the actor identifier.
+    pub fn contains(&self, aid: &ActorId) -> bool {
+        self.proxies.iter().any(|p| p.aid() == aid)
+    }
+
     /// Adds a new proxy to the internal collection.
     #[inline]
     pub fn add_proxy(&mut self, proxy: Proxy<PMsg<P>>) {
@@ -0,0 +1,112 @@
+//! The acceptor of the Paxos consensus.
+
+use super::{broadcast, send, Ballot, PaxosCont, PaxosPayload, Proposal};
+use crate::{
+    fault::Recover,
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, ProtocolHandler,
+};
+use log::debug;
+use std::{collections::BTreeMap, fmt::Debug};
+
+/// An acceptor, which votes for the proposals of the proposers.
+pub struct Acceptor<V> {
+    aid: ActorId,
+    promised: Option<Ballot>,
+    accepted: BTreeMap<usize, (Ballot, Proposal<V>)>,
+}
+
+impl<V> Acceptor<V>
+where
+    V: Clone + Debug + Send + 'static,
+{
+    /// Creates a new acceptor, which promised nothing yet.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            promised: None,
+            accepted: Default::default(),
+        }
+    }
+
+    /// Whether the acceptor can take part in the ballot.
+    fn admits(&self, ballot: Ballot) -> bool {
+        Some(ballot) >= self.promised
+    }
+
+    fn nack(&self, to: ActorId, session: Session) -> PaxosCont<V> {
+        match self.promised {
+            Some(ballot) => send(self.aid, to, session, PaxosPayload::Nack { ballot }),
+            None => ContinuationHandler::Done,
+        }
+    }
+}
+
+impl<V> ProtocolHandler for Acceptor<V>
+where
+    V: Clone + Debug + Send + 'static,
+{
+    type Payload = PaxosPayload<V>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let from = msg.sender.as_aid();
+        let session = msg.session;
+
+        match msg.payload {
+            PaxosPayload::Prepare { ballot, slot } if self.admits(ballot) => {
+                debug!("PAXS | on {:?} | promise {:?}", self.aid, ballot);
+                self.promised = Some(ballot);
+                let accepted = self
+                    .accepted
+                    .range(slot..)
+                    .map(|(s, (b, p))| (*s, *b, p.clone()))
+                    .collect();
+                send(
+                    self.aid,
+                    from,
+                    session,
+                    PaxosPayload::Promise { ballot, accepted },
+                )
+            }
+            PaxosPayload::Accept {
+                ballot,
+                slot,
+                proposal,
+            } if self.admits(ballot) => {
+                debug!("PAXS | on {:?} | accept {:?} in {}", self.aid, ballot, slot);
+                self.promised = Some(ballot);
+                self.accepted.insert(slot, (ballot, proposal.clone()));
+                let accepted = PaxosPayload::Accepted {
+                    ballot,
+                    slot,
+                    proposal,
+                };
+                broadcast(self.aid, session, accepted)
+            }
+            PaxosPayload::Prepare { .. } | PaxosPayload::Accept { .. } => self.nack(from, session),
+            _ => ContinuationHandler::Done,
+        }
+    }
+}
+
+impl<V> Recover for Acceptor<V>
+where
+    V: Clone + Debug + Send + 'static,
+{
+    /// The promises and the accepted proposals are all kept on a stable storage.
+    fn recover(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        _session: Session,
+    ) -> ContinuationHandler<Self::Payload> {
+        ContinuationHandler::Done
+    }
+}
@@ -0,0 +1,135 @@
+//! A checker of the proposals learned by the nodes.
+
+use super::Proposal;
+use crate::{protocol::RequestId, ActorId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+/// A violation of the guarantees of the consensus.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsensusViolation<V> {
+    /// A node learned a proposal which no client made.
+    Invalid {
+        /// The node.
+        aid: ActorId,
+        /// The slot.
+        slot: usize,
+        /// The proposal.
+        proposal: Proposal<V>,
+    },
+    /// A node learned another proposal than the one learned before in the slot.
+    Disagreement {
+        /// The node.
+        aid: ActorId,
+        /// The slot.
+        slot: usize,
+        /// The proposal the node learned.
+        proposal: Proposal<V>,
+        /// The proposal learned before.
+        chosen: Proposal<V>,
+    },
+}
+
+struct Decisions<V> {
+    proposed: HashMap<RequestId, V>,
+    chosen: BTreeMap<usize, Proposal<V>>,
+    learned: HashMap<ActorId, BTreeMap<usize, Proposal<V>>>,
+    violations: Vec<ConsensusViolation<V>>,
+}
+
+/// Records the proposals the clients make and the proposals the nodes learn,
+/// and checks the agreement, i.e. that the nodes learn the same proposal in
+/// a slot, and the validity, i.e. that they only learn proposals made by the
+/// clients.
+pub struct ConsensusChecker<V> {
+    decisions: Arc<Mutex<Decisions<V>>>,
+}
+
+impl<V> Clone for ConsensusChecker<V> {
+    fn clone(&self) -> Self {
+        Self {
+            decisions: self.decisions.clone(),
+        }
+    }
+}
+
+impl<V> Default for ConsensusChecker<V> {
+    fn default() -> Self {
+        Self {
+            decisions: Arc::new(Mutex::new(Decisions {
+                proposed: Default::default(),
+                chosen: Default::default(),
+                learned: Default::default(),
+                violations: vec![],
+            })),
+        }
+    }
+}
+
+impl<V> ConsensusChecker<V>
+where
+    V: Clone + PartialEq + Debug,
+{
+    /// Creates a checker with no proposal made.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records that a client made the proposal.
+    pub fn propose(&self, proposal: Proposal<V>) {
+        let mut decisions = self.decisions.lock().unwrap();
+        decisions.proposed.insert(proposal.request, proposal.value);
+    }
+
+    /// Records that the node learned the proposal in the slot.
+    pub fn learn(&self, aid: ActorId, slot: usize, proposal: Proposal<V>) {
+        let mut decisions = self.decisions.lock().unwrap();
+        if decisions.proposed.get(&proposal.request) != Some(&proposal.value) {
+            let proposal = proposal.clone();
+            decisions.violations.push(ConsensusViolation::Invalid {
+                aid,
+                slot,
+                proposal,
+            });
+        }
+        match decisions.chosen.get(&slot) {
+            Some(chosen) if *chosen != proposal => {
+                let chosen = chosen.clone();
+                decisions.violations.push(ConsensusViolation::Disagreement {
+                    aid,
+                    slot,
+                    proposal: proposal.clone(),
+                    chosen,
+                });
+            }
+            Some(_) => (),
+            None => {
+                decisions.chosen.insert(slot, proposal.clone());
+            }
+        }
+        decisions
+            .learned
+            .entry(aid)
+            .or_default()
+            .insert(slot, proposal);
+    }
+
+    /// Returns the proposals learned first in every slot.
+    pub fn chosen(&self) -> BTreeMap<usize, Proposal<V>> {
+        self.decisions.lock().unwrap().chosen.clone()
+    }
+
+    /// Returns the proposals the node learned, by slot.
+    pub fn learned(&self, aid: ActorId) -> BTreeMap<usize, Proposal<V>> {
+        let decisions = self.decisions.lock().unwrap();
+        decisions.learned.get(&aid).cloned().unwrap_or_default()
+    }
+
+    /// Returns the violations of the guarantees of the consensus.
+    pub fn violations(&self) -> Vec<ConsensusViolation<V>> {
+        self.decisions.lock().unwrap().violations.clone()
+    }
+}
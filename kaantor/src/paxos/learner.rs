@@ -0,0 +1,131 @@
+//! The learner of the Paxos consensus.
+
+use super::{quorum, Ballot, Chosen, ConsensusChecker, PaxosPayload, Proposal};
+use crate::{
+    fault::Recover,
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::info;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+/// A learner, which learns the proposals chosen by the acceptors.
+pub struct Learner<V> {
+    aid: ActorId,
+    acceptors: usize,
+    votes: BTreeMap<(usize, Ballot), BTreeSet<ActorId>>,
+    chosen: BTreeMap<usize, Proposal<V>>,
+    results: Option<UnboundedSender<Chosen<V>>>,
+    checker: Option<ConsensusChecker<V>>,
+}
+
+impl<V> Learner<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Creates a new learner, among `acceptors` acceptors.
+    pub fn new(aid: ActorId, acceptors: usize) -> Self {
+        Self {
+            aid,
+            acceptors,
+            votes: Default::default(),
+            chosen: Default::default(),
+            results: None,
+            checker: None,
+        }
+    }
+
+    /// Sends the proposals the learner learns to the channel.
+    pub fn with_results(mut self, results: UnboundedSender<Chosen<V>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Records the proposals the learner learns in the checker.
+    pub fn with_checker(mut self, checker: ConsensusChecker<V>) -> Self {
+        self.checker = Some(checker);
+        self
+    }
+
+    /// Gets the proposals learned, by slot.
+    pub fn chosen(&self) -> &BTreeMap<usize, Proposal<V>> {
+        &self.chosen
+    }
+
+    fn learn(&mut self, slot: usize, proposal: Proposal<V>) {
+        if self.chosen.contains_key(&slot) {
+            return;
+        }
+        info!(
+            "PAXS | on {:?} | learn {:?} in {}",
+            self.aid, proposal, slot
+        );
+        self.chosen.insert(slot, proposal.clone());
+        self.votes.retain(|(s, _), _| *s != slot);
+        if let Some(checker) = &self.checker {
+            checker.learn(self.aid, slot, proposal.clone());
+        }
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Chosen {
+                aid: self.aid,
+                slot,
+                proposal,
+            });
+        }
+    }
+}
+
+impl<V> ProtocolHandler for Learner<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    type Payload = PaxosPayload<V>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let from = msg.sender.as_aid();
+
+        match msg.payload {
+            PaxosPayload::Accepted {
+                ballot,
+                slot,
+                proposal,
+            } if !self.chosen.contains_key(&slot) => {
+                let votes = self.votes.entry((slot, ballot)).or_default();
+                votes.insert(from);
+                if votes.len() >= quorum(self.acceptors) {
+                    self.learn(slot, proposal);
+                }
+            }
+            PaxosPayload::Decided { slot, proposal } => self.learn(slot, proposal),
+            _ => (),
+        }
+        ContinuationHandler::Done
+    }
+}
+
+impl<V> Recover for Learner<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// The proposals learned are kept on a stable storage.
+    fn recover(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        _session: Session,
+    ) -> ContinuationHandler<Self::Payload> {
+        self.votes.clear();
+        ContinuationHandler::Done
+    }
+}
@@ -0,0 +1,283 @@
+//! The Paxos consensus, single-decree and Multi-Paxos.
+//!
+//! The nodes play the roles of proposers, acceptors and learners, any of them
+//! on the same node with the `Paxos` handler. The proposers get the values of
+//! the clients through the API, and propose them in the instances, or slots,
+//! of the consensus with ballots ordered by round then by `ActorId`, so that
+//! two proposers never share a ballot. A value is chosen in a slot once a
+//! majority of the acceptors accepted it with the same ballot.
+//!
+//! A proposer which completed the first phase leads the following slots too,
+//! and only runs the second phase for them, until a higher ballot preempts it.
+//! The other proposers forward their values to the leader while they hear of
+//! it. A single-decree proposer only proposes in the first slot.
+//!
+//! The proposers repeat their messages on a timer, and the leader repeats the
+//! chosen values, so that the consensus copes with lost messages. A value is
+//! proposed with the `RequestId` of its submission, which tells it from the
+//! same value submitted by another request.
+
+mod acceptor;
+mod checker;
+mod learner;
+mod node;
+mod proposer;
+
+pub use acceptor::*;
+pub use checker::*;
+pub use learner::*;
+pub use node::*;
+pub use proposer::*;
+
+use crate::{
+    protocol::{Builder, RequestId, Session},
+    ActorId, ContinuationHandler, PayloadDebug,
+};
+use std::{fmt::Debug, time::Duration};
+
+/// A ballot, which orders the proposals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ballot {
+    /// The round of the ballot.
+    pub round: u64,
+    /// The proposer which owns the ballot.
+    pub aid: ActorId,
+}
+
+/// A value submitted by a client, with the request which submitted it.
+#[derive(Clone, PartialEq)]
+pub struct Proposal<V> {
+    /// The request.
+    pub request: RequestId,
+    /// The value.
+    pub value: V,
+}
+
+impl<V: Debug> Debug for Proposal<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}:{:?}", self.request, self.value)
+    }
+}
+
+/// A proposal chosen in a slot, as learned by a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chosen<V> {
+    /// The node.
+    pub aid: ActorId,
+    /// The slot.
+    pub slot: usize,
+    /// The proposal.
+    pub proposal: Proposal<V>,
+}
+
+/// The payload of the Paxos consensus on the values `V`.
+#[derive(Clone, PayloadDebug)]
+pub enum PaxosPayload<V> {
+    /// Submits a proposal, sent by the API or forwarded to the leader.
+    Submit(Proposal<V>),
+    /// The timer of a proposer, with its epoch.
+    Tick(u64),
+    /// Asks the acceptors to promise to ignore the lower ballots, from the slot on.
+    Prepare {
+        /// The ballot.
+        ballot: Ballot,
+        /// The first slot.
+        slot: usize,
+    },
+    /// Promises to ignore the lower ballots, with the proposals the acceptor accepted.
+    Promise {
+        /// The ballot.
+        ballot: Ballot,
+        /// The slots, with the ballot and the proposal the acceptor last accepted.
+        accepted: Vec<(usize, Ballot, Proposal<V>)>,
+    },
+    /// Asks the acceptors to accept the proposal in the slot.
+    Accept {
+        /// The ballot.
+        ballot: Ballot,
+        /// The slot.
+        slot: usize,
+        /// The proposal.
+        proposal: Proposal<V>,
+    },
+    /// Tells that the acceptor accepted the proposal in the slot.
+    Accepted {
+        /// The ballot.
+        ballot: Ballot,
+        /// The slot.
+        slot: usize,
+        /// The proposal.
+        proposal: Proposal<V>,
+    },
+    /// Rejects a ballot lower than the one the acceptor promised.
+    Nack {
+        /// The ballot the acceptor promised.
+        ballot: Ballot,
+    },
+    /// Tells that the proposal was chosen in the slot.
+    Decided {
+        /// The slot.
+        slot: usize,
+        /// The proposal.
+        proposal: Proposal<V>,
+    },
+}
+
+type PaxosCont<V> = ContinuationHandler<PaxosPayload<V>>;
+
+/// The acceptors needed to choose a value.
+fn quorum(acceptors: usize) -> usize {
+    acceptors / 2 + 1
+}
+
+/// Sends to a node, which is the node itself when it plays several roles.
+fn send<V>(aid: ActorId, to: ActorId, session: Session, payload: PaxosPayload<V>) -> PaxosCont<V> {
+    let msg = Builder::with_from_actor(aid)
+        .with_to_actor(to)
+        .with_session(session)
+        .with_payload(payload)
+        .with_sender(aid)
+        .build();
+    if to == aid {
+        ContinuationHandler::Schedule(Duration::ZERO, msg)
+    } else {
+        ContinuationHandler::SendToNode(to, msg)
+    }
+}
+
+/// Sends to all the neighbours, which ignore the messages of the roles they
+/// do not play, and to the node itself.
+fn broadcast<V: Clone>(aid: ActorId, session: Session, payload: PaxosPayload<V>) -> PaxosCont<V> {
+    let msg = Builder::with_from_actor(aid)
+        .with_to_all_actors()
+        .with_session(session)
+        .with_payload(payload.clone())
+        .with_sender(aid)
+        .build();
+    ContinuationHandler::SendToAllNodes(msg).and_then(send(aid, aid, session, payload))
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        fault::{FaultPayload, Faults},
+        protocol::{Message, SessionAllocator},
+        NodeActor,
+    };
+    use futures::StreamExt;
+    use std::{collections::BTreeSet, time::Duration};
+
+    const N: usize = 5;
+
+    fn submit(
+        session: Session,
+        aid: usize,
+        proposal: Proposal<u64>,
+    ) -> Message<FaultPayload<PaxosPayload<u64>>> {
+        Builder::with_from_api()
+            .with_to_actor(aid.into())
+            .with_session(session)
+            .with_payload(FaultPayload::App(PaxosPayload::Submit(proposal)))
+            .with_sender(aid.into())
+            .build()
+    }
+
+    /// The `seq`th value of the client, among the values 0 to 2.
+    fn proposal(seq: u64) -> Proposal<u64> {
+        Proposal {
+            request: RequestId::new(0, seq),
+            value: seq % 3,
+        }
+    }
+
+    /// Runs the nodes 1 and 2 as proposers, the nodes 2 to 5 as acceptors and
+    /// the nodes 4 and 5 as learners, on a complete graph which loses and
+    /// duplicates the messages. The proposers get their proposals, and the
+    /// learners learn proposals until `done`.
+    async fn consensus(
+        single: bool,
+        proposals: &[(usize, Proposal<u64>)],
+        done: impl Fn(&ConsensusChecker<u64>) -> bool,
+    ) -> ConsensusChecker<u64> {
+        let checker = ConsensusChecker::new();
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes: Vec<_> = (1..=N)
+            .map(|i| {
+                let aid = ActorId::from(i);
+                let mut paxos = Paxos::new(aid);
+                if i <= 2 {
+                    let proposer = Proposer::new(aid, 4, Duration::from_millis(10));
+                    let proposer = if single {
+                        proposer.single_decree()
+                    } else {
+                        proposer
+                    };
+                    paxos = paxos.with_proposer(proposer);
+                }
+                if i >= 2 {
+                    paxos = paxos.with_acceptor(Acceptor::new(aid));
+                }
+                if i >= 4 {
+                    let learner = Learner::new(aid, 4)
+                        .with_results(tx.clone())
+                        .with_checker(checker.clone());
+                    paxos = paxos.with_learner(learner);
+                }
+                NodeActor::build(Faults::new(paxos).with_loss(0.2).with_duplication(0.2))
+            })
+            .collect();
+        for b in 1..N {
+            for a in 0..b {
+                let (left, right) = nodes.split_at_mut(b);
+                add_edge(&mut left[a], &mut right[0]).await;
+            }
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for (aid, proposal) in proposals {
+            checker.propose(proposal.clone());
+            nodes[aid - 1].do_send(submit(session, *aid, proposal.clone()));
+        }
+        while !done(&checker) {
+            actix_rt::time::timeout(Duration::from_secs(10), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        checker
+    }
+
+    fn learners() -> impl Iterator<Item = ActorId> {
+        (4..=N).map(ActorId::from)
+    }
+
+    #[actix_rt::test]
+    async fn single_decree() {
+        // Both proposers compete for the single slot.
+        let done = |c: &ConsensusChecker<u64>| learners().all(|l| c.learned(l).contains_key(&0));
+        let checker = consensus(true, &[(1, proposal(1)), (2, proposal(2))], done).await;
+
+        assert_eq!(Vec::<ConsensusViolation<u64>>::new(), checker.violations());
+        assert!([proposal(1), proposal(2)].contains(&checker.chosen()[&0]));
+    }
+
+    #[actix_rt::test]
+    async fn multi_paxos() {
+        // Both proposers get half of the proposals, which are all chosen,
+        // although they repeat the same few values.
+        let proposals: Vec<_> = (1..=20)
+            .map(|seq| (seq as usize % 2 + 1, proposal(seq)))
+            .collect();
+        let done = |c: &ConsensusChecker<u64>| {
+            learners().all(|l| {
+                let learned: BTreeSet<_> = c.learned(l).into_values().map(|p| p.request).collect();
+                proposals.iter().all(|(_, p)| learned.contains(&p.request))
+            })
+        };
+        let checker = consensus(false, &proposals, done).await;
+
+        assert_eq!(Vec::<ConsensusViolation<u64>>::new(), checker.violations());
+    }
+}
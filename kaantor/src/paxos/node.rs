@@ -0,0 +1,121 @@
+//! A node playing any of the roles of the Paxos consensus.
+
+use super::{Acceptor, Learner, PaxosPayload, Proposer};
+use crate::{
+    fault::Recover,
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, ProtocolHandler,
+};
+use std::fmt::Debug;
+
+/// A node of the Paxos consensus, which plays the roles it is given. Every
+/// role handles every message the node receives, and ignores the messages
+/// meant for the others.
+pub struct Paxos<V> {
+    aid: ActorId,
+    proposer: Option<Proposer<V>>,
+    acceptor: Option<Acceptor<V>>,
+    learner: Option<Learner<V>>,
+}
+
+impl<V> Paxos<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Creates a new node, which plays no role yet.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            proposer: None,
+            acceptor: None,
+            learner: None,
+        }
+    }
+
+    /// Plays the role of the proposer.
+    pub fn with_proposer(mut self, proposer: Proposer<V>) -> Self {
+        self.proposer = Some(proposer);
+        self
+    }
+
+    /// Plays the role of the acceptor.
+    pub fn with_acceptor(mut self, acceptor: Acceptor<V>) -> Self {
+        self.acceptor = Some(acceptor);
+        self
+    }
+
+    /// Plays the role of the learner.
+    pub fn with_learner(mut self, learner: Learner<V>) -> Self {
+        self.learner = Some(learner);
+        self
+    }
+
+    /// Gets the proposer, if the node plays its role.
+    pub fn proposer(&self) -> Option<&Proposer<V>> {
+        self.proposer.as_ref()
+    }
+
+    /// Gets the acceptor, if the node plays its role.
+    pub fn acceptor(&self) -> Option<&Acceptor<V>> {
+        self.acceptor.as_ref()
+    }
+
+    /// Gets the learner, if the node plays its role.
+    pub fn learner(&self) -> Option<&Learner<V>> {
+        self.learner.as_ref()
+    }
+}
+
+impl<V> ProtocolHandler for Paxos<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    type Payload = PaxosPayload<V>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let mut res = ContinuationHandler::Done;
+        if let Some(proposer) = &mut self.proposer {
+            res = res.and_then(proposer.receive(ns.iter().copied(), msg.clone()));
+        }
+        if let Some(acceptor) = &mut self.acceptor {
+            res = res.and_then(acceptor.receive(ns.iter().copied(), msg.clone()));
+        }
+        if let Some(learner) = &mut self.learner {
+            res = res.and_then(learner.receive(ns.iter().copied(), msg));
+        }
+        res
+    }
+}
+
+impl<V> Recover for Paxos<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    fn recover(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let mut res = ContinuationHandler::Done;
+        if let Some(proposer) = &mut self.proposer {
+            res = res.and_then(proposer.recover(ns.iter().copied(), session));
+        }
+        if let Some(acceptor) = &mut self.acceptor {
+            res = res.and_then(acceptor.recover(ns.iter().copied(), session));
+        }
+        if let Some(learner) = &mut self.learner {
+            res = res.and_then(learner.recover(ns.iter().copied(), session));
+        }
+        res
+    }
+}
@@ -0,0 +1,442 @@
+//! The proposer of the Paxos consensus.
+
+use super::{broadcast, quorum, send, Ballot, PaxosCont, PaxosPayload, Proposal};
+use crate::{
+    fault::{Recover, Xorshift},
+    protocol::{Builder, Message, RequestId, Session},
+    ActorId, ContinuationHandler, ProtocolHandler,
+};
+use log::{debug, info};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
+    fmt::Debug,
+    mem,
+    time::Duration,
+};
+
+enum Phase<V> {
+    Idle,
+    Preparing {
+        promises: BTreeMap<ActorId, Vec<(usize, Ballot, Proposal<V>)>>,
+    },
+    Leading,
+}
+
+/// A proposer, which proposes the values of the clients to the acceptors.
+pub struct Proposer<V> {
+    aid: ActorId,
+    acceptors: usize,
+    single: bool,
+    // The state which survives the crashes.
+    round: u64,
+    pending: VecDeque<Proposal<V>>,
+    chosen: BTreeMap<usize, Proposal<V>>,
+    decided: HashSet<RequestId>,
+    // The volatile state.
+    ballot: Option<Ballot>,
+    phase: Phase<V>,
+    proposals: BTreeMap<usize, Proposal<V>>,
+    votes: BTreeMap<usize, BTreeSet<ActorId>>,
+    leader: Option<ActorId>,
+    heard: bool,
+    ticking: bool,
+    epoch: u64,
+    session: Session,
+    // The configuration.
+    tick: Duration,
+    rng: Xorshift,
+}
+
+impl<V> Proposer<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Creates a new Multi-Paxos proposer, among `acceptors` acceptors, whose
+    /// timer ticks between `tick` and twice `tick`.
+    pub fn new(aid: ActorId, acceptors: usize, tick: Duration) -> Self {
+        Self {
+            aid,
+            acceptors,
+            single: false,
+            round: 0,
+            pending: Default::default(),
+            chosen: Default::default(),
+            decided: Default::default(),
+            ballot: None,
+            phase: Phase::Idle,
+            proposals: Default::default(),
+            votes: Default::default(),
+            leader: None,
+            heard: false,
+            ticking: false,
+            epoch: 0,
+            session: 0.into(),
+            tick,
+            rng: Xorshift::new(aid),
+        }
+    }
+
+    /// Only proposes in the first slot, and drops the values
+    /// submitted once a value was chosen.
+    pub fn single_decree(mut self) -> Self {
+        self.single = true;
+        self
+    }
+
+    /// Gets the proposals chosen, by slot, as far as the proposer knows.
+    pub fn chosen(&self) -> &BTreeMap<usize, Proposal<V>> {
+        &self.chosen
+    }
+
+    /// Whether the proposer leads the slots.
+    pub fn is_leading(&self) -> bool {
+        matches!(self.phase, Phase::Leading)
+    }
+
+    fn is_decided(&self) -> bool {
+        self.single && self.chosen.contains_key(&0)
+    }
+
+    fn is_known(&self, request: RequestId) -> bool {
+        self.decided.contains(&request)
+            || self.pending.iter().any(|p| p.request == request)
+            || self.proposals.values().any(|p| p.request == request)
+    }
+
+    fn first_free(&self) -> usize {
+        (0..).find(|s| !self.chosen.contains_key(s)).unwrap()
+    }
+
+    fn next_free(&self) -> usize {
+        (0..)
+            .find(|s| !self.chosen.contains_key(s) && !self.proposals.contains_key(s))
+            .unwrap()
+    }
+
+    /// Submits again a proposal which was not chosen, before the others.
+    fn requeue(&mut self, proposal: Proposal<V>) {
+        if !self.is_known(proposal.request) && !self.is_decided() {
+            self.pending.push_front(proposal);
+        }
+    }
+
+    /// Schedules the next tick, unless it is scheduled or there is nothing to do.
+    fn wake(&mut self) -> PaxosCont<V> {
+        if self.ticking || (self.pending.is_empty() && matches!(self.phase, Phase::Idle)) {
+            return ContinuationHandler::Done;
+        }
+        self.ticking = true;
+        let millis = self.tick.as_millis() as u64;
+        let delay = self.tick + Duration::from_millis(self.rng.next() % millis.max(1));
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(self.aid)
+            .with_session(self.session)
+            .with_payload(PaxosPayload::Tick(self.epoch))
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::Schedule(delay, msg)
+    }
+
+    fn prepare(&mut self) -> PaxosCont<V> {
+        self.round += 1;
+        let ballot = Ballot {
+            round: self.round,
+            aid: self.aid,
+        };
+        info!("PAXS | on {:?} | prepare {:?}", self.aid, ballot);
+        self.ballot = Some(ballot);
+        self.phase = Phase::Preparing {
+            promises: Default::default(),
+        };
+        self.votes.clear();
+        let slot = self.first_free();
+        broadcast(
+            self.aid,
+            self.session,
+            PaxosPayload::Prepare { ballot, slot },
+        )
+    }
+
+    /// Starts leading, and proposes in every slot the proposal accepted
+    /// with the highest ballot by the acceptors which promised.
+    fn lead(
+        &mut self,
+        promises: BTreeMap<ActorId, Vec<(usize, Ballot, Proposal<V>)>>,
+    ) -> PaxosCont<V> {
+        info!("PAXS | on {:?} | leads with {:?}", self.aid, self.ballot);
+        self.phase = Phase::Leading;
+        self.leader = None;
+
+        let mut highest: BTreeMap<usize, (Ballot, Proposal<V>)> = BTreeMap::new();
+        for (slot, ballot, proposal) in promises.into_values().flatten() {
+            if self.chosen.contains_key(&slot) {
+                continue;
+            }
+            if highest.get(&slot).is_none_or(|(b, _)| ballot > *b) {
+                highest.insert(slot, (ballot, proposal));
+            }
+        }
+        for (slot, (_, proposal)) in highest {
+            let request = proposal.request;
+            if let Some(own) = self.proposals.insert(slot, proposal) {
+                if own.request != request {
+                    self.requeue(own);
+                }
+            }
+        }
+        let proposals = &self.proposals;
+        self.pending
+            .retain(|p| !proposals.values().any(|q| q.request == p.request));
+
+        let accepts = self
+            .proposals
+            .iter()
+            .map(|(slot, proposal)| self.accept(*slot, proposal.clone()))
+            .collect();
+        ContinuationHandler::Sequence(accepts).and_then(self.propose())
+    }
+
+    /// Proposes the pending proposals in the free slots.
+    fn propose(&mut self) -> PaxosCont<V> {
+        let mut res = ContinuationHandler::Done;
+        while let Some(proposal) = self.pending.pop_front() {
+            let slot = self.next_free();
+            if self.single && slot > 0 {
+                debug!("PAXS | on {:?} | drop {:?}", self.aid, proposal);
+                continue;
+            }
+            self.proposals.insert(slot, proposal.clone());
+            res = res.and_then(self.accept(slot, proposal));
+        }
+        res
+    }
+
+    fn accept(&self, slot: usize, proposal: Proposal<V>) -> PaxosCont<V> {
+        match self.ballot {
+            Some(ballot) => broadcast(
+                self.aid,
+                self.session,
+                PaxosPayload::Accept {
+                    ballot,
+                    slot,
+                    proposal,
+                },
+            ),
+            None => ContinuationHandler::Done,
+        }
+    }
+
+    /// Records the proposal chosen in the slot.
+    fn learn(&mut self, slot: usize, proposal: Proposal<V>) {
+        if self.chosen.contains_key(&slot) {
+            return;
+        }
+        let request = proposal.request;
+        self.chosen.insert(slot, proposal);
+        self.decided.insert(request);
+        self.votes.remove(&slot);
+        if let Some(own) = self.proposals.remove(&slot) {
+            if own.request != request {
+                self.requeue(own);
+            }
+        }
+        self.pending.retain(|p| p.request != request);
+        if self.is_decided() {
+            self.pending.clear();
+        }
+    }
+
+    /// Gives up leading, once a higher ballot preempted the proposer.
+    fn preempt(&mut self, ballot: Ballot) {
+        self.round = self.round.max(ballot.round);
+        self.leader = Some(ballot.aid);
+        self.heard = true;
+        if Some(ballot) <= self.ballot {
+            return;
+        }
+        if !matches!(self.phase, Phase::Idle) {
+            info!("PAXS | on {:?} | preempted by {:?}", self.aid, ballot);
+        }
+        self.phase = Phase::Idle;
+        self.votes.clear();
+        for proposal in mem::take(&mut self.proposals).into_values() {
+            self.requeue(proposal);
+        }
+    }
+
+    fn on_tick(&mut self) -> PaxosCont<V> {
+        match self.phase {
+            // The leader repeats the accepts still running and the proposals
+            // chosen, which tell the others it is alive.
+            Phase::Leading => {
+                let accepts = self
+                    .proposals
+                    .iter()
+                    .map(|(slot, proposal)| self.accept(*slot, proposal.clone()));
+                let decided = self.chosen.iter().map(|(slot, proposal)| {
+                    let proposal = proposal.clone();
+                    broadcast(
+                        self.aid,
+                        self.session,
+                        PaxosPayload::Decided {
+                            slot: *slot,
+                            proposal,
+                        },
+                    )
+                });
+                let res = ContinuationHandler::Sequence(accepts.chain(decided).collect());
+                res.and_then(self.propose())
+            }
+            Phase::Preparing { .. } => {
+                let ballot = self.ballot.unwrap();
+                let slot = self.first_free();
+                broadcast(
+                    self.aid,
+                    self.session,
+                    PaxosPayload::Prepare { ballot, slot },
+                )
+            }
+            Phase::Idle if self.pending.is_empty() => ContinuationHandler::Done,
+            Phase::Idle => match self.leader {
+                Some(leader) if self.heard => {
+                    self.heard = false;
+                    let forwards = self
+                        .pending
+                        .iter()
+                        .map(|p| {
+                            send(
+                                self.aid,
+                                leader,
+                                self.session,
+                                PaxosPayload::Submit(p.clone()),
+                            )
+                        })
+                        .collect();
+                    ContinuationHandler::Sequence(forwards)
+                }
+                _ => self.prepare(),
+            },
+        }
+    }
+}
+
+impl<V> ProtocolHandler for Proposer<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    type Payload = PaxosPayload<V>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let from = msg.sender.as_aid();
+
+        let res = match msg.payload {
+            PaxosPayload::Submit(proposal) => {
+                self.session = msg.session;
+                if self.is_known(proposal.request) || self.is_decided() {
+                    ContinuationHandler::Done
+                } else {
+                    self.pending.push_back(proposal);
+                    match self.phase {
+                        Phase::Leading => self.propose(),
+                        _ => ContinuationHandler::Done,
+                    }
+                }
+            }
+            PaxosPayload::Tick(epoch) if epoch == self.epoch => {
+                self.ticking = false;
+                self.on_tick()
+            }
+            PaxosPayload::Promise { ballot, accepted } if Some(ballot) == self.ballot => {
+                match &mut self.phase {
+                    Phase::Preparing { promises } => {
+                        promises.insert(from, accepted);
+                        if promises.len() >= quorum(self.acceptors) {
+                            let promises = mem::take(promises);
+                            self.lead(promises)
+                        } else {
+                            ContinuationHandler::Done
+                        }
+                    }
+                    _ => ContinuationHandler::Done,
+                }
+            }
+            PaxosPayload::Accepted {
+                ballot,
+                slot,
+                proposal,
+            } if Some(ballot) == self.ballot && self.is_leading() => {
+                if self.chosen.contains_key(&slot) {
+                    return ContinuationHandler::Done;
+                }
+                let votes = self.votes.entry(slot).or_default();
+                votes.insert(from);
+                if votes.len() < quorum(self.acceptors) {
+                    return ContinuationHandler::Done;
+                }
+                info!(
+                    "PAXS | on {:?} | chose {:?} in {}",
+                    self.aid, proposal, slot
+                );
+                self.learn(slot, proposal.clone());
+                let decided = broadcast(
+                    self.aid,
+                    self.session,
+                    PaxosPayload::Decided { slot, proposal },
+                );
+                decided.and_then(self.propose())
+            }
+            // A higher ballot is running, its proposer leads.
+            PaxosPayload::Accepted { ballot, .. } if Some(ballot) > self.ballot => {
+                self.preempt(ballot);
+                ContinuationHandler::Done
+            }
+            PaxosPayload::Nack { ballot } => {
+                self.preempt(ballot);
+                ContinuationHandler::Done
+            }
+            PaxosPayload::Decided { slot, proposal } => {
+                if from != self.aid {
+                    self.leader = Some(from);
+                    self.heard = true;
+                }
+                self.learn(slot, proposal);
+                ContinuationHandler::Done
+            }
+            _ => ContinuationHandler::Done,
+        };
+        res.and_then(self.wake())
+    }
+}
+
+impl<V> Recover for Proposer<V>
+where
+    V: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Forgets the ballot it ran, and the leader it knew of. The rounds, the
+    /// proposals to make and the proposals chosen are kept on a stable storage.
+    fn recover(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        _session: Session,
+    ) -> ContinuationHandler<Self::Payload> {
+        self.ballot = None;
+        self.phase = Phase::Idle;
+        self.votes.clear();
+        for proposal in mem::take(&mut self.proposals).into_values() {
+            self.requeue(proposal);
+        }
+        self.leader = None;
+        self.heard = false;
+        self.ticking = false;
+        self.epoch += 1;
+        self.wake()
+    }
+}
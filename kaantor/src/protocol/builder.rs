@@ -174,6 +174,7 @@ impl<P> Builder<P, states::Ready> {
     /// Finalizes the chain by building the `Message` instance.
     pub fn build(self) -> super::Message<P> {
         super::Message {
+            mid: None,
             from: self.from.unwrap(),
             to: self.to.unwrap(),
             session: self.session.unwrap(),
@@ -201,6 +202,7 @@ mod utests {
         assert_eq!(Session::from(50), msg.session);
         assert_eq!(SenderId::from(200), msg.sender);
         assert_eq!(5000, msg.payload);
+        assert_eq!(None, msg.mid());
 
         let msg = Builder::with_message(msg).with_sender(300.into()).build();
 
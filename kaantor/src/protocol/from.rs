@@ -4,6 +4,7 @@ use crate::ActorId;
 
 /// Represents origin of the message.
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FromId {
     /// The message is originating from an actor
     Actor(ActorId),
@@ -0,0 +1,76 @@
+use crate::ActorId;
+use std::fmt::Debug;
+
+/// Represents the globally unique identifier of a message. It is made of
+/// the identifier of the actor which sent the message and a counter local
+/// to that actor.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageId {
+    aid: ActorId,
+    seq: usize,
+}
+
+impl Debug for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "M{}.{}", self.aid.inner(), self.seq)
+    }
+}
+
+impl MessageId {
+    pub(crate) fn new(aid: ActorId, seq: usize) -> Self {
+        Self { aid, seq }
+    }
+
+    /// Gets the identifier of the actor which assigned the message identifier.
+    pub fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    /// Gets the value of the counter at the moment the identifier was assigned.
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+}
+
+/// A generator of message identifiers for a given actor.
+#[derive(Debug)]
+pub(crate) struct MessageIds {
+    aid: ActorId,
+    next: usize,
+}
+
+impl MessageIds {
+    pub(crate) fn new(aid: ActorId) -> Self {
+        Self { aid, next: 0 }
+    }
+
+    /// Returns a new message identifier, incrementing the internal counter.
+    pub(crate) fn next_mid(&mut self) -> MessageId {
+        let mid = MessageId::new(self.aid, self.next);
+        self.next += 1;
+        mid
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn next_mid_() {
+        let mut ids = MessageIds::new(5.into());
+
+        let m0 = ids.next_mid();
+        let m1 = ids.next_mid();
+
+        assert_ne!(m0, m1);
+        assert!(m0 < m1);
+        assert_eq!(ActorId::from(5), m1.aid());
+        assert_eq!(1, m1.seq());
+        assert_eq!("M5.1", format!("{m1:?}"));
+
+        let mut others = MessageIds::new(6.into());
+        assert_ne!(m0, others.next_mid());
+    }
+}
@@ -2,12 +2,18 @@
 
 mod builder;
 mod from;
+mod mid;
+mod request;
 mod sender;
 mod session;
 mod to;
+#[cfg(feature = "serde")]
+pub mod wire;
 
 pub use builder::*;
 pub use from::*;
+pub use mid::*;
+pub use request::*;
 pub use sender::*;
 pub use session::*;
 pub use to::*;
@@ -16,7 +22,9 @@ use std::fmt::Debug;
 
 /// A protocol message
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message<P> {
+    pub(crate) mid: Option<MessageId>,
     pub(crate) from: FromId,
     pub(crate) to: ToId,
     pub(crate) session: Session,
@@ -31,13 +39,19 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "[{:?}] [{:?}-->{:?}] [{:?}]",
-            &self.session, &self.from, &self.to, &self.sender
+            "[{:?}] [{:?}] [{:?}-->{:?}] [{:?}]",
+            &self.mid, &self.session, &self.from, &self.to, &self.sender
         )
     }
 }
 
 impl<P> Message<P> {
+    /// Get the `MessageId`. The identifier is assigned when the message is sent,
+    /// so a message which was just built has no identifier yet.
+    pub fn mid(&self) -> Option<MessageId> {
+        self.mid
+    }
+
     /// Get the `FromId`
     pub fn from(&self) -> &FromId {
         &self.from
@@ -62,6 +76,21 @@ impl<P> Message<P> {
     pub fn payload(&self) -> &P {
         &self.payload
     }
+
+    /// Transforms the payload, keeping all the other fields of the message.
+    pub fn map_payload<Q, F>(self, f: F) -> Message<Q>
+    where
+        F: FnOnce(P) -> Q,
+    {
+        Message {
+            mid: self.mid,
+            from: self.from,
+            to: self.to,
+            session: self.session,
+            sender: self.sender,
+            payload: f(self.payload),
+        }
+    }
 }
 
 impl<P> actix::Message for Message<P> {
@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+
+/// Represents the identifier of a request of a client, made of the client
+/// and a counter local to that client. A client submitting a request again
+/// keeps its identifier, so the nodes tell it from a new request which
+/// happens to carry the same value.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestId {
+    client: usize,
+    seq: u64,
+}
+
+impl Debug for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "R{}.{}", self.client, self.seq)
+    }
+}
+
+impl RequestId {
+    /// Creates the identifier of the `seq`th request of the client.
+    pub fn new(client: usize, seq: u64) -> Self {
+        Self { client, seq }
+    }
+
+    /// Gets the client which made the request.
+    pub fn client(&self) -> usize {
+        self.client
+    }
+
+    /// Gets the value of the counter of the client for the request.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn request_id() {
+        let rid = RequestId::new(2, 7);
+        assert_eq!("R2.7", format!("{rid:?}"));
+        assert!(rid < RequestId::new(2, 8));
+        assert!(rid < RequestId::new(3, 0));
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 
 /// Reprsents the sender of the message for this current leg.
 #[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SenderId(ActorId);
 
 impl Debug for SenderId {
@@ -1,17 +1,150 @@
-use std::fmt::Debug;
+use crate::ActorId;
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-/// Represents a unique session
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Session(usize);
+/// The next client of the api, which gets its own namespace of sessions.
+static API_CLIENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// The allocator of a session, which keeps apart the sessions of the
+/// different allocators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Origin {
+    /// Built with `Session::from`.
+    Raw,
+    /// Allocated for the api, in the namespace of the given client.
+    Api(usize),
+    /// Allocated by the given actor.
+    Actor(ActorId),
+}
+
+/// Represents a unique session. A session is made of a sequence number and
+/// of its origin: the actor or the api client which allocated it, if any.
+/// Sessions are ordered by their sequence number first and by their origin
+/// next, so algorithms can use them for tie-breaking.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    seq: usize,
+    origin: Origin,
+}
 
 impl Debug for Session {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "K{}", self.0)
+        match self.origin {
+            Origin::Raw => write!(f, "K{}", self.seq),
+            Origin::Api(client) => write!(f, "K{}.api{}", self.seq, client),
+            Origin::Actor(aid) => write!(f, "K{}.{}", self.seq, aid.inner()),
+        }
     }
 }
 
 impl From<usize> for Session {
     fn from(value: usize) -> Self {
-        Self(value)
+        Self {
+            seq: value,
+            origin: Origin::Raw,
+        }
+    }
+}
+
+impl Session {
+    /// Gets the sequence number of the session.
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+
+    /// Gets the actor which allocated the session, or `None` for
+    /// the other sessions.
+    pub fn origin(&self) -> Option<ActorId> {
+        match self.origin {
+            Origin::Actor(aid) => Some(aid),
+            _ => None,
+        }
+    }
+
+    /// Gets the allocator of the session, which numbers its sessions in order.
+    pub(crate) fn allocator(&self) -> Origin {
+        self.origin
+    }
+
+    /// Whether the session was allocated for the api.
+    pub fn is_api(&self) -> bool {
+        matches!(self.origin, Origin::Api(_))
+    }
+}
+
+/// Allocates the sessions initiated by a node, or by a client of the api.
+/// The sessions carry the identifier of the node, or a client namespace
+/// which no other allocator of the process gets, so they are unique across
+/// the whole network as long as every node uses its own allocator. They
+/// never collide with the sessions built with `Session::from`.
+#[derive(Debug)]
+pub struct SessionAllocator {
+    origin: Origin,
+    next: usize,
+}
+
+impl SessionAllocator {
+    /// Creates a new allocator for the given actor.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            origin: Origin::Actor(aid),
+            next: 0,
+        }
+    }
+
+    /// Creates a new allocator for the computations started through the api,
+    /// in a client namespace of its own.
+    pub fn for_api() -> Self {
+        Self {
+            origin: Origin::Api(API_CLIENTS.fetch_add(1, Ordering::Relaxed)),
+            next: 0,
+        }
+    }
+
+    /// Returns a new session, greater than all the sessions
+    /// previously returned by this allocator.
+    pub fn next_session(&mut self) -> Session {
+        let seq = self.next;
+        self.next += 1;
+
+        Session {
+            seq,
+            origin: self.origin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+
+    #[test]
+    fn next_session_() {
+        let mut a1 = SessionAllocator::new(1.into());
+        let mut a2 = SessionAllocator::new(2.into());
+
+        let s10 = a1.next_session();
+        let s11 = a1.next_session();
+        let s20 = a2.next_session();
+
+        assert_ne!(s10, s20);
+        assert!(s10 < s11);
+        assert!(s10 < s20);
+        assert!(s20 < s11);
+        assert_eq!(Some(ActorId::from(2)), s20.origin());
+        assert_eq!("K1.1", format!("{s11:?}"));
+
+        let mut c1 = SessionAllocator::for_api();
+        let api = c1.next_session();
+        assert!(api < c1.next_session());
+        assert!(api.is_api());
+        assert_eq!(None, api.origin());
+        assert_ne!(api, Session::from(0));
+        assert_ne!(api, SessionAllocator::for_api().next_session());
+        assert!(format!("{api:?}").starts_with("K0.api"));
     }
 }
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 
 /// Represents the destination of the message.
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ToId {
     /// The destination is a specific actor.
     Actor(ActorId),
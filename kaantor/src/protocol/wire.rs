@@ -0,0 +1,183 @@
+//! A compact binary encoding for the protocol messages.
+//!
+//! Every encoded message starts with a header made of the `MAGIC` bytes
+//! followed by the version of the wire format. The rest of the frame is
+//! the message itself, encoded with variable length integers.
+
+use super::Message;
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Display;
+
+/// The bytes which mark the beginning of an encoded message.
+pub const MAGIC: [u8; 2] = *b"KT";
+
+/// The current version of the wire format.
+pub const VERSION: u8 = 2;
+
+const HEADER_LEN: usize = MAGIC.len() + 1;
+
+/// The errors which can occur while encoding or decoding a message.
+#[derive(Debug)]
+pub enum WireError {
+    /// The buffer is shorter than the header.
+    Truncated,
+    /// The buffer does not start with the `MAGIC` bytes.
+    BadMagic,
+    /// The message was encoded with a version we cannot decode.
+    UnsupportedVersion(u8),
+    /// The message could not be serialized or deserialized.
+    Codec(bincode::Error),
+}
+
+impl Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "truncated header"),
+            Self::BadMagic => write!(f, "bad magic bytes"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported version {v}"),
+            Self::Codec(e) => write!(f, "codec error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<bincode::Error> for WireError {
+    fn from(value: bincode::Error) -> Self {
+        Self::Codec(value)
+    }
+}
+
+#[inline]
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Encodes a message, prefixing it with the versioned header.
+pub fn encode<P>(msg: &Message<P>) -> Result<Vec<u8>, WireError>
+where
+    P: Serialize,
+{
+    let body = options().serialize(msg)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+    bytes.extend(body);
+
+    Ok(bytes)
+}
+
+/// Decodes a message previously encoded with `encode`.
+pub fn decode<P>(bytes: &[u8]) -> Result<Message<P>, WireError>
+where
+    P: DeserializeOwned,
+{
+    if bytes.len() < HEADER_LEN {
+        return Err(WireError::Truncated);
+    }
+
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(WireError::BadMagic);
+    }
+
+    match bytes[MAGIC.len()] {
+        VERSION => Ok(options().deserialize(&bytes[HEADER_LEN..])?),
+        v => Err(WireError::UnsupportedVersion(v)),
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        protocol::{Builder, FromId, MessageId, SessionAllocator, ToId},
+        ActorId,
+    };
+
+    fn round_trip(msg: Message<String>) {
+        let bytes = encode(&msg).unwrap();
+        assert_eq!(&MAGIC, &bytes[..2]);
+        assert_eq!(VERSION, bytes[2]);
+
+        let decoded: Message<String> = decode(&bytes).unwrap();
+
+        assert_eq!(msg.mid(), decoded.mid());
+        assert_eq!(msg.from(), decoded.from());
+        assert_eq!(msg.to(), decoded.to());
+        assert_eq!(msg.session(), decoded.session());
+        assert_eq!(msg.sender(), decoded.sender());
+        assert_eq!(msg.payload(), decoded.payload());
+    }
+
+    #[test]
+    fn round_trip_to_actor() {
+        let mut msg = Builder::with_from_actor(5.into())
+            .with_to_actor(10.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload("go".to_string())
+            .with_sender(5.into())
+            .build();
+        msg.mid = Some(MessageId::new(5.into(), 7));
+
+        round_trip(msg);
+    }
+
+    #[test]
+    fn round_trip_to_all() {
+        let msg = Builder::with_from_api()
+            .with_to_all_actors()
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload("start".to_string())
+            .with_sender(1.into())
+            .build();
+
+        assert_eq!(&FromId::Api, msg.from());
+        round_trip(msg);
+    }
+
+    #[test]
+    fn round_trip_to_all_except() {
+        let mut msg = Builder::with_from_actor(1.into())
+            .with_to_all_actors()
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload("forward".to_string())
+            .with_sender(2.into())
+            .build();
+        msg.to = ToId::AllExcept(vec![ActorId::from(3), ActorId::from(4)]);
+
+        round_trip(msg);
+    }
+
+    #[test]
+    fn decode_bad_header() {
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(5000_usize)
+            .with_sender(1.into())
+            .build();
+        let bytes = encode(&msg).unwrap();
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(matches!(decode::<usize>(&bad), Err(WireError::BadMagic)));
+
+        let mut bad = bytes.clone();
+        bad[2] = VERSION + 1;
+        assert!(matches!(
+            decode::<usize>(&bad),
+            Err(WireError::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+
+        assert!(matches!(
+            decode::<usize>(&bytes[..2]),
+            Err(WireError::Truncated)
+        ));
+        assert!(matches!(
+            decode::<usize>(&bytes[..4]),
+            Err(WireError::Codec(_))
+        ));
+    }
+}
@@ -1,14 +1,17 @@
 //! A implementation for a proxy for a remote node.
+//!
+//! A proxy is FIFO: the messages sent through the same proxy are received by
+//! the remote node in the order they were sent, as the mailbox of an actor keeps
+//! the order of the messages of each sender.
 
 mod builder;
 mod metrics;
-mod mid;
 
 pub use builder::*;
 
 use crate::ActorId;
 
-use self::{metrics::Metrics, mid::MessageId};
+use self::metrics::Metrics;
 use actix::prelude::*;
 use log::{debug, error};
 
@@ -20,7 +23,6 @@ where
     M::Result: Send,
 {
     pub(crate) aid: ActorId,
-    mid: MessageId,
     metrics: Metrics,
     recipient: Recipient<M>,
 }
@@ -30,11 +32,6 @@ where
     M: Message + Send,
     M::Result: Send,
 {
-    #[inline]
-    fn incrememt_mid(&mut self) -> MessageId {
-        self.mid.increment_mid()
-    }
-
     #[inline]
     fn debug_op(&self, op: &str, from: &ActorId) {
         let to = &self.aid;
@@ -44,7 +41,6 @@ where
     fn new(aid: ActorId, recipient: Recipient<M>) -> Self {
         Self {
             aid,
-            mid: Default::default(),
             metrics: Default::default(),
             recipient,
         }
@@ -62,7 +58,6 @@ where
 
     /// Sends a message `M` to the remote node.
     pub async fn send(&mut self, from: &ActorId, msg: M) -> Result<M::Result, MailboxError> {
-        let mid = self.incrememt_mid();
         self.debug_op("send", from);
 
         match self.recipient.send(msg).await {
@@ -71,7 +66,7 @@ where
                 Ok(x)
             }
             Err(e) => {
-                error!("send'fd [{}]", mid);
+                error!("send'fd [{:?}-->{:?}]", from, self.aid);
                 self.metrics.record_failure();
                 Err(e)
             }
@@ -80,7 +75,6 @@ where
 
     /// Tries to send a message `M` to the remote node.
     pub fn try_send(&mut self, from: &ActorId, msg: M) -> Result<(), SendError<M>> {
-        let mid = self.incrememt_mid();
         self.debug_op("try_send", from);
 
         match self.recipient.try_send(msg) {
@@ -89,7 +83,7 @@ where
                 Ok(x)
             }
             Err(e) => {
-                error!("send'fd [{}]", mid);
+                error!("send'fd [{:?}-->{:?}]", from, self.aid);
                 self.metrics.record_failure();
                 Err(e)
             }
@@ -98,7 +92,6 @@ where
 
     /// Does send a message to the remote node.
     pub fn do_send(&mut self, from: &ActorId, msg: M) {
-        let _mid = self.incrememt_mid();
         self.debug_op("do_send", from);
 
         self.recipient.do_send(msg);
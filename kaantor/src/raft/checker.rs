@@ -0,0 +1,200 @@
+//! A checker of the logs applied by the nodes.
+
+use crate::{protocol::RequestId, ActorId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+/// A violation of the guarantees of the replicated log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogViolation<C> {
+    /// A node applied an entry before the previous ones.
+    Gap {
+        /// The node.
+        aid: ActorId,
+        /// The index of the entry.
+        index: usize,
+    },
+    /// A node applied another entry than the one another node applied
+    /// at the same index.
+    Diverged {
+        /// The node.
+        aid: ActorId,
+        /// The index of the entry.
+        index: usize,
+        /// The entry the node applied.
+        entry: (RequestId, C),
+        /// The entry applied before at this index.
+        applied: (RequestId, C),
+    },
+    /// A request was applied at two indexes.
+    Duplicate {
+        /// The request.
+        request: RequestId,
+        /// The indexes.
+        indexes: (usize, usize),
+    },
+    /// A request was ordered before a request which was applied
+    /// before it was submitted.
+    Order {
+        /// The request applied first.
+        first: RequestId,
+        /// The request submitted later, but ordered before.
+        later: RequestId,
+    },
+}
+
+/// A request submitted by a client, with the logical times of its submission
+/// and of its first application, and its index.
+struct Operation {
+    invoked: u64,
+    completed: Option<(u64, usize)>,
+}
+
+struct History<C> {
+    clock: u64,
+    log: Vec<(RequestId, C)>,
+    applied: HashMap<ActorId, usize>,
+    operations: BTreeMap<RequestId, Operation>,
+    violations: Vec<LogViolation<C>>,
+}
+
+/// Records the requests the clients submit and the entries the nodes apply,
+/// and checks that all the nodes apply the same log, in which every request
+/// appears once, and whose order agrees with the real time order of the
+/// requests: a request applied before another is submitted comes first.
+pub struct LogChecker<C> {
+    history: Arc<Mutex<History<C>>>,
+}
+
+impl<C> Clone for LogChecker<C> {
+    fn clone(&self) -> Self {
+        Self {
+            history: self.history.clone(),
+        }
+    }
+}
+
+impl<C> Default for LogChecker<C> {
+    fn default() -> Self {
+        Self {
+            history: Arc::new(Mutex::new(History {
+                clock: 0,
+                log: vec![],
+                applied: Default::default(),
+                operations: Default::default(),
+                violations: vec![],
+            })),
+        }
+    }
+}
+
+impl<C> LogChecker<C>
+where
+    C: Clone + PartialEq + Debug,
+{
+    /// Creates a checker with an empty history.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records that a client submitted the request, unless it was submitted before.
+    pub fn invoke(&self, request: RequestId) {
+        let mut history = self.history.lock().unwrap();
+        if !history.operations.contains_key(&request) {
+            history.clock += 1;
+            let invoked = history.clock;
+            history.operations.insert(
+                request,
+                Operation {
+                    invoked,
+                    completed: None,
+                },
+            );
+        }
+    }
+
+    /// Records that the node applied the entry at the index, from 1. A node
+    /// which restarts applies its log again from the start.
+    pub fn apply(&self, aid: ActorId, index: usize, request: RequestId, command: C) {
+        let mut history = self.history.lock().unwrap();
+        history.clock += 1;
+        let clock = history.clock;
+
+        let applied = history.applied.entry(aid).or_default();
+        if index > *applied + 1 {
+            history.violations.push(LogViolation::Gap { aid, index });
+            return;
+        }
+        *applied = index;
+
+        let entry = (request, command);
+        match history.log.get(index - 1) {
+            Some(applied) if *applied != entry => {
+                let applied = applied.clone();
+                history.violations.push(LogViolation::Diverged {
+                    aid,
+                    index,
+                    entry,
+                    applied,
+                });
+            }
+            Some(_) => (),
+            None => {
+                history.log.push(entry);
+                if let Some(op) = history.operations.get_mut(&request) {
+                    op.completed.get_or_insert((clock, index));
+                }
+            }
+        }
+    }
+
+    /// Returns the index of the latest entry the node applied.
+    pub fn applied(&self, aid: ActorId) -> usize {
+        let history = self.history.lock().unwrap();
+        history.applied.get(&aid).copied().unwrap_or_default()
+    }
+
+    /// Returns the log applied by the nodes.
+    pub fn log(&self) -> Vec<(RequestId, C)> {
+        self.history.lock().unwrap().log.clone()
+    }
+
+    /// Returns the violations of the guarantees of the replicated log.
+    pub fn violations(&self) -> Vec<LogViolation<C>> {
+        let history = self.history.lock().unwrap();
+        let mut violations = history.violations.clone();
+
+        let mut indexes = HashMap::new();
+        for (i, (request, _)) in history.log.iter().enumerate() {
+            if let Some(first) = indexes.insert(*request, i + 1) {
+                violations.push(LogViolation::Duplicate {
+                    request: *request,
+                    indexes: (first, i + 1),
+                });
+            }
+        }
+
+        for (first, op) in &history.operations {
+            let Some((completed, index)) = op.completed else {
+                continue;
+            };
+            for (later, later_op) in &history.operations {
+                match later_op.completed {
+                    Some((_, later_index))
+                        if completed < later_op.invoked && later_index < index =>
+                    {
+                        violations.push(LogViolation::Order {
+                            first: *first,
+                            later: *later,
+                        });
+                    }
+                    _ => (),
+                }
+            }
+        }
+        violations
+    }
+}
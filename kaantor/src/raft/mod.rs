@@ -0,0 +1,219 @@
+//! The Raft consensus, which replicates a log of commands on the nodes.
+//!
+//! The nodes elect a leader for a term, which appends the commands the clients
+//! submit to its log and replicates its log on the other nodes. An entry is
+//! committed once a majority of the nodes stored it, and the nodes apply the
+//! committed entries in the order of the log. The clients can submit their
+//! commands to any node, which forwards them to the leader it knows of. A
+//! command submitted again with the same `RequestId` is appended only once.
+//!
+//! The timers are scheduled messages, cancelled by an epoch, and the nodes can
+//! be crashed and partitioned when they are wrapped in `fault::Faults`. A node
+//! keeps its term, its vote and its log across the crashes.
+
+mod checker;
+mod node;
+
+pub use checker::*;
+pub use node::*;
+
+use crate::{protocol::RequestId, ActorId, PayloadDebug};
+
+/// An entry of the log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry<C> {
+    /// The term in which the leader appended the entry.
+    pub term: u64,
+    /// The request which submitted the command.
+    pub request: RequestId,
+    /// The command.
+    pub command: C,
+}
+
+/// An entry applied by a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Applied<C> {
+    /// The node.
+    pub aid: ActorId,
+    /// The index of the entry, from 1.
+    pub index: usize,
+    /// The term of the entry.
+    pub term: u64,
+    /// The request which submitted the command.
+    pub request: RequestId,
+    /// The command.
+    pub command: C,
+}
+
+/// The payload of the Raft consensus on the commands `C`.
+#[derive(Clone, PayloadDebug)]
+pub enum RaftPayload<C> {
+    /// Sent by the API to start the node.
+    Start,
+    /// Submits a command, sent by the API or forwarded to the leader.
+    Submit(RequestId, C),
+    /// The election timer, with its epoch.
+    Election(u64),
+    /// The heartbeat timer of the leader, with its epoch.
+    Heartbeat(u64),
+    /// Asks for the vote of the node.
+    RequestVote {
+        /// The term of the candidate.
+        term: u64,
+        /// The index of the last entry of the candidate.
+        last_index: usize,
+        /// The term of the last entry of the candidate.
+        last_term: u64,
+    },
+    /// Answers a request for a vote.
+    Vote {
+        /// The term of the node.
+        term: u64,
+        /// Whether the node voted for the candidate.
+        granted: bool,
+    },
+    /// Appends the entries following the previous one, or only
+    /// tells that the leader is alive if there is none.
+    Append {
+        /// The term of the leader.
+        term: u64,
+        /// The index of the entry before the entries.
+        prev_index: usize,
+        /// The term of the entry before the entries.
+        prev_term: u64,
+        /// The entries.
+        entries: Vec<Entry<C>>,
+        /// The commit index of the leader.
+        commit: usize,
+    },
+    /// Answers an append.
+    Appended {
+        /// The term of the node.
+        term: u64,
+        /// Whether the entries were appended.
+        success: bool,
+        /// The index of the last entry appended, or of the previous entry
+        /// the leader should try next.
+        index: usize,
+    },
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        fault::{FaultPayload, Faults},
+        protocol::{Builder, Message, RequestId, Session, SessionAllocator},
+        NodeActor,
+    };
+    use futures::StreamExt;
+    use std::{collections::BTreeSet, time::Duration};
+
+    const N: usize = 5;
+    const COMMANDS: u64 = 30;
+
+    fn api(
+        session: Session,
+        aid: usize,
+        payload: FaultPayload<RaftPayload<u64>>,
+    ) -> Message<FaultPayload<RaftPayload<u64>>> {
+        Builder::with_from_api()
+            .with_to_actor(aid.into())
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(aid.into())
+            .build()
+    }
+
+    #[actix_rt::test]
+    async fn raft() {
+        let checker = LogChecker::new();
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes: Vec<_> = (1..=N)
+            .map(|i| {
+                let raft = Raft::new(i.into(), Duration::from_millis(40))
+                    .with_results(tx.clone())
+                    .with_checker(checker.clone());
+                NodeActor::build(Faults::new(raft))
+            })
+            .collect();
+        for b in 1..N {
+            for a in 0..b {
+                let (left, right) = nodes.split_at_mut(b);
+                add_edge(&mut left[a], &mut right[0]).await;
+            }
+        }
+        let session = SessionAllocator::for_api().next_session();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.do_send(api(session, i + 1, FaultPayload::App(RaftPayload::Start)));
+        }
+
+        // Every node is crashed or isolated in turn, the leader included,
+        // once a number of commands was applied.
+        let others = |aid: usize| (1..=N).filter(|i| *i != aid).map(ActorId::from).collect();
+        let mut faults = vec![
+            (4, 1, FaultPayload::Crash),
+            (8, 1, FaultPayload::Restart),
+            (8, 2, FaultPayload::Crash),
+            (12, 2, FaultPayload::Restart),
+            (12, 3, FaultPayload::Isolate(others(3))),
+            (16, 3, FaultPayload::Heal),
+            (16, 4, FaultPayload::Crash),
+            (20, 4, FaultPayload::Restart),
+            (20, 5, FaultPayload::Crash),
+            (24, 5, FaultPayload::Restart),
+        ]
+        .into_iter()
+        .peekable();
+
+        // The client submits the next command, and submits again the ones
+        // not applied yet, to the nodes in turn. The commands repeat the
+        // same few values, which are told apart by their requests.
+        let mut submitted = 0;
+        let mut turn = 0;
+        let mut terms = BTreeSet::new();
+        let mut tick = actix_rt::time::interval(Duration::from_millis(10));
+        let deadline = actix_rt::time::Instant::now() + Duration::from_secs(20);
+        loop {
+            let applied: BTreeSet<_> = checker.log().into_iter().map(|(r, _)| r).collect();
+            while let Some((_, aid, fault)) = faults.next_if(|(at, ..)| applied.len() >= *at) {
+                nodes[aid - 1].do_send(api(session, aid, fault));
+            }
+            if applied.len() as u64 == COMMANDS && faults.peek().is_none() {
+                break;
+            }
+            assert!(actix_rt::time::Instant::now() < deadline, "{:?}", applied);
+
+            if submitted < COMMANDS {
+                submitted += 1;
+                checker.invoke(RequestId::new(0, submitted));
+            }
+            for seq in 1..=submitted {
+                let request = RequestId::new(0, seq);
+                if applied.contains(&request) {
+                    continue;
+                }
+                turn = (turn + 1) % N;
+                let submit = FaultPayload::App(RaftPayload::Submit(request, seq % 3));
+                nodes[turn].do_send(api(session, turn + 1, submit));
+            }
+
+            tick.tick().await;
+            while let Ok(applied) = rx.try_recv() {
+                terms.insert(applied.term);
+            }
+        }
+
+        // Every node applies the whole log, the crashed ones included.
+        while (1..=N).any(|i| checker.applied(i.into()) < COMMANDS as usize) {
+            assert!(actix_rt::time::Instant::now() < deadline);
+            let _ = actix_rt::time::timeout(Duration::from_millis(100), rx.next()).await;
+        }
+
+        // The first leader lost its leadership to another.
+        assert!(terms.len() > 1, "{:?}", terms);
+        assert_eq!(COMMANDS as usize, checker.log().len());
+        assert_eq!(Vec::<LogViolation<u64>>::new(), checker.violations());
+    }
+}
@@ -0,0 +1,520 @@
+//! A node of the Raft consensus.
+
+use super::{Applied, Entry, LogChecker, RaftPayload};
+use crate::{
+    fault::{Recover, Xorshift},
+    protocol::{Builder, Message, RequestId, Session},
+    ActorId, ContinuationHandler, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::{info, warn};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+    time::Duration,
+};
+
+type RaftCont<C> = ContinuationHandler<RaftPayload<C>>;
+
+/// The entries sent at most in a single append.
+const MAX_ENTRIES: usize = 32;
+
+enum Role {
+    Follower,
+    Candidate {
+        votes: BTreeSet<ActorId>,
+    },
+    Leader {
+        next: BTreeMap<ActorId, usize>,
+        matched: BTreeMap<ActorId, usize>,
+    },
+}
+
+/// A node of the Raft consensus, on a complete graph, which replicates
+/// the commands `C`.
+pub struct Raft<C> {
+    aid: ActorId,
+    // The state which survives the crashes.
+    term: u64,
+    voted_for: Option<ActorId>,
+    log: Vec<Entry<C>>,
+    // The number of entries of the log for every request.
+    requests: HashMap<RequestId, usize>,
+    // The volatile state.
+    role: Role,
+    leader: Option<ActorId>,
+    commit: usize,
+    applied: usize,
+    epoch: u64,
+    session: Session,
+    // The configuration.
+    timeout: Duration,
+    rng: Xorshift,
+    results: Option<UnboundedSender<Applied<C>>>,
+    checker: Option<LogChecker<C>>,
+}
+
+impl<C> Raft<C>
+where
+    C: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Creates a new node, whose election timeout is drawn between
+    /// `timeout` and twice `timeout`. The leader sends heartbeats
+    /// every quarter of `timeout`.
+    pub fn new(aid: ActorId, timeout: Duration) -> Self {
+        Self {
+            aid,
+            term: 0,
+            voted_for: None,
+            log: vec![],
+            requests: Default::default(),
+            role: Role::Follower,
+            leader: None,
+            commit: 0,
+            applied: 0,
+            epoch: 0,
+            session: 0.into(),
+            timeout,
+            rng: Xorshift::new(aid),
+            results: None,
+            checker: None,
+        }
+    }
+
+    /// Sends the entries applied by the node to the channel.
+    pub fn with_results(mut self, results: UnboundedSender<Applied<C>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Records the entries applied by the node in the checker.
+    pub fn with_checker(mut self, checker: LogChecker<C>) -> Self {
+        self.checker = Some(checker);
+        self
+    }
+
+    /// Gets the current term of the node.
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    /// Gets the leader the node knows of, if any.
+    pub fn leader(&self) -> Option<ActorId> {
+        self.leader
+    }
+
+    fn last(&self) -> (usize, u64) {
+        (self.log.len(), self.term_at(self.log.len()))
+    }
+
+    fn term_at(&self, index: usize) -> u64 {
+        match index {
+            0 => 0,
+            i => self.log.get(i - 1).map(|e| e.term).unwrap_or_default(),
+        }
+    }
+
+    /// Appends the entry to the log.
+    fn push(&mut self, entry: Entry<C>) {
+        *self.requests.entry(entry.request).or_default() += 1;
+        self.log.push(entry);
+    }
+
+    /// Removes the entries of the log from the index `len + 1` on.
+    fn truncate(&mut self, len: usize) {
+        for entry in self.log.drain(len.min(self.log.len())..) {
+            if let Some(count) = self.requests.get_mut(&entry.request) {
+                *count -= 1;
+                if *count == 0 {
+                    self.requests.remove(&entry.request);
+                }
+            }
+        }
+    }
+
+    fn majority(&self, ns: &[ActorId]) -> usize {
+        // A majority of the node and its neighbours.
+        let size = ns.len() + 1;
+        size / 2 + 1
+    }
+
+    fn send(&self, to: ActorId, payload: RaftPayload<C>) -> RaftCont<C> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(self.session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    fn broadcast(&self, payload: RaftPayload<C>) -> RaftCont<C> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(self.session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToAllNodes(msg)
+    }
+
+    /// Schedules a timer, and cancels the ones scheduled before.
+    fn schedule(
+        &mut self,
+        delay: Duration,
+        timer: impl FnOnce(u64) -> RaftPayload<C>,
+    ) -> RaftCont<C> {
+        self.epoch += 1;
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(self.aid)
+            .with_session(self.session)
+            .with_payload(timer(self.epoch))
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::Schedule(delay, msg)
+    }
+
+    fn election_timer(&mut self) -> RaftCont<C> {
+        // At random, to draw the timeouts of the nodes apart.
+        let millis = self.timeout.as_millis() as u64;
+        let delay = self.timeout + Duration::from_millis(self.rng.next() % millis.max(1));
+        self.schedule(delay, RaftPayload::Election)
+    }
+
+    /// Follows the leader of a newer term.
+    fn step_down(&mut self, term: u64) -> RaftCont<C> {
+        if term > self.term {
+            self.term = term;
+            self.voted_for = None;
+        }
+        if let Role::Leader { .. } = self.role {
+            info!(
+                "RAFT | on {:?} | steps down in term {}",
+                self.aid, self.term
+            );
+        }
+        self.role = Role::Follower;
+        self.election_timer()
+    }
+
+    fn campaign(&mut self, ns: &[ActorId]) -> RaftCont<C> {
+        self.term += 1;
+        self.voted_for = Some(self.aid);
+        self.leader = None;
+        self.role = Role::Candidate {
+            votes: BTreeSet::from([self.aid]),
+        };
+        info!("RAFT | on {:?} | campaigns in term {}", self.aid, self.term);
+
+        if self.majority(ns) == 1 {
+            return self.lead(ns);
+        }
+        let (last_index, last_term) = self.last();
+        let request = self.broadcast(RaftPayload::RequestVote {
+            term: self.term,
+            last_index,
+            last_term,
+        });
+        request.and_then(self.election_timer())
+    }
+
+    fn lead(&mut self, ns: &[ActorId]) -> RaftCont<C> {
+        info!("RAFT | on {:?} | leads in term {}", self.aid, self.term);
+        let next = self.log.len() + 1;
+        self.role = Role::Leader {
+            next: ns.iter().map(|n| (*n, next)).collect(),
+            matched: ns.iter().map(|n| (*n, 0)).collect(),
+        };
+        self.leader = Some(self.aid);
+        self.heartbeat(ns)
+    }
+
+    /// Sends the missing entries to every follower, then schedules the next heartbeat.
+    fn heartbeat(&mut self, ns: &[ActorId]) -> RaftCont<C> {
+        let appends = ns.iter().map(|n| self.append(*n)).collect();
+        let timer = self.schedule(self.timeout / 4, RaftPayload::Heartbeat);
+        ContinuationHandler::Sequence(appends).and_then(timer)
+    }
+
+    fn append(&self, to: ActorId) -> RaftCont<C> {
+        let Role::Leader { next, .. } = &self.role else {
+            return ContinuationHandler::Done;
+        };
+        let prev_index = next.get(&to).copied().unwrap_or(1) - 1;
+        let entries = self.log[prev_index..]
+            .iter()
+            .take(MAX_ENTRIES)
+            .cloned()
+            .collect();
+
+        self.send(
+            to,
+            RaftPayload::Append {
+                term: self.term,
+                prev_index,
+                prev_term: self.term_at(prev_index),
+                entries,
+                commit: self.commit,
+            },
+        )
+    }
+
+    /// Commits the entries of the current term replicated on a majority.
+    fn advance(&mut self, ns: &[ActorId]) {
+        let Role::Leader { matched, .. } = &self.role else {
+            return;
+        };
+        let majority = self.majority(ns);
+        let replicated = |index: usize| 1 + matched.values().filter(|m| **m >= index).count();
+
+        let commit = (self.commit + 1..=self.log.len())
+            .rev()
+            .find(|i| self.term_at(*i) == self.term && replicated(*i) >= majority);
+        if let Some(commit) = commit {
+            self.commit = commit;
+        }
+        self.apply();
+    }
+
+    fn apply(&mut self) {
+        while self.applied < self.commit {
+            self.applied += 1;
+            let entry = &self.log[self.applied - 1];
+            if let Some(checker) = &self.checker {
+                checker.apply(self.aid, self.applied, entry.request, entry.command.clone());
+            }
+            if let Some(results) = &self.results {
+                let _ = results.unbounded_send(Applied {
+                    aid: self.aid,
+                    index: self.applied,
+                    term: entry.term,
+                    request: entry.request,
+                    command: entry.command.clone(),
+                });
+            }
+        }
+    }
+
+    fn on_append(
+        &mut self,
+        from: ActorId,
+        term: u64,
+        prev_index: usize,
+        prev_term: u64,
+        entries: Vec<Entry<C>>,
+        commit: usize,
+    ) -> RaftCont<C> {
+        if term < self.term {
+            let reply = RaftPayload::Appended {
+                term: self.term,
+                success: false,
+                index: 0,
+            };
+            return self.send(from, reply);
+        }
+
+        let timer = self.step_down(term);
+        self.leader = Some(from);
+        if prev_index > self.log.len() || self.term_at(prev_index) != prev_term {
+            let index = self.log.len().min(prev_index.saturating_sub(1));
+            let reply = RaftPayload::Appended {
+                term: self.term,
+                success: false,
+                index,
+            };
+            return timer.and_then(self.send(from, reply));
+        }
+
+        let matched = prev_index + entries.len();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let index = prev_index + i + 1;
+            if self.term_at(index) != entry.term {
+                self.truncate(index - 1);
+                self.push(entry);
+            }
+        }
+        self.commit = self.commit.max(commit.min(matched));
+        self.apply();
+
+        let reply = RaftPayload::Appended {
+            term: self.term,
+            success: true,
+            index: matched,
+        };
+        timer.and_then(self.send(from, reply))
+    }
+
+    fn on_appended(
+        &mut self,
+        ns: &[ActorId],
+        from: ActorId,
+        term: u64,
+        success: bool,
+        index: usize,
+    ) -> RaftCont<C> {
+        if term > self.term {
+            return self.step_down(term);
+        }
+        let Role::Leader { next, matched } = &mut self.role else {
+            return ContinuationHandler::Done;
+        };
+        if term < self.term {
+            return ContinuationHandler::Done;
+        }
+
+        if success {
+            let m = matched.entry(from).or_default();
+            *m = (*m).max(index);
+            next.insert(from, *m + 1);
+            self.advance(ns);
+            ContinuationHandler::Done
+        } else {
+            next.insert(from, index + 1);
+            self.append(from)
+        }
+    }
+
+    fn on_request_vote(
+        &mut self,
+        from: ActorId,
+        term: u64,
+        last_index: usize,
+        last_term: u64,
+    ) -> RaftCont<C> {
+        let mut res = ContinuationHandler::Done;
+        if term > self.term {
+            res = self.step_down(term);
+            self.leader = None;
+        }
+
+        let free = self.voted_for.is_none_or(|v| v == from);
+        let (index, log_term) = self.last();
+        let granted = term == self.term && free && (last_term, last_index) >= (log_term, index);
+        if granted {
+            self.voted_for = Some(from);
+            res = self.election_timer();
+        }
+
+        let vote = RaftPayload::Vote {
+            term: self.term,
+            granted,
+        };
+        res.and_then(self.send(from, vote))
+    }
+
+    fn on_vote(&mut self, ns: &[ActorId], from: ActorId, term: u64, granted: bool) -> RaftCont<C> {
+        if term > self.term {
+            return self.step_down(term);
+        }
+        let majority = self.majority(ns);
+        match &mut self.role {
+            Role::Candidate { votes } if term == self.term && granted => {
+                votes.insert(from);
+                if votes.len() >= majority {
+                    return self.lead(ns);
+                }
+                ContinuationHandler::Done
+            }
+            _ => ContinuationHandler::Done,
+        }
+    }
+
+    fn on_submit(&mut self, ns: &[ActorId], request: RequestId, command: C) -> RaftCont<C> {
+        match (&self.role, self.leader) {
+            (Role::Leader { .. }, _) => {
+                if self.requests.contains_key(&request) {
+                    return ContinuationHandler::Done;
+                }
+                self.push(Entry {
+                    term: self.term,
+                    request,
+                    command,
+                });
+                self.advance(ns);
+                ContinuationHandler::Sequence(ns.iter().map(|n| self.append(*n)).collect())
+            }
+            (_, Some(leader)) if leader != self.aid => {
+                self.send(leader, RaftPayload::Submit(request, command))
+            }
+            _ => {
+                warn!("RAFT | on {:?} | no leader for {:?}", self.aid, request);
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+impl<C> ProtocolHandler for Raft<C>
+where
+    C: Clone + PartialEq + Debug + Send + 'static,
+{
+    type Payload = RaftPayload<C>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let from = msg.sender().as_aid();
+
+        match msg.payload {
+            RaftPayload::Start => {
+                self.session = msg.session;
+                self.election_timer()
+            }
+            RaftPayload::Submit(request, command) => self.on_submit(&ns, request, command),
+            RaftPayload::Election(epoch) if epoch == self.epoch => match self.role {
+                Role::Leader { .. } => ContinuationHandler::Done,
+                _ => self.campaign(&ns),
+            },
+            RaftPayload::Heartbeat(epoch) if epoch == self.epoch => match self.role {
+                Role::Leader { .. } => self.heartbeat(&ns),
+                _ => ContinuationHandler::Done,
+            },
+            RaftPayload::Election(_) | RaftPayload::Heartbeat(_) => ContinuationHandler::Done,
+            RaftPayload::RequestVote {
+                term,
+                last_index,
+                last_term,
+            } => self.on_request_vote(from, term, last_index, last_term),
+            RaftPayload::Vote { term, granted } => self.on_vote(&ns, from, term, granted),
+            RaftPayload::Append {
+                term,
+                prev_index,
+                prev_term,
+                entries,
+                commit,
+            } => self.on_append(from, term, prev_index, prev_term, entries, commit),
+            RaftPayload::Appended {
+                term,
+                success,
+                index,
+            } => self.on_appended(&ns, from, term, success, index),
+        }
+    }
+}
+
+impl<C> Recover for Raft<C>
+where
+    C: Clone + PartialEq + Debug + Send + 'static,
+{
+    /// Keeps the term, the vote and the log, and starts over as a follower
+    /// which applies its log again once it learns the commit index.
+    fn recover(
+        &mut self,
+        _neighbours: impl Iterator<Item = ActorId>,
+        session: Session,
+    ) -> ContinuationHandler<Self::Payload> {
+        self.role = Role::Follower;
+        self.leader = None;
+        self.commit = 0;
+        self.applied = 0;
+        self.session = session;
+        self.election_timer()
+    }
+}
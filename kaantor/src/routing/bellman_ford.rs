@@ -0,0 +1,143 @@
+use super::{weights, Route, RoutingTable};
+use crate::{
+    graph::Topology,
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashMap;
+
+/// The payload of the Bellman–Ford algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum BellmanFordPayload {
+    /// Wakes the node up.
+    Start,
+    /// The distances known by the sender to every destination, in a round.
+    Vector(usize, Vec<(ActorId, u64)>),
+}
+
+/// The Bellman–Ford algorithm, computing the routes to every destination.
+/// In every round, each node sends the distances it knows to its neighbours,
+/// and relaxes its routes with the distances of the neighbours. A shortest path
+/// has at most `n - 1` edges, so the routes are final after `n - 1` rounds.
+///
+/// A neighbour can be one round ahead, in which case its vector is deferred
+/// until the round of the node catches up. Any node can be woken up with
+/// `BellmanFordPayload::Start`, and the other ones wake up when they receive
+/// their first message.
+pub struct BellmanFord {
+    aid: ActorId,
+    weights: HashMap<ActorId, u64>,
+    rounds: usize,
+    round: usize,
+    received: usize,
+    started: bool,
+    table: RoutingTable,
+    results: Option<UnboundedSender<RoutingTable>>,
+}
+
+impl BellmanFord {
+    /// Creates the handler for the node `aid`, with the weights of
+    /// its edges and the number of nodes taken from the topology.
+    pub fn new(aid: ActorId, topology: &Topology) -> Self {
+        Self {
+            aid,
+            weights: weights(topology, aid),
+            rounds: topology.nodes().len().saturating_sub(1),
+            round: 0,
+            received: 0,
+            started: false,
+            table: RoutingTable::new(aid),
+            results: None,
+        }
+    }
+
+    /// Sends the routing table of the node to the channel, once it is final.
+    pub fn with_results(mut self, results: UnboundedSender<RoutingTable>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Sends the distances of the current round to the neighbours,
+    /// or reports the table after the last round.
+    fn next_round(&mut self, session: Session) -> ContinuationHandler<BellmanFordPayload> {
+        if self.round == self.rounds {
+            log::info!(
+                "ROUTES | on {:?} | {:?} | {:?}",
+                self.aid,
+                session,
+                self.table
+            );
+            if let Some(results) = &self.results {
+                let _ = results.unbounded_send(self.table.clone());
+            }
+            return ContinuationHandler::Done;
+        }
+
+        let vector = self
+            .table
+            .routes
+            .iter()
+            .map(|(dest, route)| (*dest, route.distance))
+            .collect();
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(BellmanFordPayload::Vector(self.round, vector))
+            .with_sender(self.aid)
+            .build();
+
+        ContinuationHandler::SendToAllNodes(msg)
+    }
+}
+
+impl ProtocolHandler for BellmanFord {
+    type Payload = BellmanFordPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let j = msg.sender().as_aid();
+
+        let mut res = ContinuationHandler::Done;
+        if !self.started {
+            self.started = true;
+            res = self.next_round(session);
+        }
+
+        let cont = match msg.payload() {
+            BellmanFordPayload::Start => ContinuationHandler::Done,
+            BellmanFordPayload::Vector(round, _) if *round > self.round => {
+                ContinuationHandler::Defer(msg)
+            }
+            BellmanFordPayload::Vector(_, vector) => {
+                let weight = self.weights[&j];
+                for (dest, distance) in vector {
+                    let route = Route {
+                        distance: distance + weight,
+                        next_hop: j,
+                    };
+                    self.table.relax(*dest, route);
+                }
+
+                self.received += 1;
+                if self.received == self.weights.len() {
+                    self.received = 0;
+                    self.round += 1;
+                    self.next_round(session)
+                } else {
+                    ContinuationHandler::Done
+                }
+            }
+        };
+
+        res.and_then(cont)
+    }
+}
@@ -0,0 +1,184 @@
+use super::{weights, Route, RoutingTable};
+use crate::{
+    graph::Topology,
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashMap;
+
+/// The payload of the Chandy–Misra algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum ChandyMisraPayload {
+    /// Makes the node the root. It has to be sent to the root only.
+    Start,
+    /// The distance of the sender to the root.
+    Length(u64),
+    /// Acknowledges a `Length` message.
+    Ack,
+    /// The computation terminated.
+    Terminated,
+}
+
+/// The Chandy–Misra algorithm, computing the routes to a single root. Every
+/// node which finds a shorter path to the root tells its neighbours, and the
+/// neighbour on that path becomes its next hop. The termination is detected
+/// by the root with the scheme of Dijkstra and Scholten: every `Length` message
+/// is acknowledged, but a node keeps the acknowledgement of its next hop until
+/// all its own messages were acknowledged. The root then floods the
+/// termination, and every node reports its route.
+pub struct ChandyMisra {
+    aid: ActorId,
+    root: ActorId,
+    weights: HashMap<ActorId, u64>,
+    distance: Option<u64>,
+    pred: Option<ActorId>,
+    num: usize,
+    terminated: bool,
+    results: Option<UnboundedSender<RoutingTable>>,
+}
+
+impl ChandyMisra {
+    /// Creates the handler for the node `aid`, computing the routes to the
+    /// root, with the weights of its edges taken from the topology.
+    pub fn new(aid: ActorId, root: ActorId, topology: &Topology) -> Self {
+        Self {
+            aid,
+            root,
+            weights: weights(topology, aid),
+            distance: None,
+            pred: None,
+            num: 0,
+            terminated: false,
+            results: None,
+        }
+    }
+
+    /// Sends the routing table of the node to the channel, once it is final.
+    pub fn with_results(mut self, results: UnboundedSender<RoutingTable>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    fn send(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: ChandyMisraPayload,
+    ) -> ContinuationHandler<ChandyMisraPayload> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Tells the neighbours but one about the new distance of the node.
+    fn propagate(
+        &mut self,
+        session: Session,
+        except: Option<ActorId>,
+    ) -> ContinuationHandler<ChandyMisraPayload> {
+        let distance = self.distance.expect("the node knows its distance");
+        let ns: Vec<_> = self
+            .weights
+            .keys()
+            .copied()
+            .filter(|n| Some(*n) != except)
+            .collect();
+        self.num += ns.len();
+
+        ns.into_iter().fold(ContinuationHandler::Done, |res, n| {
+            res.and_then(self.send(n, session, ChandyMisraPayload::Length(distance)))
+        })
+    }
+
+    /// Acknowledges the message of the next hop, or terminates at the
+    /// root, once all the messages of the node were acknowledged.
+    fn check_done(&mut self, session: Session) -> ContinuationHandler<ChandyMisraPayload> {
+        if self.num != 0 {
+            return ContinuationHandler::Done;
+        }
+
+        match self.pred {
+            Some(pred) => self.send(pred, session, ChandyMisraPayload::Ack),
+            None => self.terminate(session, None),
+        }
+    }
+
+    fn terminate(
+        &mut self,
+        session: Session,
+        sender: Option<ActorId>,
+    ) -> ContinuationHandler<ChandyMisraPayload> {
+        self.terminated = true;
+
+        let mut table = RoutingTable::new(self.aid);
+        if let (Some(distance), Some(next_hop)) = (self.distance, self.pred) {
+            table.routes = [(self.root, Route { distance, next_hop })].into();
+        }
+        log::info!("ROUTES | on {:?} | {:?} | {:?}", self.aid, session, table);
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(table);
+        }
+
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(ChandyMisraPayload::Terminated)
+            .with_sender(self.aid)
+            .build();
+        ContinuationHandler::SendToAllNodesExcept(msg, sender.into_iter().collect())
+    }
+}
+
+impl ProtocolHandler for ChandyMisra {
+    type Payload = ChandyMisraPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let j = msg.sender().as_aid();
+
+        match *msg.payload() {
+            ChandyMisraPayload::Start if self.aid == self.root && self.distance.is_none() => {
+                self.distance = Some(0);
+                self.propagate(session, None)
+                    .and_then(self.check_done(session))
+            }
+            ChandyMisraPayload::Start => ContinuationHandler::Done,
+            ChandyMisraPayload::Length(d)
+                if self.distance.is_none_or(|x| d + self.weights[&j] < x) =>
+            {
+                // The node moves under its new next hop, and releases the
+                // previous one if it was still holding its acknowledgement.
+                let mut res = ContinuationHandler::Done;
+                if let (true, Some(pred)) = (self.num > 0, self.pred) {
+                    res = self.send(pred, session, ChandyMisraPayload::Ack);
+                }
+
+                self.pred = Some(j);
+                self.distance = Some(d + self.weights[&j]);
+                res.and_then(self.propagate(session, Some(j)))
+                    .and_then(self.check_done(session))
+            }
+            ChandyMisraPayload::Length(_) => self.send(j, session, ChandyMisraPayload::Ack),
+            ChandyMisraPayload::Ack => {
+                self.num -= 1;
+                self.check_done(session)
+            }
+            ChandyMisraPayload::Terminated if !self.terminated => self.terminate(session, Some(j)),
+            ChandyMisraPayload::Terminated => ContinuationHandler::Done,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::{weights, Route, RoutingTable};
+use crate::{
+    graph::Topology,
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashMap;
+
+/// The payload of the Merlin–Segall algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum MerlinSegallPayload {
+    /// Makes the node the root. It has to be sent to the root only.
+    Start,
+    /// The distance of the sender to the root, in a round.
+    Distance(usize, u64),
+}
+
+/// The Merlin–Segall algorithm, computing the routes to a single root in
+/// `n - 1` rounds. Every round is a wave over the tree of the next hops, from
+/// the root: a node tells its distance to its neighbours but its parent when
+/// it gets the message of its parent, and to its parent once it got the
+/// messages of all its neighbours. A node which finds a shorter path changes
+/// its next hop, which becomes its parent at the next round. In the first
+/// round, the parent of a node is the first neighbour it hears from.
+pub struct MerlinSegall {
+    aid: ActorId,
+    root: ActorId,
+    weights: HashMap<ActorId, u64>,
+    rounds: usize,
+    round: usize,
+    received: usize,
+    distance: Option<u64>,
+    parent: Option<ActorId>,
+    next_hop: Option<ActorId>,
+    results: Option<UnboundedSender<RoutingTable>>,
+}
+
+impl MerlinSegall {
+    /// Creates the handler for the node `aid`, computing the routes to the root,
+    /// with the weights of its edges and the number of nodes taken from the topology.
+    pub fn new(aid: ActorId, root: ActorId, topology: &Topology) -> Self {
+        Self {
+            aid,
+            root,
+            weights: weights(topology, aid),
+            rounds: topology.nodes().len().saturating_sub(1),
+            round: 0,
+            received: 0,
+            distance: None,
+            parent: None,
+            next_hop: None,
+            results: None,
+        }
+    }
+
+    /// Sends the routing table of the node to the channel, once it is final.
+    pub fn with_results(mut self, results: UnboundedSender<RoutingTable>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    fn build_msg(&self, session: Session) -> Message<MerlinSegallPayload> {
+        let distance = self.distance.expect("the node knows its distance");
+        Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(MerlinSegallPayload::Distance(self.round, distance))
+            .with_sender(self.aid)
+            .build()
+    }
+
+    /// Moves to the next round, and reports the table after the last one.
+    fn next_round(&mut self, session: Session) -> ContinuationHandler<MerlinSegallPayload> {
+        self.received = 0;
+        self.round += 1;
+        self.parent = self.next_hop;
+
+        if self.round < self.rounds {
+            if self.aid == self.root {
+                return ContinuationHandler::SendToAllNodes(self.build_msg(session));
+            }
+            return ContinuationHandler::Done;
+        }
+
+        let mut table = RoutingTable::new(self.aid);
+        if let (Some(distance), Some(next_hop)) = (self.distance, self.next_hop) {
+            table.routes = [(self.root, Route { distance, next_hop })].into();
+        }
+        log::info!("ROUTES | on {:?} | {:?} | {:?}", self.aid, session, table);
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(table);
+        }
+        ContinuationHandler::Done
+    }
+}
+
+impl ProtocolHandler for MerlinSegall {
+    type Payload = MerlinSegallPayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        _ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let j = msg.sender().as_aid();
+
+        match *msg.payload() {
+            MerlinSegallPayload::Start if self.aid == self.root && self.distance.is_none() => {
+                self.distance = Some(0);
+                if self.rounds == 0 {
+                    return self.next_round(session);
+                }
+                ContinuationHandler::SendToAllNodes(self.build_msg(session))
+            }
+            MerlinSegallPayload::Start => ContinuationHandler::Done,
+            MerlinSegallPayload::Distance(round, _) if round > self.round => {
+                ContinuationHandler::Defer(msg)
+            }
+            MerlinSegallPayload::Distance(_, d) => {
+                let distance = d + self.weights[&j];
+                if self.aid != self.root && self.distance.is_none_or(|x| distance < x) {
+                    self.distance = Some(distance);
+                    self.next_hop = Some(j);
+                }
+                if self.parent.is_none() && self.aid != self.root {
+                    self.parent = Some(j);
+                }
+
+                let mut res = ContinuationHandler::Done;
+                if Some(j) == self.parent {
+                    let msg = self.build_msg(session);
+                    res = ContinuationHandler::SendToAllNodesExcept(msg, vec![j]);
+                }
+
+                self.received += 1;
+                if self.received < self.weights.len() {
+                    return res;
+                }
+
+                if let Some(parent) = self.parent {
+                    let msg = self.build_msg(session);
+                    res = res.and_then(ContinuationHandler::SendToNode(parent, msg));
+                }
+                res.and_then(self.next_round(session))
+            }
+        }
+    }
+}
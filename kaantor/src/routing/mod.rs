@@ -0,0 +1,388 @@
+//! Shortest paths and routing tables.
+//!
+//! The weights of the edges are taken from the `Topology` of the graph, which
+//! has to match the edges added with `add_edge`. Every node reports its own
+//! routing table once it is final, and the tables of all the nodes are
+//! assembled into `RoutingTables`, which can be checked against the shortest
+//! paths computed by a centralized Dijkstra.
+//!
+//! A `Router` uses the table of a node to deliver the messages of a handler
+//! to any node of the network, and not only to its neighbours.
+
+mod bellman_ford;
+mod chandy_misra;
+mod merlin_segall;
+mod router;
+
+pub use bellman_ford::*;
+pub use chandy_misra::*;
+pub use merlin_segall::*;
+pub use router::*;
+
+use crate::{graph::Topology, ActorId};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
+    fmt::Display,
+};
+
+/// The route from a node to a destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// The length of the shortest path to the destination.
+    pub distance: u64,
+    /// The neighbour to forward the messages to, or the node
+    /// itself when it is the destination.
+    pub next_hop: ActorId,
+}
+
+/// The routes known by a node, by destination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingTable {
+    /// The node which owns the table.
+    pub aid: ActorId,
+    /// The routes, by destination.
+    pub routes: BTreeMap<ActorId, Route>,
+}
+
+impl RoutingTable {
+    /// Creates a table with the route of the node to itself.
+    pub fn new(aid: ActorId) -> Self {
+        let mut routes = BTreeMap::new();
+        routes.insert(
+            aid,
+            Route {
+                distance: 0,
+                next_hop: aid,
+            },
+        );
+
+        Self { aid, routes }
+    }
+
+    /// Returns the route to the destination, if any.
+    pub fn route(&self, dest: ActorId) -> Option<&Route> {
+        self.routes.get(&dest)
+    }
+
+    /// Replaces the route to the destination if the new one is shorter,
+    /// and returns `true` if it did.
+    pub(crate) fn relax(&mut self, dest: ActorId, route: Route) -> bool {
+        match self.routes.get(&dest) {
+            Some(r) if r.distance <= route.distance => false,
+            _ => {
+                self.routes.insert(dest, route);
+                true
+            }
+        }
+    }
+}
+
+/// A route which is not a shortest path, as found by `RoutingTables::verify`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoutingError {
+    /// A node of the topology has no routing table.
+    MissingTable(ActorId),
+    /// A node has no route to a reachable destination.
+    MissingRoute {
+        /// The node.
+        aid: ActorId,
+        /// The destination.
+        dest: ActorId,
+    },
+    /// A node has a route with the wrong distance.
+    Distance {
+        /// The node.
+        aid: ActorId,
+        /// The destination.
+        dest: ActorId,
+        /// The length of the shortest path.
+        expected: u64,
+        /// The distance of the route.
+        actual: u64,
+    },
+    /// A node forwards to a neighbour which is not on a shortest path.
+    NextHop {
+        /// The node.
+        aid: ActorId,
+        /// The destination.
+        dest: ActorId,
+        /// The next hop of the route.
+        next_hop: ActorId,
+    },
+}
+
+impl Display for RoutingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingTable(aid) => write!(f, "{aid:?} has no routing table"),
+            Self::MissingRoute { aid, dest } => write!(f, "{aid:?} has no route to {dest:?}"),
+            Self::Distance {
+                aid,
+                dest,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{aid:?} is at {actual} from {dest:?} instead of {expected}"
+            ),
+            Self::NextHop {
+                aid,
+                dest,
+                next_hop,
+            } => write!(
+                f,
+                "{aid:?} goes to {dest:?} through {next_hop:?}, off the shortest paths"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+/// The routing tables of all the nodes of a graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoutingTables {
+    tables: BTreeMap<ActorId, RoutingTable>,
+}
+
+impl FromIterator<RoutingTable> for RoutingTables {
+    fn from_iter<T: IntoIterator<Item = RoutingTable>>(iter: T) -> Self {
+        Self {
+            tables: iter.into_iter().map(|t| (t.aid, t)).collect(),
+        }
+    }
+}
+
+impl RoutingTables {
+    /// Assembles the tables reported by the nodes.
+    pub fn assemble(tables: impl IntoIterator<Item = RoutingTable>) -> Self {
+        tables.into_iter().collect()
+    }
+
+    /// Computes the routing tables of all the nodes of the topology, to every
+    /// destination, with Dijkstra's algorithm.
+    pub fn dijkstra(topology: &Topology) -> Self {
+        topology
+            .nodes()
+            .into_iter()
+            .map(|aid| dijkstra(topology, aid))
+            .collect()
+    }
+
+    /// Returns the table of a node.
+    pub fn table(&self, aid: ActorId) -> Option<&RoutingTable> {
+        self.tables.get(&aid)
+    }
+
+    /// Returns the tables, by node.
+    pub fn tables(&self) -> impl Iterator<Item = &RoutingTable> {
+        self.tables.values()
+    }
+
+    /// Returns the path from a node to a destination, following the next
+    /// hops, or `None` if a node on the way has no route to the destination.
+    pub fn path(&self, from: ActorId, dest: ActorId) -> Option<Vec<ActorId>> {
+        let mut path = vec![from];
+        let mut aid = from;
+        while aid != dest {
+            aid = self.tables.get(&aid)?.route(dest)?.next_hop;
+            if path.contains(&aid) {
+                return None;
+            }
+            path.push(aid);
+        }
+        Some(path)
+    }
+
+    /// Checks that every node of the topology has a table, with a route to every
+    /// destination known by any node, and that the routes are shortest paths.
+    pub fn verify(&self, topology: &Topology) -> Result<(), RoutingError> {
+        let expected = Self::dijkstra(topology);
+        let dests: BTreeSet<_> = self
+            .tables()
+            .flat_map(|t| t.routes.keys().copied())
+            .collect();
+
+        for aid in topology.nodes() {
+            let table = self.table(aid).ok_or(RoutingError::MissingTable(aid))?;
+            let shortest = expected.table(aid).expect("dijkstra covers all the nodes");
+
+            for dest in dests.iter().copied() {
+                let Some(best) = shortest.route(dest) else {
+                    continue;
+                };
+                let route = table
+                    .route(dest)
+                    .ok_or(RoutingError::MissingRoute { aid, dest })?;
+
+                if route.distance != best.distance {
+                    return Err(RoutingError::Distance {
+                        aid,
+                        dest,
+                        expected: best.distance,
+                        actual: route.distance,
+                    });
+                }
+
+                let next_hop = route.next_hop;
+                let on_path = if aid == dest {
+                    next_hop == aid
+                } else {
+                    let weight = topology.weight(aid, next_hop);
+                    let rest = expected.table(next_hop).and_then(|t| t.route(dest));
+                    matches!((weight, rest), (Some(w), Some(r)) if w + r.distance == best.distance)
+                };
+                if !on_path {
+                    return Err(RoutingError::NextHop {
+                        aid,
+                        dest,
+                        next_hop,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the routing table of a node, to every destination.
+fn dijkstra(topology: &Topology, aid: ActorId) -> RoutingTable {
+    let mut table = RoutingTable::new(aid);
+    let mut heap = BinaryHeap::new();
+    let mut done = HashSet::new();
+    heap.push(Reverse((0, aid, aid)));
+
+    while let Some(Reverse((distance, node, next_hop))) = heap.pop() {
+        if !done.insert(node) {
+            continue;
+        }
+        table.relax(node, Route { distance, next_hop });
+
+        for n in topology.neighbours(node) {
+            let weight = topology.weight(node, n).expect("the edge exists");
+            // The next hop of the neighbours of the node itself is the neighbour.
+            let hop = if node == aid { n } else { next_hop };
+            heap.push(Reverse((distance + weight, n, hop)));
+        }
+    }
+
+    table
+}
+
+/// Returns the weights of the edges of a node, by neighbour.
+fn weights(topology: &Topology, aid: ActorId) -> HashMap<ActorId, u64> {
+    topology
+        .neighbours(aid)
+        .map(|n| (n, topology.weight(aid, n).expect("the edge exists")))
+        .collect()
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, SessionAllocator},
+        NodeActor, ProtocolHandler,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::time::Duration;
+
+    const EDGES: [(usize, usize, u64); 11] = [
+        (1, 2, 4),
+        (1, 3, 2),
+        (2, 3, 5),
+        (2, 4, 10),
+        (3, 5, 3),
+        (4, 5, 4),
+        (4, 6, 11),
+        (5, 6, 7),
+        (6, 7, 1),
+        (5, 7, 7),
+        (3, 7, 9),
+    ];
+
+    async fn compute<H, P>(
+        build: impl Fn(ActorId, &Topology, UnboundedSender<RoutingTable>) -> H,
+        start: P,
+    ) -> RoutingTables
+    where
+        H: ProtocolHandler<Payload = P> + Unpin + 'static,
+        P: Clone + Send + std::fmt::Debug + 'static,
+    {
+        let topology = EDGES.iter().fold(Topology::new(), |t, (a, b, w)| {
+            t.with_weighted_edge((*a).into(), (*b).into(), *w)
+        });
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=7)
+            .map(|i| NodeActor::build(build(i.into(), &topology, tx.clone())))
+            .collect();
+        for (a, b, _) in EDGES {
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(start)
+            .with_sender(1.into())
+            .build();
+        nodes[0].do_send(msg);
+
+        let mut tables = vec![];
+        for _ in 0..7 {
+            let table = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            tables.push(table);
+        }
+
+        let tables = RoutingTables::assemble(tables);
+        assert_eq!(Ok(()), tables.verify(&topology));
+        tables
+    }
+
+    #[actix_rt::test]
+    async fn shortest_paths() {
+        let topology = EDGES.iter().fold(Topology::new(), |t, (a, b, w)| {
+            t.with_weighted_edge((*a).into(), (*b).into(), *w)
+        });
+        let expected = RoutingTables::dijkstra(&topology);
+        let (a1, a7) = (ActorId::from(1), ActorId::from(7));
+        assert_eq!(11, expected.table(a7).unwrap().route(a1).unwrap().distance);
+        assert_eq!(Some(vec![a1, 3.into(), a7]), expected.path(a1, a7));
+
+        let bf = |aid, t: &Topology, tx| BellmanFord::new(aid, t).with_results(tx);
+        let tables = compute(bf, BellmanFordPayload::Start).await;
+        for aid in topology.nodes() {
+            assert_eq!(7, tables.table(aid).unwrap().routes.len());
+        }
+
+        let cm = |aid, t: &Topology, tx| ChandyMisra::new(aid, a1, t).with_results(tx);
+        let tables = compute(cm, ChandyMisraPayload::Start).await;
+        assert_eq!(Some(vec![a7, 3.into(), a1]), tables.path(a7, a1));
+
+        let ms = |aid, t: &Topology, tx| MerlinSegall::new(aid, a1, t).with_results(tx);
+        let mut tables = compute(ms, MerlinSegallPayload::Start).await;
+        assert_eq!(Some(vec![a7, 3.into(), a1]), tables.path(a7, a1));
+
+        // A route which is too long is caught by the verification.
+        let mut table = tables.table(a7).unwrap().clone();
+        table.routes.get_mut(&a1).unwrap().distance = 12;
+        tables.tables.insert(a7, table);
+        assert_eq!(
+            Err(RoutingError::Distance {
+                aid: a7,
+                dest: a1,
+                expected: 11,
+                actual: 12
+            }),
+            tables.verify(&topology)
+        );
+    }
+}
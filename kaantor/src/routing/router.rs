@@ -0,0 +1,391 @@
+use super::{RoutingTable, RoutingTables};
+use crate::{
+    graph::Topology,
+    protocol::{FromId, Message, SenderId, ToId},
+    ActorId, ContinuationHandler, NeighbourError, PayloadDebug, ProtocolHandler,
+};
+use std::{fmt::Display, time::Duration};
+
+/// The payload of a `Router`.
+#[derive(Clone, PayloadDebug)]
+pub enum RouterPayload<P> {
+    /// A payload for the neighbours only, e.g. a broadcast.
+    Local(P),
+    /// A payload forwarded hop by hop, from the origin of the message
+    /// to its destination.
+    Routed(P),
+    /// A routed payload which could not reach the given destination,
+    /// sent back to its origin.
+    Unreachable(ActorId, P),
+}
+
+type RMsg<P> = Message<RouterPayload<P>>;
+type RCont<P> = ContinuationHandler<RouterPayload<P>>;
+
+/// The reasons a routed message was not delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteError {
+    /// A node on the way has no route to the destination.
+    Unreachable {
+        /// The node without a route.
+        at: ActorId,
+        /// The destination.
+        dest: ActorId,
+    },
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreachable { at, dest } => write!(f, "{at:?} has no route to {dest:?}"),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// A handler whose messages are routed by a `Router`.
+pub trait RoutedHandler: ProtocolHandler {
+    /// Handles a message sent by the node which could not be delivered.
+    fn on_undeliverable(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        err: RouteError,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload>;
+}
+
+/// Routes the messages of a handler, so it can send to any node of the network
+/// with `ContinuationHandler::SendToNode`. The messages are forwarded hop by hop,
+/// along the routing table of every node on the way. A message which reaches
+/// a node without a route to its destination is sent back to its origin, and
+/// handed to `RoutedHandler::on_undeliverable`.
+///
+/// The handler gets the routed messages with their origin in `from`, while
+/// the sender is the last hop. The messages sent to all the neighbours are not
+/// routed.
+pub struct Router<H> {
+    inner: H,
+    table: RoutingTable,
+}
+
+impl<H> Router<H>
+where
+    H: RoutedHandler,
+{
+    /// Creates a router with the given routing table of the node.
+    pub fn new(inner: H, table: RoutingTable) -> Self {
+        Self { inner, table }
+    }
+
+    /// Creates a router with the routes of the node to every node
+    /// of the topology, computed with Dijkstra's algorithm.
+    pub fn with_topology(inner: H, topology: &Topology) -> Self {
+        let aid = inner.aid();
+        let table = RoutingTables::dijkstra(topology)
+            .table(aid)
+            .cloned()
+            .unwrap_or_else(|| RoutingTable::new(aid));
+
+        Self::new(inner, table)
+    }
+
+    /// Gets the routed handler.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Gets the routing table of the node.
+    pub fn table(&self) -> &RoutingTable {
+        &self.table
+    }
+
+    /// Sends a routed message to the next hop towards its destination, or
+    /// returns it if the node has no route to it.
+    fn forward(
+        &self,
+        dest: ActorId,
+        mut msg: RMsg<H::Payload>,
+    ) -> Result<RCont<H::Payload>, RMsg<H::Payload>> {
+        let aid = self.inner.aid();
+        let Some(route) = self.table.route(dest) else {
+            return Err(msg);
+        };
+
+        msg.sender = SenderId::from(aid);
+        if route.next_hop == aid {
+            // The node sends to itself.
+            return Ok(ContinuationHandler::Schedule(Duration::ZERO, msg));
+        }
+        Ok(ContinuationHandler::SendToNode(route.next_hop, msg))
+    }
+
+    /// Routes the messages sent by the handler.
+    fn route(
+        &mut self,
+        ns: &[ActorId],
+        res: ContinuationHandler<H::Payload>,
+    ) -> ContinuationHandler<RouterPayload<H::Payload>> {
+        match res {
+            ContinuationHandler::SendToNode(dest, mut msg) => {
+                msg.to = ToId::Actor(dest);
+                match self.forward(dest, msg.map_payload(RouterPayload::Routed)) {
+                    Ok(res) => res,
+                    Err(msg) => {
+                        let msg = msg.map_payload(|p| match p {
+                            RouterPayload::Routed(p) => p,
+                            _ => unreachable!("the message was just routed"),
+                        });
+                        let err = RouteError::Unreachable {
+                            at: self.inner.aid(),
+                            dest,
+                        };
+                        let res = self.inner.on_undeliverable(ns.iter().copied(), err, msg);
+                        self.route(ns, res)
+                    }
+                }
+            }
+            ContinuationHandler::Sequence(conts) => ContinuationHandler::Sequence(
+                conts.into_iter().map(|res| self.route(ns, res)).collect(),
+            ),
+            res => res.map(RouterPayload::Local),
+        }
+    }
+}
+
+/// Splits a message into its header and its payload.
+fn split<P>(msg: Message<P>) -> (Message<()>, P) {
+    let mut payload = None;
+    let header = msg.map_payload(|p| payload = Some(p));
+    (header, payload.expect("the message has a payload"))
+}
+
+/// Builds a message from a header and a payload.
+fn join<P>(header: &Message<()>, payload: P) -> Message<P> {
+    header.clone().map_payload(|_| payload)
+}
+
+impl<H> ProtocolHandler for Router<H>
+where
+    H: RoutedHandler,
+{
+    type Payload = RouterPayload<H::Payload>;
+
+    fn aid(&self) -> ActorId {
+        self.inner.aid()
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let aid = self.inner.aid();
+        let dest = match msg.to() {
+            ToId::Actor(dest) => Some(*dest),
+            _ => None,
+        };
+        let origin = match msg.from() {
+            FromId::Actor(origin) => Some(*origin),
+            FromId::Api => None,
+        };
+
+        let (header, payload) = split(msg);
+        let res = match payload {
+            RouterPayload::Local(p) => self.inner.receive(ns.iter().copied(), join(&header, p)),
+            RouterPayload::Routed(p) if dest.is_none_or(|d| d == aid) => {
+                self.inner.receive(ns.iter().copied(), join(&header, p))
+            }
+            RouterPayload::Routed(p) => {
+                let dest = dest.expect("a routed message has a destination");
+                match self.forward(dest, join(&header, RouterPayload::Routed(p))) {
+                    Ok(res) => return res,
+                    Err(msg) => {
+                        let RouterPayload::Routed(p) = msg.payload else {
+                            unreachable!("the message is routed")
+                        };
+                        let Some(origin) = origin else {
+                            log::warn!("UNREACHABLE | on {aid:?} | {dest:?}");
+                            return ContinuationHandler::Done;
+                        };
+
+                        // Sent back to the origin, which has to be reachable
+                        // as the message came from there.
+                        let mut back = join(&header, RouterPayload::Unreachable(dest, p));
+                        back.mid = None;
+                        back.from = FromId::Actor(aid);
+                        back.to = ToId::Actor(origin);
+                        return self.forward(origin, back).unwrap_or_else(|msg| {
+                            log::warn!("UNREACHABLE | on {aid:?} | {:?}", msg.to);
+                            ContinuationHandler::Done
+                        });
+                    }
+                }
+            }
+            RouterPayload::Unreachable(unreachable, p) if dest.is_none_or(|d| d == aid) => {
+                // The origin of the message is the node without a route.
+                let err = RouteError::Unreachable {
+                    at: origin.unwrap_or(aid),
+                    dest: unreachable,
+                };
+                let mut msg = join(&header, p);
+                msg.mid = None;
+                msg.from = FromId::Actor(aid);
+                msg.to = ToId::Actor(unreachable);
+                msg.sender = SenderId::from(aid);
+                self.inner.on_undeliverable(ns.iter().copied(), err, msg)
+            }
+            RouterPayload::Unreachable(unreachable, p) => {
+                let dest = dest.expect("a routed message has a destination");
+                let msg = join(&header, RouterPayload::Unreachable(unreachable, p));
+                return self.forward(dest, msg).unwrap_or_else(|msg| {
+                    log::warn!("UNREACHABLE | on {aid:?} | {:?}", msg.to);
+                    ContinuationHandler::Done
+                });
+            }
+        };
+
+        self.route(&ns, res)
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let res = self.inner.on_neighbour_error(ns.iter().copied(), err);
+
+        self.route(&ns, res)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        protocol::{Builder, SessionAllocator},
+        routing::Route,
+        NodeActor,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+
+    /// Sends a number to the given nodes when asked through the api.
+    struct Send {
+        aid: ActorId,
+        results: UnboundedSender<(ActorId, Result<ActorId, RouteError>)>,
+    }
+
+    impl ProtocolHandler for Send {
+        type Payload = Vec<usize>;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            msg: Message<Vec<usize>>,
+        ) -> ContinuationHandler<Vec<usize>> {
+            if let FromId::Actor(origin) = msg.from() {
+                let _ = self.results.unbounded_send((self.aid, Ok(*origin)));
+                return ContinuationHandler::Done;
+            }
+
+            msg.payload()
+                .iter()
+                .fold(ContinuationHandler::Done, |res, i| {
+                    let to = ActorId::from(*i);
+                    let msg = Builder::with_from_actor(self.aid)
+                        .with_to_actor(to)
+                        .with_session(*msg.session())
+                        .with_payload(vec![])
+                        .with_sender(self.aid)
+                        .build();
+                    res.and_then(ContinuationHandler::SendToNode(to, msg))
+                })
+        }
+    }
+
+    impl RoutedHandler for Send {
+        fn on_undeliverable(
+            &mut self,
+            _ns: impl Iterator<Item = ActorId>,
+            err: RouteError,
+            _msg: Message<Vec<usize>>,
+        ) -> ContinuationHandler<Vec<usize>> {
+            let _ = self.results.unbounded_send((self.aid, Err(err)));
+            ContinuationHandler::Done
+        }
+    }
+
+    #[actix_rt::test]
+    async fn router() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        // A line 1-2-3-4, where node 1 believes node 2 has a route to 5.
+        let topology = Topology::new()
+            .with_edge(1.into(), 2.into())
+            .with_edge(2.into(), 3.into())
+            .with_edge(3.into(), 4.into());
+        let mut nodes: Vec<_> = (1..=4)
+            .map(|i| {
+                let send = Send {
+                    aid: i.into(),
+                    results: tx.clone(),
+                };
+                let mut router = Router::with_topology(send, &topology);
+                if i == 1 {
+                    let route = Route {
+                        distance: 4,
+                        next_hop: 2.into(),
+                    };
+                    router.table.routes.insert(5.into(), route);
+                }
+                NodeActor::build(router)
+            })
+            .collect();
+        for b in 1..4 {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[b - 1], &mut right[0]).await;
+        }
+
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(RouterPayload::Local(vec![4, 5, 6]))
+            .with_sender(1.into())
+            .build();
+        nodes[0].do_send(msg);
+
+        let mut results = vec![];
+        for _ in 0..3 {
+            let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            results.push(res);
+        }
+
+        let (a1, a2) = (ActorId::from(1), ActorId::from(2));
+        assert!(results.contains(&(4.into(), Ok(a1))));
+        let err = |at, dest: usize| {
+            (
+                a1,
+                Err(RouteError::Unreachable {
+                    at,
+                    dest: dest.into(),
+                }),
+            )
+        };
+        assert!(results.contains(&err(a2, 5)));
+        assert!(results.contains(&err(a1, 6)));
+    }
+}
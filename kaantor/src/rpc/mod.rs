@@ -0,0 +1,337 @@
+//! Request/response calls between neighbours.
+//!
+//! The messages sent by a node are fire-and-forget, so the calls are built on
+//! top of them. A handler keeps a `Calls` tracker, which tags every request with
+//! a `CallId` and schedules a timeout for it. The response, or the timeout if the
+//! neighbour did not answer in time, comes back through `receive`, and `accept`
+//! matches it against the calls which are still pending.
+
+use crate::{
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler,
+};
+use std::{collections::HashMap, fmt::Debug, fmt::Display, marker::PhantomData, time::Duration};
+
+/// Represents the correlation identifier of a call. It is made of the
+/// identifier of the caller and of a counter local to the caller.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallId {
+    aid: ActorId,
+    seq: usize,
+}
+
+impl Debug for CallId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "C{}.{}", self.aid.inner(), self.seq)
+    }
+}
+
+impl CallId {
+    /// Gets the identifier of the caller.
+    pub fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    /// Gets the value of the counter at the moment the call was made.
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+}
+
+/// The payload of the messages exchanged by the nodes which make calls.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RpcPayload<P> {
+    /// A one-way message.
+    Cast(P),
+    /// A request which expects a response.
+    Request(CallId, P),
+    /// The response to a request.
+    Response(CallId, P),
+    /// The timer of a call expired. Sent by a node to itself.
+    Timeout(CallId),
+}
+
+/// The errors with which a call can complete.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcError {
+    /// The neighbour did not answer in time.
+    Timeout(ActorId),
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout(aid) => write!(f, "call to {aid:?} timed out"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// A received message, as classified by `Calls::accept`.
+#[derive(Debug)]
+pub enum Incoming<P> {
+    /// A one-way message.
+    Cast(Message<P>),
+    /// A request, which should be answered with `Calls::respond`.
+    Request(CallId, Message<P>),
+    /// The outcome of a call made by the current node.
+    Response(CallId, Result<Message<P>, RpcError>),
+    /// A response or a timeout for a call which already completed,
+    /// or a response sent by another node than the callee.
+    Stale(CallId),
+}
+
+/// Keeps track of the calls made by a node which did not complete yet.
+pub struct Calls<P> {
+    aid: ActorId,
+    next: usize,
+    pending: HashMap<CallId, ActorId>,
+    phantom: PhantomData<P>,
+}
+
+impl<P> Calls<P> {
+    /// Creates a new tracker for the calls made by the actor `aid`.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            next: 0,
+            pending: Default::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of calls which did not complete yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if the call did not complete yet.
+    pub fn is_pending(&self, id: &CallId) -> bool {
+        self.pending.contains_key(id)
+    }
+
+    fn build(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: RpcPayload<P>,
+    ) -> Message<RpcPayload<P>> {
+        Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build()
+    }
+
+    /// Sends a request to the neighbour `to`. The call completes with the
+    /// response of the neighbour, or with a timeout once the delay elapsed.
+    pub fn call(
+        &mut self,
+        to: ActorId,
+        session: Session,
+        payload: P,
+        timeout: Duration,
+    ) -> (CallId, ContinuationHandler<RpcPayload<P>>) {
+        let id = CallId {
+            aid: self.aid,
+            seq: self.next,
+        };
+        self.next += 1;
+        self.pending.insert(id, to);
+
+        let req = self.build(to, session, RpcPayload::Request(id, payload));
+        let tmo = self.build(self.aid, session, RpcPayload::Timeout(id));
+        let res = ContinuationHandler::SendToNode(to, req)
+            .and_then(ContinuationHandler::Schedule(timeout, tmo));
+
+        (id, res)
+    }
+
+    /// Sends a one-way message to the neighbour `to`.
+    pub fn cast(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: P,
+    ) -> ContinuationHandler<RpcPayload<P>> {
+        let msg = self.build(to, session, RpcPayload::Cast(payload));
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Answers a request received from a neighbour.
+    pub fn respond(
+        &self,
+        id: CallId,
+        req: &Message<P>,
+        payload: P,
+    ) -> ContinuationHandler<RpcPayload<P>> {
+        let to = req.sender().as_aid();
+        let msg = self.build(to, *req.session(), RpcPayload::Response(id, payload));
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Classifies a received message, completing the matching call
+    /// when the message is a response or a timeout.
+    pub fn accept(&mut self, msg: Message<RpcPayload<P>>) -> Incoming<P> {
+        let Message {
+            mid,
+            from,
+            to,
+            session,
+            sender,
+            payload,
+        } = msg;
+        let with = |payload| Message {
+            mid,
+            from,
+            to,
+            session,
+            sender,
+            payload,
+        };
+
+        match payload {
+            RpcPayload::Cast(p) => Incoming::Cast(with(p)),
+            RpcPayload::Request(id, p) => Incoming::Request(id, with(p)),
+            RpcPayload::Response(id, p) => match self.pending.get(&id) {
+                Some(callee) if *callee == sender.as_aid() => {
+                    self.pending.remove(&id);
+                    Incoming::Response(id, Ok(with(p)))
+                }
+                _ => Incoming::Stale(id),
+            },
+            RpcPayload::Timeout(id) => match self.pending.remove(&id) {
+                Some(aid) => Incoming::Response(id, Err(RpcError::Timeout(aid))),
+                None => Incoming::Stale(id),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor, ProtocolHandler};
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+
+    #[derive(Debug, Clone)]
+    enum Payload {
+        Query,
+        Answer(usize),
+    }
+
+    /// Queries the neighbours for their identifier. Node 3 never answers.
+    struct Query {
+        aid: ActorId,
+        calls: Calls<Payload>,
+        results: UnboundedSender<(ActorId, Result<usize, RpcError>)>,
+        targets: HashMap<CallId, ActorId>,
+    }
+
+    impl ProtocolHandler for Query {
+        type Payload = RpcPayload<Payload>;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            msg: Message<Self::Payload>,
+        ) -> ContinuationHandler<Self::Payload> {
+            match self.calls.accept(msg) {
+                Incoming::Cast(msg) => ns.fold(ContinuationHandler::Done, |res, n| {
+                    let timeout = Duration::from_millis(100);
+                    let (id, call) = self.calls.call(n, *msg.session(), Payload::Query, timeout);
+                    self.targets.insert(id, n);
+                    res.and_then(call)
+                }),
+                Incoming::Request(_, _) if self.aid == ActorId::from(3) => {
+                    ContinuationHandler::Done
+                }
+                Incoming::Request(id, msg) => {
+                    let answer = Payload::Answer(self.aid.inner() * 10);
+                    self.calls.respond(id, &msg, answer)
+                }
+                Incoming::Response(id, res) => {
+                    let res = res.map(|msg| match msg.payload() {
+                        Payload::Answer(v) => *v,
+                        Payload::Query => unreachable!(),
+                    });
+                    let _ = self.results.unbounded_send((self.targets[&id], res));
+                    ContinuationHandler::Done
+                }
+                Incoming::Stale(_) => ContinuationHandler::Done,
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn call_and_timeout() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=3)
+            .map(|i| {
+                NodeActor::build(Query {
+                    aid: i.into(),
+                    calls: Calls::new(i.into()),
+                    results: tx.clone(),
+                    targets: Default::default(),
+                })
+            })
+            .collect();
+
+        for (a, b) in [(0, 1), (0, 2)] {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[a], &mut right[0]).await;
+        }
+
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(RpcPayload::Cast(Payload::Query))
+            .with_sender(1.into())
+            .build();
+        nodes[0].do_send(msg);
+
+        let mut results = vec![];
+        for _ in 0..2 {
+            let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            results.push(res);
+        }
+        assert!(results.contains(&(2.into(), Ok(20))));
+        assert!(results.contains(&(3.into(), Err(RpcError::Timeout(3.into())))));
+    }
+
+    #[test]
+    fn response_from_callee() {
+        let mut calls = Calls::new(1.into());
+        let session = SessionAllocator::for_api().next_session();
+        let (id, _) = calls.call(2.into(), session, Payload::Query, Duration::from_secs(1));
+        let response = |sender: usize| {
+            Builder::with_from_actor(sender.into())
+                .with_to_actor(1.into())
+                .with_session(session)
+                .with_payload(RpcPayload::Response(id, Payload::Answer(sender)))
+                .with_sender(sender.into())
+                .build()
+        };
+
+        // Only the callee can complete the call.
+        assert!(matches!(calls.accept(response(3)), Incoming::Stale(_)));
+        assert!(calls.is_pending(&id));
+        assert!(matches!(
+            calls.accept(response(2)),
+            Incoming::Response(_, Ok(_))
+        ));
+        assert_eq!(0, calls.pending());
+    }
+}
@@ -0,0 +1,418 @@
+//! The Chandy–Lamport snapshot algorithm, layered under any protocol handler
+//! whose state can be cloned.
+//!
+//! The initiator records its state and sends a marker on every channel. A node
+//! records its state when it receives its first marker, then sends a marker on
+//! every channel. The messages a node receives on a channel between its record
+//! and the marker of the channel were in flight. This requires FIFO channels,
+//! which all the proxies and transports are.
+//!
+//! The first marker of a node comes from its parent, and the marker the node
+//! sends back to its parent says so. Once a node received a marker on every
+//! channel, it knows its children, and it sends its part of the snapshot and
+//! the parts of its children to its parent, up to the initiator.
+//!
+//! A message the handler defers is recorded when the handler accepts it. A
+//! message deferred before the node records its state and accepted after the
+//! marker of its channel is neither in the state nor in the channel, so the
+//! snapshot is consistent only if the handler does not defer messages across
+//! a snapshot.
+
+use super::{GlobalSnapshot, LocalSnapshot, SnapshotId};
+use crate::{
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, NeighbourError, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::info;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+};
+
+/// The payload of a `ChandyLamport` handler, on top of the payload `P`
+/// of a handler whose state is `S`.
+#[derive(Clone, PayloadDebug)]
+pub enum ChandyLamportPayload<S, P> {
+    /// A payload of the handler.
+    App(P),
+    /// Sent by the API to initiate a snapshot.
+    Take,
+    /// The marker of a snapshot, which tells if the receiver is the parent.
+    Marker(SnapshotId, bool),
+    /// The parts of the snapshot recorded by a subtree.
+    Report(SnapshotId, Vec<LocalSnapshot<S, P>>),
+}
+
+type ClPayload<H> = ChandyLamportPayload<H, <H as ProtocolHandler>::Payload>;
+type ClCont<H> = ContinuationHandler<ClPayload<H>>;
+
+/// The snapshot on a node, until its part and the parts
+/// of its children are sent to its parent.
+struct Recording<H: ProtocolHandler> {
+    session: Session,
+    state: H,
+    parent: Option<ActorId>,
+    pending: BTreeSet<ActorId>,
+    channels: BTreeMap<ActorId, Vec<H::Payload>>,
+    children: BTreeSet<ActorId>,
+    reported: BTreeSet<ActorId>,
+    locals: Vec<LocalSnapshot<H, H::Payload>>,
+}
+
+/// Takes snapshots of the handler `H` and of the messages between the handlers.
+/// Several snapshots can be taken at the same time.
+pub struct ChandyLamport<H: ProtocolHandler> {
+    inner: H,
+    seq: usize,
+    recordings: HashMap<SnapshotId, Recording<H>>,
+    results: Option<UnboundedSender<GlobalSnapshot<H, H::Payload>>>,
+}
+
+impl<H> ChandyLamport<H>
+where
+    H: ProtocolHandler + Clone + Debug + Send,
+    H::Payload: Clone + Debug,
+{
+    /// Creates a new handler taking snapshots of `inner`.
+    pub fn new(inner: H) -> Self {
+        Self {
+            inner,
+            seq: 0,
+            recordings: Default::default(),
+            results: None,
+        }
+    }
+
+    /// Sends the snapshots initiated by this node to the channel.
+    pub fn with_results(mut self, results: UnboundedSender<GlobalSnapshot<H, H::Payload>>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Gets the handler the snapshots are taken of.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    fn send(&self, to: ActorId, session: Session, payload: ClPayload<H>) -> ClCont<H> {
+        let aid = self.inner.aid();
+        let msg = Builder::with_from_actor(aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(aid)
+            .build();
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Records the state of the node and sends the markers.
+    fn record(
+        &mut self,
+        id: SnapshotId,
+        parent: Option<ActorId>,
+        session: Session,
+        ns: &[ActorId],
+    ) -> ClCont<H> {
+        info!("SNAP | on {:?} | {:?}", self.inner.aid(), id);
+        let recording = Recording {
+            session,
+            state: self.inner.clone(),
+            parent,
+            pending: ns.iter().copied().filter(|n| Some(*n) != parent).collect(),
+            channels: ns.iter().map(|n| (*n, vec![])).collect(),
+            children: Default::default(),
+            reported: Default::default(),
+            locals: vec![],
+        };
+        self.recordings.insert(id, recording);
+
+        let markers = ns
+            .iter()
+            .map(|n| {
+                self.send(
+                    *n,
+                    session,
+                    ChandyLamportPayload::Marker(id, Some(*n) == parent),
+                )
+            })
+            .collect();
+        ContinuationHandler::Sequence(markers)
+    }
+
+    /// Sends the parts of the snapshot to the parent, once the markers
+    /// and the parts of the children are received.
+    fn complete(&mut self, id: SnapshotId) -> ClCont<H> {
+        let Some(recording) = self.recordings.get(&id) else {
+            return ContinuationHandler::Done;
+        };
+        if !recording.pending.is_empty() || recording.reported != recording.children {
+            return ContinuationHandler::Done;
+        }
+
+        let mut recording = self
+            .recordings
+            .remove(&id)
+            .expect("the snapshot is recorded");
+        recording.locals.push(LocalSnapshot {
+            aid: self.inner.aid(),
+            state: recording.state,
+            channels: recording.channels,
+        });
+
+        match recording.parent {
+            Some(parent) => self.send(
+                parent,
+                recording.session,
+                ChandyLamportPayload::Report(id, recording.locals),
+            ),
+            None => {
+                info!("SNAP | on {:?} | {:?} assembled", self.inner.aid(), id);
+                let snapshot = GlobalSnapshot::assemble(id, recording.locals);
+                if let Some(results) = &self.results {
+                    let _ = results.unbounded_send(snapshot);
+                }
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+impl<H> ProtocolHandler for ChandyLamport<H>
+where
+    H: ProtocolHandler + Clone + Debug + Send,
+    H::Payload: Clone + Debug,
+{
+    type Payload = ClPayload<H>;
+
+    fn aid(&self) -> ActorId {
+        self.inner.aid()
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let from = msg.sender().as_aid();
+        let session = *msg.session();
+
+        match msg.payload() {
+            ChandyLamportPayload::App(p) => {
+                let p = p.clone();
+                let msg = msg.map_payload(|p| match p {
+                    ChandyLamportPayload::App(p) => p,
+                    _ => unreachable!(),
+                });
+                let res = self.inner.receive(ns.iter().copied(), msg);
+
+                // A deferred message is recorded once the handler accepts it.
+                if !res.is_deferred() {
+                    for recording in self.recordings.values_mut() {
+                        if recording.pending.contains(&from) {
+                            recording.channels.entry(from).or_default().push(p.clone());
+                        }
+                    }
+                }
+                res.map(ChandyLamportPayload::App)
+            }
+            ChandyLamportPayload::Take => {
+                let id = SnapshotId {
+                    initiator: self.inner.aid(),
+                    seq: self.seq,
+                };
+                self.seq += 1;
+
+                let markers = self.record(id, None, session, &ns);
+                markers.and_then(self.complete(id))
+            }
+            ChandyLamportPayload::Marker(id, child) => {
+                let (id, child) = (*id, *child);
+                let markers = if self.recordings.contains_key(&id) {
+                    ContinuationHandler::Done
+                } else {
+                    self.record(id, Some(from), session, &ns)
+                };
+
+                let recording = self
+                    .recordings
+                    .get_mut(&id)
+                    .expect("the snapshot is recorded");
+                recording.pending.remove(&from);
+                if child {
+                    recording.children.insert(from);
+                }
+                markers.and_then(self.complete(id))
+            }
+            ChandyLamportPayload::Report(id, _) => {
+                let id = *id;
+                let ChandyLamportPayload::Report(_, locals) = msg.payload else {
+                    unreachable!()
+                };
+
+                let recording = self
+                    .recordings
+                    .get_mut(&id)
+                    .expect("the snapshot is recorded");
+                recording.reported.insert(from);
+                recording.locals.extend(locals);
+                self.complete(id)
+            }
+        }
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        let res = self.inner.on_neighbour_error(ns, err);
+        res.map(ChandyLamportPayload::App)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished() && self.recordings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    enum BankPayload {
+        Start,
+        Transfer(u64, usize),
+    }
+
+    /// Moves money between the nodes, without creating or losing any.
+    #[derive(Debug, Clone)]
+    struct Bank {
+        aid: ActorId,
+        balance: u64,
+        turn: usize,
+    }
+
+    impl Bank {
+        fn transfer(
+            &mut self,
+            to: ActorId,
+            session: Session,
+            amount: u64,
+            hops: usize,
+        ) -> ContinuationHandler<BankPayload> {
+            self.balance -= amount;
+            let msg = Builder::with_from_actor(self.aid)
+                .with_to_actor(to)
+                .with_session(session)
+                .with_payload(BankPayload::Transfer(amount, hops))
+                .with_sender(self.aid)
+                .build();
+            ContinuationHandler::SendToNode(to, msg)
+        }
+    }
+
+    impl ProtocolHandler for Bank {
+        type Payload = BankPayload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            msg: Message<BankPayload>,
+        ) -> ContinuationHandler<BankPayload> {
+            let ns: Vec<_> = ns.collect();
+            let session = *msg.session();
+
+            match msg.payload() {
+                BankPayload::Start => ContinuationHandler::Sequence(
+                    ns.iter()
+                        .map(|n| self.transfer(*n, session, 10, 20))
+                        .collect(),
+                ),
+                BankPayload::Transfer(amount, hops) => {
+                    self.balance += amount;
+                    if *hops == 0 {
+                        return ContinuationHandler::Done;
+                    }
+                    let to = ns[self.turn % ns.len()];
+                    self.turn += 1;
+                    self.transfer(to, session, *amount, hops - 1)
+                }
+            }
+        }
+    }
+
+    fn api(session: Session, aid: ActorId, payload: ClPayload<Bank>) -> Message<ClPayload<Bank>> {
+        Builder::with_from_api()
+            .with_to_actor(aid)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(aid)
+            .build()
+    }
+
+    const EDGES: [(usize, usize); 5] = [(1, 2), (2, 3), (3, 4), (1, 4), (1, 3)];
+
+    #[actix_rt::test]
+    async fn chandy_lamport() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes: Vec<_> = (1..=4)
+            .map(|i| {
+                let bank = Bank {
+                    aid: i.into(),
+                    balance: 100,
+                    turn: 0,
+                };
+                NodeActor::build(ChandyLamport::new(bank).with_results(tx.clone()))
+            })
+            .collect();
+        for (a, b) in EDGES {
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for node in nodes.iter_mut() {
+            let aid = node.aid();
+            node.do_send(api(
+                session,
+                aid,
+                ChandyLamportPayload::App(BankPayload::Start),
+            ));
+        }
+        // Two concurrent snapshots, then a later one.
+        nodes[0].do_send(api(session, 1.into(), ChandyLamportPayload::Take));
+        nodes[2].do_send(api(session, 3.into(), ChandyLamportPayload::Take));
+        nodes[0].do_send(api(session, 1.into(), ChandyLamportPayload::Take));
+
+        let mut ids = BTreeSet::new();
+        for _ in 0..3 {
+            let snapshot = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+                .await
+                .unwrap()
+                .unwrap();
+            ids.insert(snapshot.id);
+
+            assert_eq!(4, snapshot.states.len());
+            assert_eq!(10, snapshot.channels.len());
+            let balances: u64 = snapshot.states.values().map(|s| s.balance).sum();
+            let in_flight: u64 = snapshot
+                .in_flight()
+                .map(|p| match p {
+                    BankPayload::Transfer(amount, _) => *amount,
+                    BankPayload::Start => panic!("start is not sent between nodes"),
+                })
+                .sum();
+            assert_eq!(400, balances + in_flight, "{:?}", snapshot.id);
+        }
+        assert_eq!(3, ids.len());
+    }
+}
@@ -0,0 +1,70 @@
+//! Snapshots of the global state of a distributed system.
+//!
+//! A snapshot records the local state of every node and the messages in flight
+//! on every channel, without stopping the system. The snapshot is consistent:
+//! every message it records as received was also recorded as sent, and every
+//! message recorded as sent was either received or recorded on its channel.
+
+mod chandy_lamport;
+
+pub use chandy_lamport::*;
+
+use crate::ActorId;
+use std::collections::BTreeMap;
+
+/// The identifier of a snapshot: the node which initiated it and the
+/// number of the snapshots it initiated before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SnapshotId {
+    /// The node which initiated the snapshot.
+    pub initiator: ActorId,
+    /// The number of the snapshot on the initiator.
+    pub seq: usize,
+}
+
+/// The part of a snapshot recorded by a single node.
+#[derive(Debug, Clone)]
+pub struct LocalSnapshot<S, P> {
+    /// The node.
+    pub aid: ActorId,
+    /// The state of the node.
+    pub state: S,
+    /// The messages in flight towards the node, by neighbour.
+    pub channels: BTreeMap<ActorId, Vec<P>>,
+}
+
+/// A snapshot of the whole system, assembled by the initiator.
+#[derive(Debug, Clone)]
+pub struct GlobalSnapshot<S, P> {
+    /// The identifier of the snapshot.
+    pub id: SnapshotId,
+    /// The state of every node.
+    pub states: BTreeMap<ActorId, S>,
+    /// The messages in flight on every channel, by sender and receiver.
+    pub channels: BTreeMap<(ActorId, ActorId), Vec<P>>,
+}
+
+impl<S, P> GlobalSnapshot<S, P> {
+    pub(crate) fn assemble(id: SnapshotId, locals: Vec<LocalSnapshot<S, P>>) -> Self {
+        let mut states = BTreeMap::new();
+        let mut channels = BTreeMap::new();
+
+        for local in locals {
+            for (from, msgs) in local.channels {
+                channels.insert((from, local.aid), msgs);
+            }
+            states.insert(local.aid, local.state);
+        }
+
+        Self {
+            id,
+            states,
+            channels,
+        }
+    }
+
+    /// Returns the messages in flight, whatever their channel.
+    pub fn in_flight(&self) -> impl Iterator<Item = &P> {
+        self.channels.values().flatten()
+    }
+}
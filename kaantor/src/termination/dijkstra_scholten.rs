@@ -0,0 +1,180 @@
+//! The Dijkstra–Scholten termination detection.
+//!
+//! Every message is acknowledged, and every node keeps the deficit of the
+//! messages it sent which are not acknowledged yet. The first message a node
+//! receives makes its sender the parent of the node, and this message is only
+//! acknowledged once the node is passive with no deficit. The others are
+//! acknowledged at once. The computation terminated when the initiator is
+//! passive with no deficit.
+
+use super::{sent, Terminated};
+use crate::{
+    protocol::{Builder, FromId, Message, Session},
+    ActorId, ContinuationHandler, NeighbourError, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::info;
+
+/// The payload of a `DijkstraScholten` handler, on top of the payload `P`.
+#[derive(Clone, PayloadDebug)]
+pub enum DijkstraScholtenPayload<P> {
+    /// A payload of the handler.
+    App(P),
+    /// Acknowledges a message.
+    Ack,
+}
+
+type DsCont<P> = ContinuationHandler<DijkstraScholtenPayload<P>>;
+
+/// The node in the tree of the engaged nodes.
+enum Engagement {
+    Idle,
+    Root(Session),
+    Child(ActorId, Session),
+}
+
+/// Detects the termination of the computation of the handler `H`.
+pub struct DijkstraScholten<H> {
+    inner: H,
+    engagement: Engagement,
+    deficit: usize,
+    results: Option<UnboundedSender<Terminated>>,
+}
+
+impl<H> DijkstraScholten<H>
+where
+    H: ProtocolHandler,
+{
+    /// Creates a new handler detecting the termination of `inner`.
+    pub fn new(inner: H) -> Self {
+        Self {
+            inner,
+            engagement: Engagement::Idle,
+            deficit: 0,
+            results: None,
+        }
+    }
+
+    /// Sends the termination of the computations initiated by this node to the channel.
+    pub fn with_results(mut self, results: UnboundedSender<Terminated>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Gets the handler the termination is detected of.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    fn ack(&self, to: ActorId, session: Session) -> DsCont<H::Payload> {
+        let aid = self.inner.aid();
+        let msg = Builder::with_from_actor(aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(DijkstraScholtenPayload::Ack)
+            .with_sender(aid)
+            .build();
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Leaves the tree once the node has no deficit.
+    fn detach(&mut self) -> DsCont<H::Payload> {
+        if self.deficit > 0 {
+            return ContinuationHandler::Done;
+        }
+
+        match std::mem::replace(&mut self.engagement, Engagement::Idle) {
+            Engagement::Idle => ContinuationHandler::Done,
+            Engagement::Root(session) => {
+                let aid = self.inner.aid();
+                info!("TERM | on {:?} | {:?}", aid, session);
+                if let Some(results) = &self.results {
+                    let _ = results.unbounded_send(Terminated { aid, session });
+                }
+                ContinuationHandler::Done
+            }
+            Engagement::Child(parent, session) => self.ack(parent, session),
+        }
+    }
+}
+
+impl<H> ProtocolHandler for DijkstraScholten<H>
+where
+    H: ProtocolHandler,
+{
+    type Payload = DijkstraScholtenPayload<H::Payload>;
+
+    fn aid(&self) -> ActorId {
+        self.inner.aid()
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let aid = self.inner.aid();
+        let from = msg.sender().as_aid();
+        let session = *msg.session();
+        let api = matches!(msg.from(), FromId::Api);
+
+        if let DijkstraScholtenPayload::Ack = msg.payload() {
+            self.deficit -= 1;
+            return self.detach();
+        }
+
+        let msg = msg.map_payload(|p| match p {
+            DijkstraScholtenPayload::App(p) => p,
+            DijkstraScholtenPayload::Ack => unreachable!(),
+        });
+        let res = self.inner.receive(ns.iter().copied(), msg);
+        self.deficit += sent(&ns, &res);
+        // The deferred message is acknowledged once the handler accepts it.
+        if res.is_deferred() {
+            return res.map(DijkstraScholtenPayload::App);
+        }
+
+        let ack = match (&self.engagement, api) {
+            (Engagement::Idle, true) => {
+                self.engagement = Engagement::Root(session);
+                ContinuationHandler::Done
+            }
+            (_, true) => ContinuationHandler::Done,
+            // A message the node scheduled for itself.
+            (_, false) if from == aid => {
+                self.deficit -= 1;
+                ContinuationHandler::Done
+            }
+            (Engagement::Idle, false) => {
+                self.engagement = Engagement::Child(from, session);
+                ContinuationHandler::Done
+            }
+            (_, false) => self.ack(from, session),
+        };
+
+        ack.and_then(res.map(DijkstraScholtenPayload::App))
+            .and_then(self.detach())
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        // The message was not sent, so it will not be acknowledged.
+        if let NeighbourError::UnknownDestination { .. } = err {
+            self.deficit -= 1;
+        }
+
+        let ns: Vec<_> = ns.collect();
+        let res = self.inner.on_neighbour_error(ns.iter().copied(), err);
+        self.deficit += sent(&ns, &res);
+        res.map(DijkstraScholtenPayload::App)
+            .and_then(self.detach())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished() && matches!(self.engagement, Engagement::Idle)
+    }
+}
@@ -0,0 +1,209 @@
+//! Termination detection for diffusing computations.
+//!
+//! A computation is started by API messages to a single node, the initiator,
+//! and the other nodes only take part when they receive messages. The handlers
+//! of the computation are wrapped, so they run unchanged, and the initiator
+//! reports when all the nodes are passive and no message is in flight.
+//!
+//! A node is passive once it handled a message. The messages a node schedules
+//! to itself are counted as messages in flight, so a node waiting on a timer
+//! keeps the computation going. A message the handler defers is counted as
+//! received when the handler accepts it, while the messages the handler sends
+//! along with the `Defer` are counted at once.
+
+mod dijkstra_scholten;
+mod safra;
+
+pub use dijkstra_scholten::*;
+pub use safra::*;
+
+use crate::{protocol::Session, ActorId, ContinuationHandler};
+
+/// The termination of a computation, detected by its initiator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Terminated {
+    /// The initiator of the computation.
+    pub aid: ActorId,
+    /// The session of the computation.
+    pub session: Session,
+}
+
+/// Returns the number of messages sent by the continuation,
+/// including the ones scheduled for the node itself.
+pub(crate) fn sent<P>(ns: &[ActorId], cont: &ContinuationHandler<P>) -> usize {
+    match cont {
+        ContinuationHandler::SendToNode(..) | ContinuationHandler::Schedule(..) => 1,
+        ContinuationHandler::SendToAllNodes(_) => ns.len(),
+        ContinuationHandler::SendToAllNodesExcept(_, except) => {
+            ns.iter().filter(|n| !except.contains(n)).count()
+        }
+        ContinuationHandler::Sequence(conts) => conts.iter().map(|c| sent(ns, c)).sum(),
+        ContinuationHandler::Defer(_) | ContinuationHandler::Done => 0,
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge,
+        graph::Ring,
+        protocol::{Builder, FromId, Message, SessionAllocator},
+        NodeActor, ProtocolHandler,
+    };
+    use futures::{channel::mpsc::UnboundedSender, StreamExt};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[derive(Debug, Clone)]
+    enum GossipPayload {
+        Spread(usize),
+        Wake,
+    }
+
+    #[derive(Default)]
+    struct Counters {
+        sent: AtomicUsize,
+        received: AtomicUsize,
+    }
+
+    /// Spreads a rumour a few hops away, each node waiting a bit on a timer
+    /// when the rumour goes no further. A node defers the rumour until it
+    /// woke up once, so it sets its timer and defers in the same step.
+    struct Gossip {
+        aid: ActorId,
+        awake: bool,
+        counters: Arc<Counters>,
+    }
+
+    impl ProtocolHandler for Gossip {
+        type Payload = GossipPayload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            ns: impl Iterator<Item = ActorId>,
+            msg: Message<GossipPayload>,
+        ) -> ContinuationHandler<GossipPayload> {
+            let ns: Vec<_> = ns.collect();
+            let reply = |payload| {
+                Builder::with_from_actor(self.aid)
+                    .with_to_all_actors()
+                    .with_session(*msg.session())
+                    .with_payload(payload)
+                    .with_sender(self.aid)
+                    .build()
+            };
+            let res = match msg.payload() {
+                GossipPayload::Spread(_) if !self.awake => ContinuationHandler::Sequence(vec![
+                    ContinuationHandler::Schedule(
+                        Duration::from_millis(5),
+                        reply(GossipPayload::Wake),
+                    ),
+                    ContinuationHandler::Defer(msg.clone()),
+                ]),
+                GossipPayload::Spread(0) => ContinuationHandler::Schedule(
+                    Duration::from_millis(5),
+                    reply(GossipPayload::Wake),
+                ),
+                GossipPayload::Spread(hops) => {
+                    let except = vec![msg.sender().as_aid()];
+                    ContinuationHandler::SendToAllNodesExcept(
+                        reply(GossipPayload::Spread(hops - 1)),
+                        except,
+                    )
+                }
+                GossipPayload::Wake => {
+                    self.awake = true;
+                    ContinuationHandler::Done
+                }
+            };
+
+            if !res.is_deferred() && !matches!(msg.from(), FromId::Api) {
+                self.counters.received.fetch_add(1, Ordering::SeqCst);
+            }
+
+            self.counters
+                .sent
+                .fetch_add(sent(&ns, &res), Ordering::SeqCst);
+            res
+        }
+    }
+
+    /// Runs the gossip from the node 1 on a ring with chords,
+    /// and checks that no message is in flight once termination is detected.
+    async fn detect<H, P>(build: impl Fn(Gossip, &Ring, UnboundedSender<Terminated>) -> H, start: P)
+    where
+        H: ProtocolHandler<Payload = P> + Unpin + 'static,
+        P: Clone + Send + std::fmt::Debug + 'static,
+    {
+        let ring = Ring::with_size(6);
+        let mut topology = ring.topology();
+        topology.add_edge(1.into(), 4.into());
+        topology.add_edge(2.into(), 5.into());
+
+        let counters = Arc::new(Counters::default());
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let mut nodes: Vec<_> = (1..=ring.len())
+            .map(|i| {
+                let gossip = Gossip {
+                    aid: i.into(),
+                    awake: false,
+                    counters: counters.clone(),
+                };
+                NodeActor::build(build(gossip, &ring, tx.clone()))
+            })
+            .collect();
+
+        for (a, b) in topology.edges() {
+            let (a, b) = (a.inner().min(b.inner()), a.inner().max(b.inner()));
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(session)
+            .with_payload(start)
+            .with_sender(1.into())
+            .build();
+        nodes[0].do_send(msg);
+
+        let terminated = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Terminated {
+                aid: 1.into(),
+                session
+            },
+            terminated
+        );
+
+        let sent = counters.sent.load(Ordering::SeqCst);
+        assert!(sent > ring.len());
+        assert_eq!(sent, counters.received.load(Ordering::SeqCst));
+    }
+
+    #[actix_rt::test]
+    async fn dijkstra_scholten() {
+        let ds = |gossip, _: &Ring, tx| DijkstraScholten::new(gossip).with_results(tx);
+        detect(ds, DijkstraScholtenPayload::App(GossipPayload::Spread(4))).await;
+    }
+
+    #[actix_rt::test]
+    async fn safra() {
+        let safra = |gossip, ring: &Ring, tx| Safra::new(gossip, ring).with_results(tx);
+        detect(safra, SafraPayload::App(GossipPayload::Spread(4))).await;
+    }
+}
@@ -0,0 +1,174 @@
+//! The Safra termination detection.
+//!
+//! Every node counts the messages it sent minus the messages it received, and
+//! turns black when it receives a message. The initiator sends a token around
+//! a ring of the nodes, and every node adds its count to the token, darkens it
+//! if the node is black, then turns white. The computation terminated when the
+//! token comes back white to a white initiator, with a total count of zero.
+//! Otherwise the initiator sends the token around again.
+//!
+//! The consecutive nodes of the ring must be neighbours, but the messages
+//! of the computation can go over any edge.
+
+use super::{sent, Terminated};
+use crate::{
+    graph::Ring,
+    protocol::{Builder, FromId, Message, Session},
+    ActorId, ContinuationHandler, NeighbourError, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use log::info;
+
+/// The payload of a `Safra` handler, on top of the payload `P`.
+#[derive(Clone, PayloadDebug)]
+pub enum SafraPayload<P> {
+    /// A payload of the handler.
+    App(P),
+    /// The token, with the counts of the nodes it went through.
+    Token {
+        /// The sum of the counts.
+        count: i64,
+        /// Whether a node it went through was black.
+        black: bool,
+    },
+}
+
+type SafraCont<P> = ContinuationHandler<SafraPayload<P>>;
+
+/// Detects the termination of the computation of the handler `H`.
+pub struct Safra<H> {
+    inner: H,
+    next: ActorId,
+    count: i64,
+    black: bool,
+    probing: Option<Session>,
+    results: Option<UnboundedSender<Terminated>>,
+}
+
+impl<H> Safra<H>
+where
+    H: ProtocolHandler,
+{
+    /// Creates a new handler detecting the termination of `inner`,
+    /// whose token goes around the ring.
+    pub fn new(inner: H, ring: &Ring) -> Self {
+        let next = ring.next(inner.aid());
+        Self {
+            inner,
+            next,
+            count: 0,
+            black: false,
+            probing: None,
+            results: None,
+        }
+    }
+
+    /// Sends the termination of the computations initiated by this node to the channel.
+    pub fn with_results(mut self, results: UnboundedSender<Terminated>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    /// Gets the handler the termination is detected of.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    fn token(&mut self, session: Session, count: i64, black: bool) -> SafraCont<H::Payload> {
+        self.black = false;
+        let aid = self.inner.aid();
+        let msg = Builder::with_from_actor(aid)
+            .with_to_actor(self.next)
+            .with_session(session)
+            .with_payload(SafraPayload::Token { count, black })
+            .with_sender(aid)
+            .build();
+        ContinuationHandler::SendToNode(self.next, msg)
+    }
+
+    /// Handles the token back to the initiator.
+    fn probe(&mut self, session: Session, count: i64, black: bool) -> SafraCont<H::Payload> {
+        if black || self.black || count + self.count != 0 {
+            return self.token(session, 0, false);
+        }
+
+        let aid = self.inner.aid();
+        info!("TERM | on {:?} | {:?}", aid, session);
+        self.probing = None;
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Terminated { aid, session });
+        }
+        ContinuationHandler::Done
+    }
+}
+
+impl<H> ProtocolHandler for Safra<H>
+where
+    H: ProtocolHandler,
+{
+    type Payload = SafraPayload<H::Payload>;
+
+    fn aid(&self) -> ActorId {
+        self.inner.aid()
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let ns: Vec<_> = ns.collect();
+        let session = *msg.session();
+        let api = matches!(msg.from(), FromId::Api);
+
+        if let SafraPayload::Token { count, black } = *msg.payload() {
+            return match self.probing {
+                Some(probing) if probing == session => self.probe(session, count, black),
+                _ => self.token(session, count + self.count, black || self.black),
+            };
+        }
+
+        let msg = msg.map_payload(|p| match p {
+            SafraPayload::App(p) => p,
+            SafraPayload::Token { .. } => unreachable!(),
+        });
+        let res = self.inner.receive(ns.iter().copied(), msg);
+        self.count += sent(&ns, &res) as i64;
+        // The deferred message is counted once the handler accepts it.
+        if res.is_deferred() {
+            return res.map(SafraPayload::App);
+        }
+
+        if !api {
+            self.count -= 1;
+            self.black = true;
+        }
+        let res = res.map(SafraPayload::App);
+
+        if api && self.probing.is_none() {
+            self.probing = Some(session);
+            return res.and_then(self.token(session, 0, false));
+        }
+        res
+    }
+
+    fn on_neighbour_error(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        err: NeighbourError,
+    ) -> ContinuationHandler<Self::Payload> {
+        // The message was not sent, so it will not be received.
+        if let NeighbourError::UnknownDestination { .. } = err {
+            self.count -= 1;
+        }
+
+        let ns: Vec<_> = ns.collect();
+        let res = self.inner.on_neighbour_error(ns.iter().copied(), err);
+        self.count += sent(&ns, &res) as i64;
+        res.map(SafraPayload::App)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished() && self.probing.is_none()
+    }
+}
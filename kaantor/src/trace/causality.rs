@@ -0,0 +1,121 @@
+use super::Event;
+use crate::{protocol::Session, ActorId};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
+
+/// A decide event which is not causally preceded by an event on every node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CausalityError {
+    /// The node which decided.
+    pub aid: ActorId,
+    /// The session in which the node decided.
+    pub session: Session,
+    /// The nodes without any event in the causal past of the decide event.
+    pub missing: Vec<ActorId>,
+}
+
+impl Display for CausalityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "decide on {:?} in {:?} is not preceded by any event on {:?}",
+            self.aid, self.session, self.missing
+        )
+    }
+}
+
+impl std::error::Error for CausalityError {}
+
+/// Checks that every decide event of the trace is causally preceded by an
+/// event on each of the given nodes, as the definition of a wave requires.
+/// Returns the number of decide events which were checked.
+///
+/// The events are expected in the order they were recorded, so a message is
+/// always sent before it is received. The messages received from outside the
+/// graph, e.g. through the api, have no causal predecessor.
+pub fn check_decide_events(events: &[Event], nodes: &[ActorId]) -> Result<usize, CausalityError> {
+    // The nodes with an event in the causal past of the last event of a node,
+    // and of the send event of a message.
+    let mut pasts: HashMap<ActorId, BTreeSet<ActorId>> = HashMap::new();
+    let mut sent = HashMap::new();
+    let mut decided = 0;
+
+    for event in events {
+        let aid = event.aid();
+        let past = pasts.entry(aid).or_default();
+        past.insert(aid);
+
+        match event {
+            Event::Send { mid, .. } => {
+                sent.insert(*mid, past.clone());
+            }
+            Event::Recv { mid, .. } => {
+                if let Some(before) = sent.get(mid) {
+                    past.extend(before);
+                }
+            }
+            Event::Decide { session, .. } => {
+                let missing: Vec<_> = nodes.iter().filter(|n| !past.contains(n)).collect();
+                if !missing.is_empty() {
+                    return Err(CausalityError {
+                        aid,
+                        session: *session,
+                        missing: missing.into_iter().copied().collect(),
+                    });
+                }
+                decided += 1;
+            }
+            Event::Error { .. } => (),
+        }
+    }
+
+    Ok(decided)
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::protocol::{MessageId, SessionAllocator};
+
+    #[test]
+    fn check_decide_events_() {
+        let a = |i: usize| ActorId::from(i);
+        let session = SessionAllocator::for_api().next_session();
+        let send = |i, j| Event::Send {
+            aid: a(i),
+            mid: MessageId::new(a(j), 0),
+            session,
+        };
+        let recv = |i, j| Event::Recv {
+            aid: a(i),
+            mid: MessageId::new(a(j), 0),
+            session,
+        };
+        let nodes = [a(1), a(2), a(3)];
+
+        // 1 -> 2 -> 1, and 3 -> 1 after 1 decided.
+        let mut events = vec![
+            send(1, 1),
+            recv(2, 1),
+            send(2, 2),
+            send(3, 3),
+            recv(1, 2),
+            Event::Decide { aid: a(1), session },
+            recv(1, 3),
+        ];
+        assert_eq!(
+            Err(CausalityError {
+                aid: a(1),
+                session,
+                missing: vec![a(3)]
+            }),
+            check_decide_events(&events, &nodes)
+        );
+
+        // The message of 3 arrives before the decide event.
+        events.swap(5, 6);
+        assert_eq!(Ok(1), check_decide_events(&events, &nodes));
+    }
+}
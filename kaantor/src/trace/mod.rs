@@ -0,0 +1,107 @@
+//! Traces of the events which took place on the nodes of a graph.
+//!
+//! The nodes built with `NodeActor::build_traced` record every message they
+//! send and receive, and the handlers can record their own events, e.g. the
+//! decide event of a wave. The trace is shared by all the nodes and it keeps
+//! the events of every node in the order in which they happened on the node.
+
+mod causality;
+
+pub use causality::*;
+
+use crate::{
+    protocol::{MessageId, Session},
+    ActorId, NeighbourError,
+};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::sync::{Arc, Mutex};
+
+/// An event which took place on a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The node sent a message. A message sent to several neighbours
+    /// is a single event.
+    Send {
+        /// The node which sent the message.
+        aid: ActorId,
+        /// The identifier of the message.
+        mid: MessageId,
+        /// The session of the message.
+        session: Session,
+    },
+    /// The node received a message.
+    Recv {
+        /// The node which received the message.
+        aid: ActorId,
+        /// The identifier of the message.
+        mid: MessageId,
+        /// The session of the message.
+        session: Session,
+    },
+    /// The node decided.
+    Decide {
+        /// The node which decided.
+        aid: ActorId,
+        /// The session in which the node decided.
+        session: Session,
+    },
+    /// A continuation of the node named a node which is not a neighbour.
+    Error {
+        /// The node.
+        aid: ActorId,
+        /// The error.
+        error: NeighbourError,
+    },
+}
+
+impl Event {
+    /// Returns the node on which the event took place.
+    pub fn aid(&self) -> ActorId {
+        match self {
+            Self::Send { aid, .. }
+            | Self::Recv { aid, .. }
+            | Self::Decide { aid, .. }
+            | Self::Error { aid, .. } => *aid,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    events: Vec<Event>,
+    subscribers: Vec<UnboundedSender<Event>>,
+}
+
+/// A trace shared by the nodes of a graph.
+#[derive(Clone, Default)]
+pub struct Trace {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Trace {
+    /// Creates an empty trace.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends an event to the trace and forwards it to the subscribers.
+    pub fn record(&self, event: Event) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .subscribers
+            .retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+        inner.events.push(event);
+    }
+
+    /// Returns the events recorded so far.
+    pub fn events(&self) -> Vec<Event> {
+        self.inner.lock().unwrap().events.clone()
+    }
+
+    /// Returns a stream with the events which will be recorded from now on.
+    pub fn subscribe(&self) -> UnboundedReceiver<Event> {
+        let (tx, rx) = unbounded();
+        self.inner.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+}
@@ -0,0 +1,74 @@
+//! A transport which carries the protocol messages over in-memory tokio channels.
+//!
+//! The messages are not encoded, so this transport is mostly useful to measure
+//! the overhead of the other transports, or to decouple two nodes of the same process.
+//!
+//! The link is FIFO: a single task reads the channel and delivers the messages
+//! to the mailbox of the node in the order they were sent.
+
+use super::{proxy, Transport, TransportError};
+use crate::{graph::GraphMsg, protocol::Message as PMsg, proxy::Proxy, Node};
+use actix::{dev::ToEnvelope, prelude::*};
+use log::debug;
+use tokio::sync::mpsc;
+
+type GMsg<P> = GraphMsg<PMsg<P>>;
+
+/// A transport which hands the messages to an unbounded tokio channel.
+pub struct ChannelTransport<P> {
+    tx: mpsc::UnboundedSender<PMsg<P>>,
+}
+
+impl<P> Transport<P> for ChannelTransport<P>
+where
+    P: Unpin + 'static,
+{
+    fn transmit(&mut self, msg: PMsg<P>) -> Result<(), TransportError> {
+        self.tx.send(msg).map_err(|_| TransportError::Closed)
+    }
+}
+
+/// Creates a channel whose receiving end delivers the messages to the given node.
+/// Returns the proxy which sends the messages over the channel, and which can be
+/// added to the neighbours of the node.
+pub fn connect<A, P>(node: &Node<A, P>) -> Proxy<PMsg<P>>
+where
+    P: Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    let aid = node.aid();
+    let recipient = node.recipient();
+    let (tx, mut rx) = mpsc::unbounded_channel::<PMsg<P>>();
+
+    actix_rt::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            recipient.do_send(msg);
+        }
+        debug!("channel to {:?} closed", aid);
+    });
+
+    proxy(aid, ChannelTransport { tx })
+}
+
+/// Adds a bi-directional connection between two nodes, over tokio channels.
+pub async fn add_edge<A, P>(a: &mut Node<A, P>, b: &mut Node<A, P>)
+where
+    P: Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    debug!("add channel edge [{:?}-{:?}]", a.aid(), b.aid());
+
+    let pxy_a = connect(a);
+    let pxy_b = connect(b);
+
+    let _ = a.add_proxy(pxy_b).await;
+    let _ = b.add_proxy(pxy_a).await;
+}
@@ -0,0 +1,44 @@
+//! Length delimited frames over a byte stream.
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The maximum size of a frame we accept from a remote node.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Writes a frame made of the length of the buffer followed by the buffer itself.
+pub(crate) async fn write_frame<W>(w: &mut W, bytes: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    if bytes.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame too long",
+        ));
+    }
+
+    w.write_u32(bytes.len() as u32).await?;
+    w.write_all(bytes).await?;
+    w.flush().await
+}
+
+/// Reads the next frame. Returns `None` when the remote node closed the stream.
+pub(crate) async fn read_frame<R>(r: &mut R) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = match r.read_u32().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+    }
+
+    let mut bytes = vec![0; len];
+    r.read_exact(&mut bytes).await?;
+    Ok(Some(bytes))
+}
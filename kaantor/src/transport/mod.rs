@@ -0,0 +1,351 @@
+//! Transports which carry the protocol messages between nodes.
+//!
+//! A transport is made of two parts: a local `Link` actor which hands the messages
+//! to a `Transport`, and which is wrapped by a `Proxy` like any other node; and a
+//! receiving end which delivers the messages to the remote node. This way a
+//! `ProtocolHandler` runs unchanged whatever carries the messages to its neighbours.
+//!
+//! All the transports deliver the messages sent over one link in the order
+//! they were sent, i.e. the links are FIFO channels, which some algorithms
+//! rely on, e.g. the snapshots of `crate::snapshot`. The `Link` actor hands the
+//! messages to the transport in the order of its mailbox, and every transport
+//! keeps that order up to the remote node, see the documentation of each one.
+
+#[cfg(feature = "channel")]
+pub mod channel;
+#[cfg(any(feature = "tcp", feature = "uds"))]
+mod frame;
+#[cfg(any(feature = "tcp", feature = "uds"))]
+mod stream;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(all(unix, feature = "uds"))]
+pub mod uds;
+
+#[cfg(any(feature = "tcp", feature = "uds"))]
+pub use frame::MAX_FRAME_LEN;
+#[cfg(any(feature = "tcp", feature = "uds"))]
+pub use stream::*;
+
+use crate::{
+    graph::Topology,
+    protocol::Message as PMsg,
+    proxy::{Builder as PxyBuilder, Proxy},
+    ActorId,
+};
+use actix::prelude::*;
+use log::error;
+use std::{collections::HashMap, fmt::Display, marker::PhantomData};
+
+/// The errors which can occur while transmitting a message.
+#[derive(Debug)]
+pub enum TransportError {
+    /// The remote end of the transport is closed.
+    Closed,
+    /// The message could not be encoded.
+    #[cfg(feature = "serde")]
+    Encode(crate::protocol::wire::WireError),
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "transport closed"),
+            #[cfg(feature = "serde")]
+            Self::Encode(e) => write!(f, "encode failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Carries the protocol messages to a remote node.
+pub trait Transport<P>: Unpin + 'static {
+    /// Transmits the message to the remote node.
+    fn transmit(&mut self, msg: PMsg<P>) -> Result<(), TransportError>;
+}
+
+/// An actor which hands the protocol messages it receives to a `Transport`.
+pub struct Link<P, T> {
+    aid: ActorId,
+    transport: T,
+    phantom: PhantomData<P>,
+}
+
+impl<P, T> Actor for Link<P, T>
+where
+    P: Unpin + 'static,
+    T: Transport<P>,
+{
+    type Context = Context<Self>;
+}
+
+impl<P, T> Handler<PMsg<P>> for Link<P, T>
+where
+    P: Unpin + 'static,
+    T: Transport<P>,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: PMsg<P>, ctx: &mut Self::Context) -> Self::Result {
+        match self.transport.transmit(msg) {
+            Ok(()) => (),
+            Err(TransportError::Closed) => {
+                error!("link to {:?} is closed", self.aid);
+                ctx.stop();
+            }
+            #[allow(unreachable_patterns)]
+            Err(e) => error!("link to {:?} failed: {}", self.aid, e),
+        }
+    }
+}
+
+/// Builds a proxy for the node `aid` which sends the messages over the given transport.
+pub fn proxy<P, T>(aid: ActorId, transport: T) -> Proxy<PMsg<P>>
+where
+    P: Send + Unpin + 'static,
+    T: Transport<P>,
+{
+    let link = Link {
+        aid,
+        transport,
+        phantom: PhantomData,
+    }
+    .start();
+
+    PxyBuilder::from_aid(aid)
+        .with_recipient(link.recipient())
+        .build()
+}
+
+/// The configuration of a network of nodes: the address every node
+/// can be reached at and the edges between the nodes.
+#[derive(Debug, Clone)]
+pub struct Config<A> {
+    addrs: HashMap<ActorId, A>,
+    topology: Topology,
+}
+
+impl<A> Default for Config<A> {
+    fn default() -> Self {
+        Self {
+            addrs: Default::default(),
+            topology: Default::default(),
+        }
+    }
+}
+
+impl<A> Config<A> {
+    /// Creates an empty configuration.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the address the node can be reached at.
+    pub fn with_node(mut self, aid: ActorId, addr: A) -> Self {
+        self.addrs.insert(aid, addr);
+        self
+    }
+
+    /// Adds a bi-directional edge between two nodes.
+    pub fn with_edge(mut self, a: ActorId, b: ActorId) -> Self {
+        self.topology.add_edge(a, b);
+        self
+    }
+
+    /// Sets the edges between the nodes.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Gets the address a node can be reached at.
+    pub fn addr(&self, aid: ActorId) -> Option<&A> {
+        self.addrs.get(&aid)
+    }
+
+    /// Gets the edges between the nodes.
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use crate::{
+        protocol::{Builder, Message as PMsg, SessionAllocator},
+        ActorId, ContinuationHandler, NodeActor, NodeHandler, ProtocolHandler,
+    };
+    use futures::{
+        channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+        StreamExt,
+    };
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    enum Payload {
+        Start,
+        Ping(usize),
+        Pong(usize),
+        Burst(usize),
+        Seq(usize),
+    }
+
+    struct PingHandler {
+        aid: ActorId,
+        pongs: UnboundedSender<usize>,
+    }
+
+    impl ProtocolHandler for PingHandler {
+        type Payload = Payload;
+
+        fn aid(&self) -> ActorId {
+            self.aid
+        }
+
+        fn receive(
+            &mut self,
+            mut ns: impl Iterator<Item = ActorId>,
+            msg: PMsg<Self::Payload>,
+        ) -> ContinuationHandler<Self::Payload> {
+            let reply = |to: ActorId, payload: Payload| {
+                let msg = Builder::with_from_actor(self.aid)
+                    .with_to_actor(to)
+                    .with_session(*msg.session())
+                    .with_payload(payload)
+                    .with_sender(self.aid)
+                    .build();
+                ContinuationHandler::SendToNode(to, msg)
+            };
+
+            match msg.payload() {
+                Payload::Start => reply(ns.next().unwrap(), Payload::Ping(7)),
+                Payload::Ping(v) => reply(msg.sender().as_aid(), Payload::Pong(v + 1)),
+                Payload::Pong(v) | Payload::Seq(v) => {
+                    let _ = self.pongs.unbounded_send(*v);
+                    ContinuationHandler::Done
+                }
+                Payload::Burst(n) => {
+                    let to = ns.next().unwrap();
+                    ContinuationHandler::Sequence(
+                        (0..*n).map(|i| reply(to, Payload::Seq(i))).collect(),
+                    )
+                }
+            }
+        }
+    }
+
+    fn build_pair() -> (
+        NodeHandler<PingHandler>,
+        NodeHandler<PingHandler>,
+        UnboundedReceiver<usize>,
+    ) {
+        let (tx, rx) = unbounded();
+        let n1 = NodeActor::build(PingHandler {
+            aid: 1.into(),
+            pongs: tx.clone(),
+        });
+        let n2 = NodeActor::build(PingHandler {
+            aid: 2.into(),
+            pongs: tx,
+        });
+        (n1, n2, rx)
+    }
+
+    fn start(n1: &NodeHandler<PingHandler>, payload: Payload) -> PMsg<Payload> {
+        Builder::with_from_api()
+            .with_to_actor(n1.aid())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(payload)
+            .with_sender(n1.aid())
+            .build()
+    }
+
+    async fn ping_pong(n1: &mut NodeHandler<PingHandler>, pongs: &mut UnboundedReceiver<usize>) {
+        n1.send(start(n1, Payload::Start)).await.unwrap();
+
+        let pong = actix_rt::time::timeout(Duration::from_secs(5), pongs.next())
+            .await
+            .unwrap();
+        assert_eq!(Some(8), pong);
+    }
+
+    /// Checks that a burst of messages is received in the order it was sent.
+    async fn fifo(n1: &mut NodeHandler<PingHandler>, seqs: &mut UnboundedReceiver<usize>) {
+        const N: usize = 500;
+        n1.send(start(n1, Payload::Burst(N))).await.unwrap();
+
+        for i in 0..N {
+            let seq = actix_rt::time::timeout(Duration::from_secs(5), seqs.next())
+                .await
+                .unwrap();
+            assert_eq!(Some(i), seq);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn fifo_over_actors() {
+        let (mut n1, mut n2, mut seqs) = build_pair();
+        crate::add_edge(&mut n1, &mut n2).await;
+        fifo(&mut n1, &mut seqs).await;
+    }
+
+    #[cfg(feature = "tcp")]
+    #[actix_rt::test]
+    async fn ping_pong_over_tcp() {
+        use super::tcp;
+
+        let (mut n1, mut n2, mut pongs) = build_pair();
+        let localhost = "127.0.0.1:0".parse().unwrap();
+
+        let a1 = tcp::listen(&n1, localhost).await.unwrap();
+        let a2 = tcp::listen(&n2, localhost).await.unwrap();
+
+        let cfg = tcp::Config::new()
+            .with_node(n1.aid(), a1)
+            .with_node(n2.aid(), a2)
+            .with_edge(n1.aid(), n2.aid());
+
+        tcp::connect_neighbours(&mut n1, &cfg).await.unwrap();
+        tcp::connect_neighbours(&mut n2, &cfg).await.unwrap();
+
+        ping_pong(&mut n1, &mut pongs).await;
+        fifo(&mut n1, &mut pongs).await;
+    }
+
+    #[cfg(all(unix, feature = "uds"))]
+    #[actix_rt::test]
+    async fn ping_pong_over_uds() {
+        use super::uds;
+
+        let (mut n1, mut n2, mut pongs) = build_pair();
+        let dir = std::env::temp_dir();
+        let pid = std::process::id();
+
+        let cfg = uds::Config::new()
+            .with_node(n1.aid(), dir.join(format!("kaantor-{pid}-1.sock")))
+            .with_node(n2.aid(), dir.join(format!("kaantor-{pid}-2.sock")))
+            .with_edge(n1.aid(), n2.aid());
+
+        uds::listen(&n1, cfg.addr(n1.aid()).unwrap()).await.unwrap();
+        uds::join(&mut n2, &cfg).await.unwrap();
+        uds::connect_neighbours(&mut n1, &cfg).await.unwrap();
+
+        ping_pong(&mut n1, &mut pongs).await;
+        fifo(&mut n1, &mut pongs).await;
+
+        for aid in [n1.aid(), n2.aid()] {
+            let _ = std::fs::remove_file(cfg.addr(aid).unwrap());
+        }
+    }
+
+    #[cfg(feature = "channel")]
+    #[actix_rt::test]
+    async fn ping_pong_over_channel() {
+        let (mut n1, mut n2, mut pongs) = build_pair();
+        super::channel::add_edge(&mut n1, &mut n2).await;
+        ping_pong(&mut n1, &mut pongs).await;
+        fifo(&mut n1, &mut pongs).await;
+    }
+}
@@ -0,0 +1,115 @@
+//! The transport shared by all the byte stream connections.
+
+use super::{
+    frame::{read_frame, write_frame},
+    Transport, TransportError,
+};
+use crate::{
+    protocol::{wire, Message as PMsg},
+    ActorId,
+};
+use actix::prelude::*;
+use log::{debug, error};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Debug, future::Future, io, marker::PhantomData, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+
+/// The number of attempts to connect to a remote node before giving up.
+pub const CONNECT_ATTEMPTS: usize = 50;
+
+/// The delay between two attempts to connect to a remote node.
+pub const CONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// The delay before a listener accepts connections again after an error,
+/// e.g. when the process ran out of file descriptors.
+pub const ACCEPT_DELAY: Duration = Duration::from_millis(100);
+
+/// Connects to the remote node at `target`, and retries while it is not listening yet.
+pub(crate) async fn dial<T, F, Fut, S>(target: T, mut connect: F) -> io::Result<S>
+where
+    T: Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = io::Result<S>>,
+{
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if attempt >= CONNECT_ATTEMPTS => return Err(e),
+            Err(e) => {
+                debug!("dial {:?} failed (attempt {}): {}", target, attempt, e);
+                attempt += 1;
+                actix_rt::time::sleep(CONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// A transport which encodes the messages and writes them,
+/// as length delimited frames, to a byte stream.
+pub struct StreamTransport<P> {
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    phantom: PhantomData<P>,
+}
+
+impl<P> StreamTransport<P> {
+    /// Creates a new transport which writes to the given stream. The writes
+    /// are done by a separate task, so transmitting a message never blocks.
+    pub fn new<W>(aid: ActorId, mut writer: W) -> Self
+    where
+        W: AsyncWrite + Unpin + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        actix_rt::spawn(async move {
+            while let Some(bytes) = rx.recv().await {
+                if let Err(e) = write_frame(&mut writer, &bytes).await {
+                    error!("stream write to {:?} failed: {}", aid, e);
+                    break;
+                }
+            }
+        });
+
+        Self {
+            tx,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<P> Transport<P> for StreamTransport<P>
+where
+    P: Serialize + Unpin + 'static,
+{
+    fn transmit(&mut self, msg: PMsg<P>) -> Result<(), TransportError> {
+        let bytes = wire::encode(&msg).map_err(TransportError::Encode)?;
+        self.tx.send(bytes).map_err(|_| TransportError::Closed)
+    }
+}
+
+/// Reads the frames from the stream, decodes them and delivers the messages
+/// to the node `aid`, until the remote end closes the stream.
+pub(crate) async fn read_loop<P, R>(aid: ActorId, mut reader: R, recipient: Recipient<PMsg<P>>)
+where
+    P: DeserializeOwned + Send + 'static,
+    R: AsyncRead + Unpin,
+{
+    loop {
+        match read_frame(&mut reader).await {
+            Ok(Some(bytes)) => match wire::decode::<P>(&bytes) {
+                Ok(msg) => recipient.do_send(msg),
+                Err(e) => error!("stream read on {:?} failed to decode: {}", aid, e),
+            },
+            Ok(None) => {
+                debug!("stream to {:?} closed", aid);
+                break;
+            }
+            Err(e) => {
+                error!("stream read on {:?} failed: {}", aid, e);
+                break;
+            }
+        }
+    }
+}
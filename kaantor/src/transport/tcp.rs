@@ -0,0 +1,114 @@
+//! A transport which carries the protocol messages over TCP connections.
+//!
+//! Each node listens on its own address. For every edge of the topology, a node
+//! dials the address of its neighbour and uses the connection to send messages
+//! to it, so every connection carries messages in a single direction.
+//!
+//! The link is FIFO: a single task writes the frames to the connection, which
+//! keeps their order, and a single task reads them and delivers the messages.
+
+use super::{dial, proxy, read_loop, StreamTransport, ACCEPT_DELAY};
+use crate::{graph::GraphMsg, protocol::Message as PMsg, proxy::Proxy, ActorId, Node};
+use actix::{dev::ToEnvelope, prelude::*};
+use log::{debug, error};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{io, net::SocketAddr};
+use tokio::net::{TcpListener, TcpStream};
+
+type GMsg<P> = GraphMsg<PMsg<P>>;
+
+/// The configuration of a network of nodes which communicate over TCP.
+pub type Config = super::Config<SocketAddr>;
+
+/// Connects to the node `aid` listening on `addr` and returns the proxy
+/// which can be added to the local node to send messages to the remote one.
+/// The connection is retried while the remote node is not listening yet.
+pub async fn connect<P>(aid: ActorId, addr: SocketAddr) -> io::Result<Proxy<PMsg<P>>>
+where
+    P: Serialize + Send + Unpin + 'static,
+{
+    let stream = dial(addr, || TcpStream::connect(addr)).await?;
+    stream.set_nodelay(true)?;
+    debug!("tcp connected to {:?} at {}", aid, addr);
+
+    Ok(proxy(aid, StreamTransport::new(aid, stream)))
+}
+
+/// Starts listening for the messages sent by the remote nodes to the given node.
+/// Returns the address the node listens on, which is useful when binding to port 0.
+pub async fn listen<A, P>(node: &Node<A, P>, addr: SocketAddr) -> io::Result<SocketAddr>
+where
+    P: DeserializeOwned + Send + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    let listener = TcpListener::bind(addr).await?;
+    let local = listener.local_addr()?;
+    let aid = node.aid();
+    let recipient = node.recipient();
+    debug!("tcp listening for {:?} at {}", aid, local);
+
+    actix_rt::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    debug!("tcp accepted for {:?} from {}", aid, peer);
+                    actix_rt::spawn(read_loop(aid, stream, recipient.clone()));
+                }
+                Err(e) => {
+                    error!("tcp accept for {:?} failed: {}", aid, e);
+                    actix_rt::time::sleep(ACCEPT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    Ok(local)
+}
+
+/// Connects the node to all its neighbours from the configuration.
+pub async fn connect_neighbours<A, P>(node: &mut Node<A, P>, cfg: &Config) -> io::Result<()>
+where
+    P: Serialize + Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    for aid in cfg.topology().neighbours(node.aid()) {
+        let addr = cfg.addr(aid).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no address for {aid:?}"))
+        })?;
+
+        let proxy = connect(aid, *addr).await?;
+        node.add_proxy(proxy).await.map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+/// Makes the node part of the network described by the configuration: the node
+/// starts listening on its own address and then connects to all its neighbours.
+pub async fn join<A, P>(node: &mut Node<A, P>, cfg: &Config) -> io::Result<SocketAddr>
+where
+    P: Serialize + DeserializeOwned + Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    let aid = node.aid();
+    let addr = cfg.addr(aid).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no address for {aid:?}"))
+    })?;
+
+    let local = listen(node, *addr).await?;
+    connect_neighbours(node, cfg).await?;
+
+    Ok(local)
+}
@@ -0,0 +1,121 @@
+//! A transport which carries the protocol messages over Unix domain sockets.
+//!
+//! Each node listens on its own socket path. For every edge of the topology, a node
+//! connects to the socket of its neighbour and uses the connection to send messages
+//! to it, so every connection carries messages in a single direction. This allows
+//! running nodes in different processes of the same host without using network ports.
+//!
+//! The link is FIFO: a single task writes the frames to the connection, which
+//! keeps their order, and a single task reads them and delivers the messages.
+
+use super::{dial, proxy, read_loop, StreamTransport, ACCEPT_DELAY};
+use crate::{graph::GraphMsg, protocol::Message as PMsg, proxy::Proxy, ActorId, Node};
+use actix::{dev::ToEnvelope, prelude::*};
+use log::{debug, error};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+};
+use tokio::net::{UnixListener, UnixStream};
+
+type GMsg<P> = GraphMsg<PMsg<P>>;
+
+/// The configuration of a network of nodes which communicate over Unix domain sockets.
+pub type Config = super::Config<PathBuf>;
+
+/// Connects to the node `aid` listening on `path` and returns the proxy
+/// which can be added to the local node to send messages to the remote one.
+/// The connection is retried while the remote node is not listening yet.
+pub async fn connect<P>(aid: ActorId, path: &Path) -> io::Result<Proxy<PMsg<P>>>
+where
+    P: Serialize + Send + Unpin + 'static,
+{
+    let stream = dial(path, || UnixStream::connect(path)).await?;
+    debug!("uds connected to {:?} at {:?}", aid, path);
+
+    Ok(proxy(aid, StreamTransport::new(aid, stream)))
+}
+
+/// Starts listening for the messages sent by the remote nodes to the given node.
+/// A socket left behind by a previous run at the same path is removed first.
+pub async fn listen<A, P>(node: &Node<A, P>, path: &Path) -> io::Result<()>
+where
+    P: DeserializeOwned + Send + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let listener = UnixListener::bind(path)?;
+    let aid = node.aid();
+    let recipient = node.recipient();
+    debug!("uds listening for {:?} at {:?}", aid, path);
+
+    actix_rt::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    debug!("uds accepted for {:?}", aid);
+                    actix_rt::spawn(read_loop(aid, stream, recipient.clone()));
+                }
+                Err(e) => {
+                    error!("uds accept for {:?} failed: {}", aid, e);
+                    actix_rt::time::sleep(ACCEPT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Connects the node to all its neighbours from the configuration.
+pub async fn connect_neighbours<A, P>(node: &mut Node<A, P>, cfg: &Config) -> io::Result<()>
+where
+    P: Serialize + Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    for aid in cfg.topology().neighbours(node.aid()) {
+        let path = cfg.addr(aid).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no path for {aid:?}"))
+        })?;
+
+        let proxy = connect(aid, path).await?;
+        node.add_proxy(proxy).await.map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+/// Makes the node part of the network described by the configuration: the node
+/// starts listening on its own socket and then connects to all its neighbours.
+pub async fn join<A, P>(node: &mut Node<A, P>, cfg: &Config) -> io::Result<()>
+where
+    P: Serialize + DeserializeOwned + Send + Unpin + 'static,
+    A: Actor,
+    A: Handler<PMsg<P>>,
+    A::Context: ToEnvelope<A, PMsg<P>>,
+    A: Handler<GMsg<P>>,
+    A::Context: ToEnvelope<A, GMsg<P>>,
+{
+    let aid = node.aid();
+    let path = cfg
+        .addr(aid)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no path for {aid:?}")))?;
+
+    listen(node, path).await?;
+    connect_neighbours(node, cfg).await
+}
@@ -0,0 +1,153 @@
+use super::{Core, Token, Traversal, TraversalHandler, TraversalPayload};
+use crate::{
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, Dispatch, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashSet;
+
+/// Awerbuch's depth-first search: on its first visit, a node tells all its
+/// neighbours but the parent that it was visited and it waits for their
+/// acknowledgements before sending the token on. The token is therefore
+/// sent only to the nodes which were not visited yet.
+pub struct Awerbuch {
+    core: Core,
+    visited: HashSet<ActorId>,
+    acks: usize,
+    token: Option<Token>,
+}
+
+impl Awerbuch {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            core: Core::new(aid),
+            visited: Default::default(),
+            acks: 0,
+            token: None,
+        }
+    }
+
+    /// Sends the outcome of the traversal to the channel, if the node is the initiator.
+    pub fn with_results(mut self, results: UnboundedSender<Traversal>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+
+    /// Sends an info to all the neighbours but the parent, keeping the token
+    /// until all of them acknowledged it.
+    fn inform(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        mut token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let parent = self.core.parent;
+        let targets: Vec<_> = ns.filter(|n| Some(*n) != parent).collect();
+        if targets.is_empty() {
+            return self.next(targets.into_iter(), session, token);
+        }
+
+        self.acks = targets.len();
+        token.messages += 2 * targets.len();
+        self.token = Some(token);
+
+        targets
+            .into_iter()
+            .fold(ContinuationHandler::Done, |res, n| {
+                res.and_then(self.core.build_msg(n, session, TraversalPayload::Info))
+            })
+    }
+
+    fn next(
+        &mut self,
+        mut ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let parent = self.core.parent;
+
+        match ns.find(|n| !self.visited.contains(n) && Some(*n) != parent) {
+            Some(to) => {
+                self.visited.insert(to);
+                self.core.forward(to, session, token)
+            }
+            None => match parent {
+                Some(pid) => self.core.forward(pid, session, token),
+                None => self.core.finish(session, token),
+            },
+        }
+    }
+}
+
+impl TraversalHandler for Awerbuch {
+    fn handle_start(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        self.core.initiator = true;
+        let token = Token {
+            route: vec![self.core.aid],
+            messages: 0,
+        };
+        self.inform(ns, *msg.session(), token)
+    }
+
+    fn handle_token(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+        token: &Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let mut token = token.clone();
+        token.route.push(self.core.aid);
+
+        if self.core.visit(msg.sender().as_aid()) {
+            self.inform(ns, *msg.session(), token)
+        } else {
+            self.next(ns, *msg.session(), token)
+        }
+    }
+
+    fn handle_info(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        _ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let sender = msg.sender().as_aid();
+        self.visited.insert(sender);
+        self.core
+            .build_msg(sender, *msg.session(), TraversalPayload::Ack)
+    }
+
+    fn handle_ack(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        self.acks -= 1;
+        if self.acks != 0 {
+            return ContinuationHandler::Done;
+        }
+
+        let token = self.token.take().expect("the node keeps the token");
+        self.next(ns, *msg.session(), token)
+    }
+}
+
+impl ProtocolHandler for Awerbuch {
+    type Payload = TraversalPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        TraversalPayload::dispatch(self, ns, msg)
+    }
+}
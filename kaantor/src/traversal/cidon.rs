@@ -0,0 +1,181 @@
+use super::{Core, Token, Traversal, TraversalHandler, TraversalPayload};
+use crate::{
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, Dispatch, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashSet;
+
+/// Cidon's depth-first search: like Awerbuch's algorithm, a node tells its
+/// neighbours it was visited, but it sends the token on without waiting for
+/// acknowledgements. A token sent to a node which was already visited is
+/// dropped there, and the sender sends it on again once it gets the info of
+/// that node.
+pub struct Cidon {
+    core: Core,
+    visited: HashSet<ActorId>,
+    // The neighbour the token was sent to, and the token as it was sent.
+    waiting: Option<(ActorId, Token)>,
+}
+
+impl Cidon {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            core: Core::new(aid),
+            visited: Default::default(),
+            waiting: None,
+        }
+    }
+
+    /// Sends the outcome of the traversal to the channel, if the node is the initiator.
+    pub fn with_results(mut self, results: UnboundedSender<Traversal>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+
+    /// Returns a neighbour which was not visited yet, as far as the node knows.
+    fn unvisited(&self, mut ns: impl Iterator<Item = ActorId>) -> Option<ActorId> {
+        let parent = self.core.parent;
+        ns.find(|n| !self.visited.contains(n) && Some(*n) != parent)
+    }
+
+    /// Sends an info to all the neighbours but the parent and the one
+    /// which gets the token next.
+    fn inform(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        mut token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let ns: Vec<_> = ns.collect();
+        let next = self.unvisited(ns.iter().copied());
+        let parent = self.core.parent;
+
+        let targets: Vec<_> = ns
+            .into_iter()
+            .filter(|n| Some(*n) != parent && Some(*n) != next)
+            .collect();
+        token.messages += targets.len();
+
+        let infos = targets.iter().fold(ContinuationHandler::Done, |res, n| {
+            res.and_then(self.core.build_msg(*n, session, TraversalPayload::Info))
+        });
+        infos.and_then(self.send_to(next, session, token))
+    }
+
+    fn next(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let next = self.unvisited(ns);
+        self.send_to(next, session, token)
+    }
+
+    /// Sends the token to the given neighbour, to the parent if there
+    /// is none, or completes the traversal at the initiator.
+    fn send_to(
+        &mut self,
+        next: Option<ActorId>,
+        session: Session,
+        mut token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        match (next, self.core.parent) {
+            (Some(to), _) => {
+                self.visited.insert(to);
+                token.messages += 1;
+                self.waiting = Some((to, token.clone()));
+
+                let payload = TraversalPayload::Token(token);
+                self.core.build_msg(to, session, payload)
+            }
+            (None, Some(pid)) => self.core.forward(pid, session, token),
+            (None, None) => self.core.finish(session, token),
+        }
+    }
+
+    fn is_waiting_for(&self, aid: ActorId) -> bool {
+        matches!(&self.waiting, Some((w, _)) if *w == aid)
+    }
+}
+
+impl TraversalHandler for Cidon {
+    fn handle_start(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        self.core.initiator = true;
+        let token = Token {
+            route: vec![self.core.aid],
+            messages: 0,
+        };
+        self.inform(ns, *msg.session(), token)
+    }
+
+    fn handle_token(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+        token: &Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let sender = msg.sender().as_aid();
+        let mut token = token.clone();
+        token.route.push(self.core.aid);
+
+        if self.core.visit(sender) {
+            self.inform(ns, *msg.session(), token)
+        } else if self.is_waiting_for(sender) {
+            self.waiting = None;
+            self.next(ns, *msg.session(), token)
+        } else {
+            // The sender did not know the node was visited, its info will
+            // tell the sender to send the token on.
+            self.visited.insert(sender);
+            ContinuationHandler::Done
+        }
+    }
+
+    fn handle_info(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let sender = msg.sender().as_aid();
+        self.visited.insert(sender);
+
+        if !self.is_waiting_for(sender) {
+            return ContinuationHandler::Done;
+        }
+
+        // The token was dropped by the sender.
+        let (_, token) = self.waiting.take().expect("the node waits for the token");
+        self.next(ns, *msg.session(), token)
+    }
+
+    fn handle_ack(
+        &mut self,
+        _msg: &Message<TraversalPayload>,
+        _ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        ContinuationHandler::Done
+    }
+}
+
+impl ProtocolHandler for Cidon {
+    type Payload = TraversalPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        TraversalPayload::dispatch(self, ns, msg)
+    }
+}
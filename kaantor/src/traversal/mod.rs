@@ -0,0 +1,215 @@
+//! Traversal algorithms.
+//!
+//! A traversal is a wave in which a single token visits the nodes one after
+//! the other, starting from the initiator and coming back to it at the end.
+//! The token records its route and the number of messages sent so far by
+//! the algorithm, and the initiator reports both once the traversal completed.
+
+mod awerbuch;
+mod cidon;
+mod tarry;
+
+pub use awerbuch::*;
+pub use cidon::*;
+pub use tarry::*;
+
+use crate::{
+    protocol::{Builder, Session},
+    ActorId, ContinuationHandler, Dispatch, PayloadDebug,
+};
+use futures::channel::mpsc::UnboundedSender;
+
+/// The token of a traversal.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Token {
+    /// The nodes visited by the token, in order.
+    pub route: Vec<ActorId>,
+    /// The number of messages sent by the algorithm, the token included.
+    pub messages: usize,
+}
+
+/// The payload of the traversal algorithms.
+#[derive(Clone, Dispatch, PayloadDebug)]
+#[payload(handler = "TraversalHandler")]
+pub enum TraversalPayload {
+    /// Makes the node the initiator.
+    Start,
+    /// The token.
+    Token(Token),
+    /// Tells a neighbour the node was visited.
+    Info,
+    /// Acknowledges an `Info` message.
+    Ack,
+}
+
+/// The outcome of a traversal, reported by the initiator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traversal {
+    /// The session of the traversal.
+    pub session: Session,
+    /// The nodes visited by the token, in order.
+    pub route: Vec<ActorId>,
+    /// The number of messages sent by the algorithm.
+    pub messages: usize,
+}
+
+/// The state shared by all the traversal algorithms.
+struct Core {
+    aid: ActorId,
+    initiator: bool,
+    parent: Option<ActorId>,
+    results: Option<UnboundedSender<Traversal>>,
+}
+
+impl Core {
+    fn new(aid: ActorId) -> Self {
+        Self {
+            aid,
+            initiator: false,
+            parent: None,
+            results: None,
+        }
+    }
+
+    /// Returns `true` if the token reached the node before.
+    fn is_visited(&self) -> bool {
+        self.initiator || self.parent.is_some()
+    }
+
+    /// Visits the node with the token received from `sender`, if it is
+    /// the first visit. Returns `true` on the first visit.
+    fn visit(&mut self, sender: ActorId) -> bool {
+        if self.is_visited() {
+            return false;
+        }
+
+        self.parent = Some(sender);
+        true
+    }
+
+    fn build_msg(
+        &self,
+        to: ActorId,
+        session: Session,
+        payload: TraversalPayload,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let msg = Builder::with_from_actor(self.aid)
+            .with_to_actor(to)
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build();
+
+        ContinuationHandler::SendToNode(to, msg)
+    }
+
+    /// Sends the token to `to`, counting the message.
+    fn forward(
+        &self,
+        to: ActorId,
+        session: Session,
+        mut token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        token.messages += 1;
+        self.build_msg(to, session, TraversalPayload::Token(token))
+    }
+
+    /// Reports the outcome of the traversal, once the token is back to the initiator.
+    fn finish(&self, session: Session, token: Token) -> ContinuationHandler<TraversalPayload> {
+        log::info!(
+            "TRAVERSAL | on {:?} | {:?} | {:?} | {} messages",
+            self.aid,
+            session,
+            token.route,
+            token.messages
+        );
+
+        if let Some(results) = &self.results {
+            let _ = results.unbounded_send(Traversal {
+                session,
+                route: token.route,
+                messages: token.messages,
+            });
+        }
+        ContinuationHandler::Done
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{
+        add_edge, graph::Topology, protocol::SessionAllocator, NodeActor, ProtocolHandler,
+    };
+    use futures::StreamExt;
+    use std::{collections::BTreeSet, time::Duration};
+
+    const EDGES: [(usize, usize); 5] = [(1, 2), (1, 3), (2, 4), (4, 5), (3, 5)];
+
+    async fn traverse<H>(build: impl Fn(ActorId, UnboundedSender<Traversal>) -> H) -> Traversal
+    where
+        H: ProtocolHandler<Payload = TraversalPayload> + Unpin + 'static,
+    {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| NodeActor::build(build(i.into(), tx.clone())))
+            .collect();
+
+        for (a, b) in EDGES {
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let msg = Builder::with_from_api()
+            .with_to_actor(1.into())
+            .with_session(SessionAllocator::for_api().next_session())
+            .with_payload(TraversalPayload::Start)
+            .with_sender(1.into())
+            .build();
+        nodes[0].do_send(msg);
+
+        let res = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The token visits all the nodes, moving along the edges,
+        // and it comes back to the initiator.
+        let topology = EDGES.iter().fold(Topology::new(), |t, (a, b)| {
+            t.with_edge((*a).into(), (*b).into())
+        });
+        let visited: BTreeSet<_> = res.route.iter().copied().collect();
+        assert_eq!(topology.nodes(), visited.into_iter().collect::<Vec<_>>());
+        assert_eq!(Some(&ActorId::from(1)), res.route.first());
+        assert_eq!(Some(&ActorId::from(1)), res.route.last());
+        for hop in res.route.windows(2) {
+            assert!(topology.neighbours(hop[0]).any(|n| n == hop[1]));
+        }
+
+        res
+    }
+
+    #[actix_rt::test]
+    async fn traversals() {
+        // Both algorithms send the token twice over every edge.
+        let res = traverse(|aid, tx| Tarry::new(aid).with_results(tx)).await;
+        assert_eq!(10, res.messages);
+        assert_eq!(11, res.route.len());
+
+        let res = traverse(|aid, tx| Tarry::new(aid).depth_first().with_results(tx)).await;
+        assert_eq!(10, res.messages);
+
+        // The token goes only over the edges of the tree, twice. Every node
+        // but the initiator sends an info to all its neighbours except the
+        // parent, and the initiator to all its neighbours, each acknowledged.
+        let res = traverse(|aid, tx| Awerbuch::new(aid).with_results(tx)).await;
+        assert_eq!(9, res.route.len());
+        assert_eq!(8 + 2 * 6, res.messages);
+
+        // Some tokens can be sent to nodes which were already visited.
+        let res = traverse(|aid, tx| Cidon::new(aid).with_results(tx)).await;
+        assert_eq!(9, res.route.len());
+        assert!(res.messages <= 4 * 5);
+    }
+}
@@ -0,0 +1,125 @@
+use super::{Core, Token, Traversal, TraversalHandler, TraversalPayload};
+use crate::{
+    protocol::{Message, Session},
+    ActorId, ContinuationHandler, Dispatch, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashSet;
+
+/// Tarry's algorithm: a node never sends the token twice to the same neighbour,
+/// and it sends the token to its parent only when there is no other option.
+pub struct Tarry {
+    core: Core,
+    sent: HashSet<ActorId>,
+    back: bool,
+}
+
+impl Tarry {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId) -> Self {
+        Self {
+            core: Core::new(aid),
+            sent: Default::default(),
+            back: false,
+        }
+    }
+
+    /// Makes the node send the token back to the sender right away when it is
+    /// visited again, if allowed, which gives the classical depth-first search.
+    pub fn depth_first(mut self) -> Self {
+        self.back = true;
+        self
+    }
+
+    /// Sends the outcome of the traversal to the channel, if the node is the initiator.
+    pub fn with_results(mut self, results: UnboundedSender<Traversal>) -> Self {
+        self.core.results = Some(results);
+        self
+    }
+
+    fn next(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+        token: Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let parent = self.core.parent;
+        let mut ns = ns.filter(|n| !self.sent.contains(n));
+
+        match ns
+            .find(|n| Some(*n) != parent)
+            .or(parent.filter(|p| !self.sent.contains(p)))
+        {
+            Some(to) => {
+                self.sent.insert(to);
+                self.core.forward(to, session, token)
+            }
+            None => self.core.finish(session, token),
+        }
+    }
+}
+
+impl TraversalHandler for Tarry {
+    fn handle_start(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        self.core.initiator = true;
+        let token = Token {
+            route: vec![self.core.aid],
+            messages: 0,
+        };
+        self.next(ns, *msg.session(), token)
+    }
+
+    fn handle_token(
+        &mut self,
+        msg: &Message<TraversalPayload>,
+        ns: impl Iterator<Item = ActorId>,
+        token: &Token,
+    ) -> ContinuationHandler<TraversalPayload> {
+        let sender = msg.sender().as_aid();
+        let first = self.core.visit(sender);
+
+        let mut token = token.clone();
+        token.route.push(self.core.aid);
+
+        if self.back && !first && Some(sender) != self.core.parent && self.sent.insert(sender) {
+            return self.core.forward(sender, *msg.session(), token);
+        }
+        self.next(ns, *msg.session(), token)
+    }
+
+    fn handle_info(
+        &mut self,
+        _msg: &Message<TraversalPayload>,
+        _ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        ContinuationHandler::Done
+    }
+
+    fn handle_ack(
+        &mut self,
+        _msg: &Message<TraversalPayload>,
+        _ns: impl Iterator<Item = ActorId>,
+    ) -> ContinuationHandler<TraversalPayload> {
+        ContinuationHandler::Done
+    }
+}
+
+impl ProtocolHandler for Tarry {
+    type Payload = TraversalPayload;
+
+    fn aid(&self) -> ActorId {
+        self.core.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        TraversalPayload::dispatch(self, ns, msg)
+    }
+}
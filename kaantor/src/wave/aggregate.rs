@@ -0,0 +1,350 @@
+use crate::{
+    layer::Service,
+    protocol::{Builder, Message, Session},
+    ActorId, ContinuationHandler, PayloadDebug, ProtocolHandler,
+};
+use futures::channel::mpsc::UnboundedSender;
+use std::{fmt::Debug, ops::Add};
+
+/// Combines the values of the nodes into a global result.
+pub trait Fold {
+    /// The partial result of a subtree.
+    type Acc: Clone + Debug + Send + Unpin;
+
+    /// Returns the partial result of a node on its own.
+    fn init(&self, aid: ActorId) -> Self::Acc;
+
+    /// Combines the partial results of two disjoint sets of nodes.
+    fn combine(&self, acc: Self::Acc, other: Self::Acc) -> Self::Acc;
+}
+
+/// Sums the values of the nodes.
+pub struct Sum<F>(pub F);
+
+impl<F, T> Fold for Sum<F>
+where
+    F: Fn(ActorId) -> T,
+    T: Add<Output = T> + Clone + Debug + Send + Unpin,
+{
+    type Acc = T;
+
+    fn init(&self, aid: ActorId) -> T {
+        (self.0)(aid)
+    }
+
+    fn combine(&self, acc: T, other: T) -> T {
+        acc + other
+    }
+}
+
+/// Finds the smallest value of the nodes.
+pub struct Min<F>(pub F);
+
+impl<F, T> Fold for Min<F>
+where
+    F: Fn(ActorId) -> T,
+    T: Ord + Clone + Debug + Send + Unpin,
+{
+    type Acc = T;
+
+    fn init(&self, aid: ActorId) -> T {
+        (self.0)(aid)
+    }
+
+    fn combine(&self, acc: T, other: T) -> T {
+        acc.min(other)
+    }
+}
+
+/// Finds the largest value of the nodes.
+pub struct Max<F>(pub F);
+
+impl<F, T> Fold for Max<F>
+where
+    F: Fn(ActorId) -> T,
+    T: Ord + Clone + Debug + Send + Unpin,
+{
+    type Acc = T;
+
+    fn init(&self, aid: ActorId) -> T {
+        (self.0)(aid)
+    }
+
+    fn combine(&self, acc: T, other: T) -> T {
+        acc.max(other)
+    }
+}
+
+/// Counts the nodes.
+pub struct Count;
+
+impl Fold for Count {
+    type Acc = usize;
+
+    fn init(&self, _aid: ActorId) -> usize {
+        1
+    }
+
+    fn combine(&self, acc: usize, other: usize) -> usize {
+        acc + other
+    }
+}
+
+/// Collects the values of the nodes, in no particular order.
+pub struct Collect<F>(pub F);
+
+impl<F, T> Fold for Collect<F>
+where
+    F: Fn(ActorId) -> T,
+    T: Clone + Debug + Send + Unpin,
+{
+    type Acc = Vec<T>;
+
+    fn init(&self, aid: ActorId) -> Vec<T> {
+        vec![(self.0)(aid)]
+    }
+
+    fn combine(&self, mut acc: Vec<T>, other: Vec<T>) -> Vec<T> {
+        acc.extend(other);
+        acc
+    }
+}
+
+/// The payload of the `Aggregate` algorithm.
+#[derive(Clone, PayloadDebug)]
+pub enum AggregatePayload<A> {
+    /// Makes the node the initiator.
+    Start,
+    /// Propagates the computation.
+    Go,
+    /// Sends the partial result of a subtree to the parent.
+    Back(A),
+}
+
+/// The echo algorithm, computing a global result at the initiator. The `Go`
+/// messages propagate from the initiator and build a spanning tree, and every
+/// node sends back to its parent the partial result of its subtree, combined
+/// with the fold. The initiator gets the result once all its neighbours answered.
+///
+/// The computation is started either by sending `AggregatePayload::Start` to the
+/// initiator, or by a request when the handler is used as a `Service`.
+pub struct Aggregate<F>
+where
+    F: Fold,
+{
+    aid: ActorId,
+    fold: F,
+    initiator: bool,
+    parent: Option<ActorId>,
+    pending: usize,
+    acc: Option<F::Acc>,
+    result: Option<F::Acc>,
+    results: Option<UnboundedSender<(Session, F::Acc)>>,
+    finished: bool,
+}
+
+impl<F> Aggregate<F>
+where
+    F: Fold,
+{
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId, fold: F) -> Self {
+        Self {
+            aid,
+            fold,
+            initiator: false,
+            parent: None,
+            pending: 0,
+            acc: None,
+            result: None,
+            results: None,
+            finished: false,
+        }
+    }
+
+    /// Sends the result, with its session, to the channel once the
+    /// computation completed, if the node is the initiator.
+    pub fn with_results(mut self, results: UnboundedSender<(Session, F::Acc)>) -> Self {
+        self.results = Some(results);
+        self
+    }
+
+    fn build_msg(
+        &self,
+        session: Session,
+        payload: AggregatePayload<F::Acc>,
+    ) -> Message<AggregatePayload<F::Acc>> {
+        Builder::with_from_actor(self.aid)
+            .with_to_all_actors()
+            .with_session(session)
+            .with_payload(payload)
+            .with_sender(self.aid)
+            .build()
+    }
+
+    fn combine(&mut self, other: F::Acc) {
+        self.acc = match self.acc.take() {
+            Some(acc) => Some(self.fold.combine(acc, other)),
+            None => Some(other),
+        };
+    }
+
+    fn start(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        session: Session,
+    ) -> ContinuationHandler<AggregatePayload<F::Acc>> {
+        self.initiator = true;
+        self.pending = ns.count();
+        self.acc = Some(self.fold.init(self.aid));
+
+        let go = self.build_msg(session, AggregatePayload::Go);
+        ContinuationHandler::SendToAllNodes(go).and_then(self.check_done(session))
+    }
+
+    fn check_done(&mut self, session: Session) -> ContinuationHandler<AggregatePayload<F::Acc>> {
+        if self.pending != 0 || self.finished {
+            return ContinuationHandler::Done;
+        }
+
+        self.finished = true;
+        let acc = self.acc.take().expect("the node has a partial result");
+
+        match self.parent {
+            Some(pid) => {
+                let back = self.build_msg(session, AggregatePayload::Back(acc));
+                ContinuationHandler::SendToNode(pid, back)
+            }
+            None => {
+                log::info!("AGGREGATE | on {:?} | {:?} | {:?}", self.aid, session, acc);
+                if let Some(results) = &self.results {
+                    let _ = results.unbounded_send((session, acc.clone()));
+                }
+                self.result = Some(acc);
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+impl<F> ProtocolHandler for Aggregate<F>
+where
+    F: Fold,
+{
+    type Payload = AggregatePayload<F::Acc>;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        let sender = msg.sender().as_aid();
+
+        match msg.payload {
+            AggregatePayload::Start => self.start(ns, session),
+            AggregatePayload::Go if !self.initiator && self.parent.is_none() => {
+                self.parent = Some(sender);
+                self.pending = ns.count() - 1;
+                self.acc = Some(self.fold.init(self.aid));
+
+                let go = self.build_msg(session, AggregatePayload::Go);
+                ContinuationHandler::SendToAllNodesExcept(go, vec![sender])
+                    .and_then(self.check_done(session))
+            }
+            AggregatePayload::Go => {
+                self.pending -= 1;
+                self.check_done(session)
+            }
+            AggregatePayload::Back(acc) => {
+                self.combine(acc);
+                self.pending -= 1;
+                self.check_done(session)
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl<F> Service for Aggregate<F>
+where
+    F: Fold,
+{
+    type Request = ();
+    type Response = F::Acc;
+
+    fn request(
+        &mut self,
+        neighbours: impl Iterator<Item = ActorId>,
+        session: Session,
+        _req: (),
+    ) -> ContinuationHandler<Self::Payload> {
+        self.start(neighbours, session)
+    }
+
+    fn response(&mut self) -> Option<Self::Response> {
+        self.result.take()
+    }
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, NodeActor};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    async fn aggregate<F>(fold: impl Fn() -> F) -> F::Acc
+    where
+        F: Fold + Unpin + 'static,
+    {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| NodeActor::build(Aggregate::new(i.into(), fold()).with_results(tx.clone())))
+            .collect();
+
+        for (a, b) in [(0, 1), (0, 2), (1, 3), (3, 4), (2, 4)] {
+            let (left, right) = nodes.split_at_mut(b);
+            add_edge(&mut left[a], &mut right[0]).await;
+        }
+
+        let started = SessionAllocator::for_api().next_session();
+        let aid = nodes[2].aid();
+        let msg = Builder::with_from_api()
+            .with_to_actor(aid)
+            .with_session(started)
+            .with_payload(AggregatePayload::Start)
+            .with_sender(aid)
+            .build();
+        nodes[2].do_send(msg);
+
+        let (session, acc) = actix_rt::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(started, session);
+        acc
+    }
+
+    #[actix_rt::test]
+    async fn folds() {
+        let value = |aid: ActorId| aid.inner() * 10;
+
+        assert_eq!(150, aggregate(|| Sum(value)).await);
+        assert_eq!(10, aggregate(|| Min(value)).await);
+        assert_eq!(50, aggregate(|| Max(value)).await);
+        assert_eq!(5, aggregate(|| Count).await);
+
+        let mut all = aggregate(|| Collect(value)).await;
+        all.sort();
+        assert_eq!(vec![10, 20, 30, 40, 50], all);
+    }
+}
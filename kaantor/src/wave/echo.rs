@@ -0,0 +1,89 @@
+use super::{decide, token, Wave, WavePayload};
+use crate::{protocol::Message, trace::Trace, ActorId, ContinuationHandler, ProtocolHandler};
+
+/// The echo algorithm: the initiator floods the token, which builds a spanning
+/// tree, and the tokens are sent back to the parents once a node received the
+/// token from all its neighbours. The initiator decides at the end.
+pub struct Echo {
+    aid: ActorId,
+    trace: Trace,
+    initiator: bool,
+    parent: Option<ActorId>,
+    pending: usize,
+    decided: bool,
+}
+
+impl Echo {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId, trace: Trace) -> Self {
+        Self {
+            aid,
+            trace,
+            initiator: false,
+            parent: None,
+            pending: 0,
+            decided: false,
+        }
+    }
+
+    fn check_done(&mut self, msg: &Message<WavePayload>) -> ContinuationHandler<WavePayload> {
+        if self.pending != 0 {
+            return ContinuationHandler::Done;
+        }
+
+        match self.parent {
+            Some(pid) => ContinuationHandler::SendToNode(pid, token(self.aid, *msg.session())),
+            None => {
+                self.decided = true;
+                decide(&self.trace, self.aid, *msg.session());
+                ContinuationHandler::Done
+            }
+        }
+    }
+}
+
+impl ProtocolHandler for Echo {
+    type Payload = WavePayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+
+        match msg.payload() {
+            WavePayload::Start => {
+                self.initiator = true;
+                self.pending = ns.count();
+                ContinuationHandler::SendToAllNodes(token(self.aid, session))
+                    .and_then(self.check_done(&msg))
+            }
+            WavePayload::Token if !self.initiator && self.parent.is_none() => {
+                let sender = msg.sender().as_aid();
+                self.parent = Some(sender);
+                self.pending = ns.count() - 1;
+                if self.pending == 0 {
+                    self.check_done(&msg)
+                } else {
+                    let tkn = token(self.aid, session);
+                    ContinuationHandler::SendToAllNodesExcept(tkn, vec![sender])
+                }
+            }
+            WavePayload::Token => {
+                self.pending -= 1;
+                self.check_done(&msg)
+            }
+        }
+    }
+}
+
+impl Wave for Echo {
+    fn has_decided(&self) -> bool {
+        self.decided
+    }
+}
@@ -0,0 +1,129 @@
+//! Wave algorithms.
+//!
+//! A wave is a computation started by one or more initiators in which some
+//! nodes eventually take a decide event, and every decide event is causally
+//! preceded by an event on each node of the graph. Waves are the building
+//! blocks of many algorithms, e.g. to propagate or collect information,
+//! to synchronize the nodes or to detect termination.
+//!
+//! The nodes record their decide events in a `Trace`, which can be checked
+//! with `trace::check_decide_events` when the nodes are built traced.
+
+mod aggregate;
+mod echo;
+mod phase;
+mod tree;
+
+pub use aggregate::*;
+pub use echo::*;
+pub use phase::*;
+pub use tree::*;
+
+use crate::{
+    protocol::{Builder, Message, Session},
+    trace::{Event, Trace},
+    ActorId, PayloadDebug, ProtocolHandler,
+};
+
+/// The payload of the wave algorithms.
+#[derive(Clone, PayloadDebug)]
+pub enum WavePayload {
+    /// Makes the node an initiator.
+    Start,
+    /// The message exchanged by the nodes.
+    Token,
+}
+
+/// A wave algorithm, started by sending `WavePayload::Start` to its initiators.
+pub trait Wave: ProtocolHandler<Payload = WavePayload> {
+    /// Returns `true` once the node took its decide event.
+    fn has_decided(&self) -> bool;
+}
+
+fn token(aid: ActorId, session: Session) -> Message<WavePayload> {
+    Builder::with_from_actor(aid)
+        .with_to_all_actors()
+        .with_session(session)
+        .with_payload(WavePayload::Token)
+        .with_sender(aid)
+        .build()
+}
+
+fn decide(trace: &Trace, aid: ActorId, session: Session) {
+    log::info!("DECIDE | on {:?} | {:?}", aid, session);
+    trace.record(Event::Decide { aid, session });
+}
+
+#[cfg(test)]
+mod utests {
+    use super::*;
+    use crate::{add_edge, protocol::SessionAllocator, trace::check_decide_events, NodeActor};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    async fn run_wave<W, F>(
+        build: F,
+        edges: &[(usize, usize)],
+        initiators: &[usize],
+        deciders: usize,
+    ) -> Vec<Event>
+    where
+        W: Wave + Unpin + 'static,
+        F: Fn(ActorId, Trace) -> W,
+    {
+        let trace = Trace::new();
+        let mut events = trace.subscribe();
+
+        let mut nodes: Vec<_> = (1..=5)
+            .map(|i| NodeActor::build_traced(build(i.into(), trace.clone()), trace.clone()))
+            .collect();
+
+        for &(a, b) in edges {
+            let (left, right) = nodes.split_at_mut(b - 1);
+            add_edge(&mut left[a - 1], &mut right[0]).await;
+        }
+
+        let session = SessionAllocator::for_api().next_session();
+        for &i in initiators {
+            let aid = nodes[i - 1].aid();
+            let msg = Builder::with_from_api()
+                .with_to_actor(aid)
+                .with_session(session)
+                .with_payload(WavePayload::Start)
+                .with_sender(aid)
+                .build();
+            nodes[i - 1].do_send(msg);
+        }
+
+        let mut decided = 0;
+        while decided < deciders {
+            let event = actix_rt::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .unwrap()
+                .unwrap();
+            if matches!(event, Event::Decide { .. }) {
+                decided += 1;
+            }
+        }
+
+        trace.events()
+    }
+
+    const GRAPH: [(usize, usize); 5] = [(1, 2), (1, 3), (2, 4), (4, 5), (3, 5)];
+    const TREE: [(usize, usize); 4] = [(1, 2), (1, 3), (2, 4), (2, 5)];
+
+    #[actix_rt::test]
+    async fn waves() {
+        let nodes: Vec<_> = (1..=5).map(ActorId::from).collect();
+
+        let events = run_wave(Echo::new, &GRAPH, &[1], 1).await;
+        assert_eq!(Ok(1), check_decide_events(&events, &nodes));
+
+        let events = run_wave(Tree::new, &TREE, &[3, 4, 5], 2).await;
+        assert_eq!(Ok(2), check_decide_events(&events, &nodes));
+
+        let phase = |aid, trace| Phase::new(aid, 3, trace);
+        let events = run_wave(phase, &GRAPH, &[1, 5], 5).await;
+        assert_eq!(Ok(5), check_decide_events(&events, &nodes));
+    }
+}
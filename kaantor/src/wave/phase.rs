@@ -0,0 +1,78 @@
+use super::{decide, token, Wave, WavePayload};
+use crate::{protocol::Message, trace::Trace, ActorId, ContinuationHandler, ProtocolHandler};
+use std::collections::HashMap;
+
+/// The phase algorithm, for graphs with a known upper bound of their diameter.
+/// Every node sends the token to its neighbours as many times as the diameter:
+/// the first time when it becomes active and the next times once it received
+/// as many tokens from each neighbour as it sent. A node decides when it
+/// received the token from each neighbour as many times as the diameter.
+pub struct Phase {
+    aid: ActorId,
+    trace: Trace,
+    diameter: usize,
+    sent: usize,
+    received: HashMap<ActorId, usize>,
+    decided: bool,
+}
+
+impl Phase {
+    /// Creates the handler for the node `aid`, in a graph whose diameter is
+    /// at most `diameter`.
+    pub fn new(aid: ActorId, diameter: usize, trace: Trace) -> Self {
+        Self {
+            aid,
+            trace,
+            diameter,
+            sent: 0,
+            received: Default::default(),
+            decided: false,
+        }
+    }
+}
+
+impl ProtocolHandler for Phase {
+    type Payload = WavePayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        if let WavePayload::Token = msg.payload() {
+            *self.received.entry(msg.sender().as_aid()).or_default() += 1;
+        }
+
+        // The number of tokens received from every neighbour.
+        let rounds = ns
+            .map(|n| self.received.get(&n).copied().unwrap_or_default())
+            .min()
+            .unwrap_or(self.diameter);
+
+        let mut res = ContinuationHandler::Done;
+        while self.sent < self.diameter && self.sent <= rounds {
+            self.sent += 1;
+            res = res.and_then(ContinuationHandler::SendToAllNodes(token(
+                self.aid, session,
+            )));
+        }
+
+        if !self.decided && rounds >= self.diameter {
+            self.decided = true;
+            decide(&self.trace, self.aid, session);
+        }
+
+        res
+    }
+}
+
+impl Wave for Phase {
+    fn has_decided(&self) -> bool {
+        self.decided
+    }
+}
@@ -0,0 +1,71 @@
+use super::{decide, token, Wave, WavePayload};
+use crate::{protocol::Message, trace::Trace, ActorId, ContinuationHandler, ProtocolHandler};
+use std::collections::HashSet;
+
+/// The tree algorithm, for graphs which are trees. A node sends the token to
+/// its last neighbour once it received the token from all the others, and it
+/// decides when it received the token from all its neighbours. Exactly two
+/// neighbouring nodes decide. All the leaves must be initiators.
+pub struct Tree {
+    aid: ActorId,
+    trace: Trace,
+    received: HashSet<ActorId>,
+    sent: Option<ActorId>,
+    decided: bool,
+}
+
+impl Tree {
+    /// Creates the handler for the node `aid`.
+    pub fn new(aid: ActorId, trace: Trace) -> Self {
+        Self {
+            aid,
+            trace,
+            received: Default::default(),
+            sent: None,
+            decided: false,
+        }
+    }
+}
+
+impl ProtocolHandler for Tree {
+    type Payload = WavePayload;
+
+    fn aid(&self) -> ActorId {
+        self.aid
+    }
+
+    fn receive(
+        &mut self,
+        ns: impl Iterator<Item = ActorId>,
+        msg: Message<Self::Payload>,
+    ) -> ContinuationHandler<Self::Payload> {
+        let session = *msg.session();
+        if let WavePayload::Token = msg.payload() {
+            self.received.insert(msg.sender().as_aid());
+        }
+
+        let ns: Vec<_> = ns.collect();
+        let mut res = ContinuationHandler::Done;
+
+        if self.sent.is_none() {
+            let mut rest = ns.iter().filter(|n| !self.received.contains(n));
+            if let (Some(&last), None) = (rest.next(), rest.next()) {
+                self.sent = Some(last);
+                res = ContinuationHandler::SendToNode(last, token(self.aid, session));
+            }
+        }
+
+        if !self.decided && self.received.len() == ns.len() {
+            self.decided = true;
+            decide(&self.trace, self.aid, session);
+        }
+
+        res
+    }
+}
+
+impl Wave for Tree {
+    fn has_decided(&self) -> bool {
+        self.decided
+    }
+}